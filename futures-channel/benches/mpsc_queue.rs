@@ -0,0 +1,128 @@
+//! Benchmarks for the message queue behind the mpsc channels.
+//!
+//! Besides the time per iteration, every benchmark prints the number of
+//! allocations made per message (pass `--nocapture` to see it). Running them
+//! on an older revision compares the block-based queue with the previous
+//! design, which allocated a node for every message.
+
+#![feature(test)]
+
+extern crate test;
+use crate::test::Bencher;
+
+use {
+    futures::{channel::mpsc, executor::block_on, future, stream::StreamExt, task::Poll},
+    futures_test::task::noop_context,
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    },
+};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Runs the benchmark, then prints the allocations per message made by it.
+fn bench_messages(b: &mut Bencher, name: &str, messages: usize, mut f: impl FnMut()) {
+    let mut iterations = 0;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    b.iter(|| {
+        iterations += 1;
+        f()
+    });
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{}: {:.3} allocations per message",
+        name,
+        allocations as f64 / (iterations * messages) as f64
+    );
+}
+
+/// Messages go through one at a time, so the queue never holds more than one.
+#[bench]
+fn unbounded_one_at_a_time(b: &mut Bencher) {
+    let mut cx = noop_context();
+    let (tx, mut rx) = mpsc::unbounded();
+
+    bench_messages(b, "unbounded_one_at_a_time", 1000, || {
+        for i in 0..1000 {
+            tx.unbounded_send(i).unwrap();
+            assert_eq!(Poll::Ready(Some(i)), rx.poll_next_unpin(&mut cx));
+        }
+    })
+}
+
+/// All messages are queued before any is received.
+#[bench]
+fn unbounded_burst(b: &mut Bencher) {
+    let mut cx = noop_context();
+    let (tx, mut rx) = mpsc::unbounded();
+
+    bench_messages(b, "unbounded_burst", 1000, || {
+        for i in 0..1000 {
+            tx.unbounded_send(i).unwrap();
+        }
+        for i in 0..1000 {
+            assert_eq!(Poll::Ready(Some(i)), rx.poll_next_unpin(&mut cx));
+        }
+    })
+}
+
+/// A bounded channel which is filled up and drained again.
+#[bench]
+fn bounded_fill_and_drain(b: &mut Bencher) {
+    let mut cx = noop_context();
+    let (mut tx, mut rx) = mpsc::channel(100);
+
+    bench_messages(b, "bounded_fill_and_drain", 1000, || {
+        for round in 0..10 {
+            for i in 0..100 {
+                tx.try_send(round * 100 + i).unwrap();
+            }
+            for i in 0..100 {
+                assert_eq!(Poll::Ready(Some(round * 100 + i)), rx.poll_next_unpin(&mut cx));
+            }
+        }
+    })
+}
+
+/// Four threads sending to one receiver at the same time.
+#[bench]
+fn unbounded_4_threads(b: &mut Bencher) {
+    bench_messages(b, "unbounded_4_threads", 4000, || {
+        let (tx, rx) = mpsc::unbounded();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        tx.unbounded_send(i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        assert_eq!(block_on(rx.fold(0, |n, _| future::ready(n + 1))), 4000);
+        for t in threads {
+            t.join().unwrap();
+        }
+    })
+}
@@ -1,5 +1,6 @@
 #![warn(rust_2018_idioms, single_use_lifetimes)]
 
+use autocfg::AutoCfg;
 use std::env;
 
 include!("no_atomic_cas.rs");
@@ -38,5 +39,24 @@ fn main() {
         println!("cargo:rustc-cfg=futures_no_atomic_cas");
     }
 
+    let cfg = match AutoCfg::new() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            println!(
+                "cargo:warning={}: unable to determine rustc version: {}",
+                env!("CARGO_PKG_NAME"),
+                e
+            );
+            return;
+        }
+    };
+
+    // Const generics stabilized in Rust 1.51:
+    // https://blog.rust-lang.org/2021/03/25/Rust-1.51.0.html#const-generics-mvp
+    println!("cargo:rustc-check-cfg=cfg(futures_const_generics)");
+    if cfg.probe_rustc_version(1, 51) {
+        println!("cargo:rustc-cfg=futures_const_generics");
+    }
+
     println!("cargo:rerun-if-changed=no_atomic_cas.rs");
 }
@@ -0,0 +1,70 @@
+//! Blocking the current thread on a channel operation.
+//!
+//! This backs the `*_blocking` methods of the channels. Unlike
+//! `futures_executor::block_on`, it does not enter an executor context, so it
+//! can be used from threads which do not run an executor as well as from ones
+//! which do.
+
+use core::mem::{self, ManuallyDrop};
+use futures_core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, Thread};
+
+// Wakes up the thread which is blocked on a channel.
+struct ThreadNotify {
+    thread: Thread,
+
+    // Set by a wakeup, so that spurious unparks of the thread can be told
+    // apart from actual ones.
+    unparked: AtomicBool,
+}
+
+impl ThreadNotify {
+    fn notify(&self) {
+        self.unparked.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_raw, wake_raw, wake_by_ref_raw, drop_raw);
+
+unsafe fn clone_raw(ptr: *const ()) -> RawWaker {
+    let notify = ManuallyDrop::new(Arc::from_raw(ptr as *const ThreadNotify));
+    let notify = Arc::clone(&notify);
+    RawWaker::new(Arc::into_raw(notify) as *const (), &VTABLE)
+}
+
+unsafe fn wake_raw(ptr: *const ()) {
+    let notify = Arc::from_raw(ptr as *const ThreadNotify);
+    notify.notify();
+}
+
+unsafe fn wake_by_ref_raw(ptr: *const ()) {
+    let notify = ManuallyDrop::new(Arc::from_raw(ptr as *const ThreadNotify));
+    notify.notify();
+}
+
+unsafe fn drop_raw(ptr: *const ()) {
+    mem::drop(Arc::from_raw(ptr as *const ThreadNotify));
+}
+
+/// Polls `f` until it is ready, parking the current thread while it is not.
+pub(crate) fn block_on<T>(mut f: impl FnMut(&mut Context<'_>) -> Poll<T>) -> T {
+    let notify =
+        Arc::new(ThreadNotify { thread: thread::current(), unparked: AtomicBool::new(false) });
+    let raw = RawWaker::new(Arc::into_raw(notify.clone()) as *const (), &VTABLE);
+    let waker = unsafe { Waker::from_raw(raw) };
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(t) = f(&mut cx) {
+            return t;
+        }
+
+        // Wait until the channel wakes us up, ignoring spurious unparks.
+        while !notify.unparked.swap(false, Ordering::Acquire) {
+            thread::park();
+        }
+    }
+}
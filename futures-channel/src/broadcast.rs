@@ -0,0 +1,525 @@
+//! A multi-producer, multi-consumer broadcast queue where every receiver
+//! observes every message.
+//!
+//! Channel creation provides a [`Sender`] and a [`Receiver`] handle. Each
+//! message sent through any [`Sender`] is cloned out to every [`Receiver`]
+//! that exists at the time it was sent. Additional receivers can be created
+//! at any time with [`Sender::subscribe`]; they only observe messages sent
+//! after they subscribed.
+//!
+//! # Lagging
+//!
+//! Messages are kept in a ring buffer of the capacity given to [`channel`].
+//! Sending never waits: once the buffer is full, sending a new message
+//! overwrites the oldest one. A receiver that has not yet observed the
+//! overwritten messages is said to have *lagged*, and its next poll yields a
+//! [`RecvError`] reporting how many messages it missed. The receiver then
+//! continues with the oldest message still held in the buffer.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, or the channel has been
+//! closed with [`Receiver::close`] or [`Sender::close_channel`], it is no
+//! longer possible to send values into the channel. Receivers still observe
+//! the messages buffered before that point, after which their stream
+//! terminates.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`Sender::subscribe`]: struct.Sender.html#method.subscribe
+//! [`Sender::close_channel`]: struct.Sender.html#method.close_channel
+//! [`Receiver::close`]: struct.Receiver.html#method.close
+//! [`RecvError`]: struct.RecvError.html
+//! [`channel`]: fn.channel.html
+
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// The transmission end of a broadcast channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: Option<Arc<Inner<T>>>,
+}
+
+/// The receiving end of a broadcast channel.
+///
+/// This value is created by the [`channel`](channel) function or by
+/// [`Sender::subscribe`](Sender::subscribe).
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Option<Arc<Inner<T>>>,
+
+    // Position of the next message this receiver will observe.
+    pos: u64,
+
+    // Index of this receiver's slot in `State::recv_tasks`.
+    key: usize,
+}
+
+// Neither half ever projects `Pin` to the inner `T`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+/// The error type returned from [`send`](Sender::send).
+#[derive(Clone, PartialEq, Eq)]
+pub struct SendError<T> {
+    val: T,
+}
+
+/// The error yielded by a [`Receiver`](Receiver) which fell behind and missed
+/// some messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvError {
+    skipped: u64,
+}
+
+/// The error type returned from [`try_next`](Receiver::try_next).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TryRecvError {
+    skipped: Option<u64>,
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "send failed because the channel is closed or has no receivers")
+    }
+}
+
+impl<T: core::any::Any> std::error::Error for SendError<T> {}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "receiver lagged behind by {} messages", self.skipped)
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+impl RecvError {
+    /// Returns the number of messages the receiver missed because they were
+    /// overwritten before it could observe them.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl fmt::Debug for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TryRecvError").field("skipped", &self.skipped).finish()
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.skipped {
+            Some(skipped) => write!(f, "receiver lagged behind by {} messages", skipped),
+            None => write!(f, "receiver channel is empty"),
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
+
+impl TryRecvError {
+    /// Returns `true` if this error is a result of the channel being empty.
+    pub fn is_empty(&self) -> bool {
+        self.skipped.is_none()
+    }
+
+    /// Returns `true` if this error is a result of the receiver having
+    /// missed some messages.
+    pub fn is_lagged(&self) -> bool {
+        self.skipped.is_some()
+    }
+
+    /// Returns the number of missed messages if the receiver lagged behind.
+    pub fn skipped(&self) -> Option<u64> {
+        self.skipped
+    }
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // Maximum number of messages retained in the ring buffer.
+    capacity: usize,
+
+    // Channel state shared between all senders and receivers.
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // Ring buffer of the most recently sent messages.
+    buffer: VecDeque<T>,
+
+    // Position of the oldest message in `buffer`. Positions increase by one
+    // for every message sent, so the position of the next message to be sent
+    // is `head + buffer.len()`.
+    head: u64,
+
+    // `true` when the channel is open
+    is_open: bool,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Number of receivers in existence
+    num_receivers: usize,
+
+    // Handles to the receivers' tasks, indexed by `Receiver::key`.
+    recv_tasks: Vec<Option<Waker>>,
+
+    // Indices of `recv_tasks` left behind by dropped receivers.
+    free_keys: Vec<usize>,
+}
+
+/// Creates a bounded broadcast channel for communicating between
+/// asynchronous tasks.
+///
+/// The channel retains at most `capacity` messages. Sending into a full
+/// channel overwrites the oldest message, and receivers which had not yet
+/// observed it will report a [`RecvError`](RecvError) on their next poll.
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait. More receivers can be
+/// created with [`Sender::subscribe`](Sender::subscribe).
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::broadcast;
+/// use futures::executor::block_on;
+/// use futures::stream::StreamExt;
+///
+/// let (tx, mut rx1) = broadcast::channel(16);
+/// let mut rx2 = tx.subscribe();
+///
+/// tx.send(10).unwrap();
+/// tx.send(20).unwrap();
+/// drop(tx);
+///
+/// block_on(async {
+///     assert_eq!(rx1.next().await, Some(Ok(10)));
+///     assert_eq!(rx1.next().await, Some(Ok(20)));
+///     assert_eq!(rx1.next().await, None);
+///
+///     assert_eq!(rx2.next().await, Some(Ok(10)));
+///     assert_eq!(rx2.next().await, Some(Ok(20)));
+///     assert_eq!(rx2.next().await, None);
+/// });
+/// ```
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be greater than zero");
+
+    let inner = Arc::new(Inner {
+        capacity,
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            head: 0,
+            is_open: true,
+            num_senders: 1,
+            num_receivers: 0,
+            recv_tasks: Vec::new(),
+            free_keys: Vec::new(),
+        }),
+    });
+
+    let rx = Receiver::new(inner.clone());
+
+    (Sender { inner: Some(inner) }, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Sends a message to every receiver currently subscribed to the channel.
+    ///
+    /// This never waits for the receivers: if the channel is at capacity, the
+    /// oldest message is overwritten. An error containing the message is
+    /// returned if the channel has been closed or there are no receivers.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return Err(SendError { val: msg }),
+        };
+        let mut state = inner.state.lock().unwrap();
+
+        if !state.is_open || state.num_receivers == 0 {
+            return Err(SendError { val: msg });
+        }
+
+        if state.buffer.len() == inner.capacity {
+            state.buffer.pop_front();
+            state.head += 1;
+        }
+        state.buffer.push_back(msg);
+
+        state.wake_all();
+        Ok(())
+    }
+
+    /// Creates a new [`Receiver`](Receiver) which observes every message sent
+    /// after this call.
+    ///
+    /// If this sender has been disconnected, the returned receiver is already
+    /// terminated.
+    pub fn subscribe(&self) -> Receiver<T> {
+        match &self.inner {
+            Some(inner) => Receiver::new(inner.clone()),
+            None => Receiver { inner: None, pos: 0, key: 0 },
+        }
+    }
+
+    /// Returns the number of receivers currently subscribed to the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.inner.as_ref().map(|inner| inner.state.lock().unwrap().num_receivers).unwrap_or(0)
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        self.inner.as_ref().map(|inner| !inner.state.lock().unwrap().is_open).unwrap_or(true)
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&self) {
+        if let Some(inner) = &self.inner {
+            inner.set_closed();
+        }
+    }
+
+    /// Disconnects this sender from the channel, closing it if there are no more senders left.
+    pub fn disconnect(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.drop_sender();
+        }
+    }
+
+    /// Returns whether the senders send to the same receivers.
+    pub fn same_receiver(&self, other: &Self) -> bool {
+        match (&self.inner, &other.inner) {
+            (Some(inner), Some(other)) => Arc::ptr_eq(inner, other),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the sender sends to this receiver.
+    pub fn is_connected_to(&self, receiver: &Receiver<T>) -> bool {
+        match (&self.inner, &receiver.inner) {
+            (Some(inner), Some(receiver)) => Arc::ptr_eq(inner, receiver),
+            _ => false,
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        if let Some(inner) = &self.inner {
+            inner.state.lock().unwrap().num_senders += 1;
+        }
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    fn new(inner: Arc<Inner<T>>) -> Self {
+        let (pos, key) = {
+            let mut state = inner.state.lock().unwrap();
+            state.num_receivers += 1;
+            let key = match state.free_keys.pop() {
+                Some(key) => key,
+                None => {
+                    state.recv_tasks.push(None);
+                    state.recv_tasks.len() - 1
+                }
+            };
+            (state.head + state.buffer.len() as u64, key)
+        };
+
+        Self { inner: Some(inner), pos, key }
+    }
+
+    /// Closes the channel for all senders and receivers, without dropping
+    /// this receiver.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling every receiver to drain the messages that are buffered.
+    pub fn close(&mut self) {
+        if let Some(inner) = &self.inner {
+            inner.set_closed();
+        }
+    }
+
+    // Release this receiver's slot, leaving the receiver terminated.
+    fn unsubscribe(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let mut state = inner.state.lock().unwrap();
+            state.recv_tasks[self.key] = None;
+            state.free_keys.push(self.key);
+            state.num_receivers -= 1;
+
+            // Nobody is left to observe the buffered messages, and new
+            // receivers start after them, so release them right away.
+            if state.num_receivers == 0 {
+                state.head += state.buffer.len() as u64;
+                state.buffer.clear();
+            }
+        }
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    ///
+    /// This function returns:
+    /// * `Ok(Some(t))` when message is fetched
+    /// * `Ok(None)` when channel is closed and no messages left in the queue
+    /// * `Err(e)` when there are no messages available, but channel is not yet
+    ///   closed, or when the receiver lagged behind and missed some messages
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(Some(Ok(msg))) => Ok(Some(msg)),
+            Poll::Ready(Some(Err(e))) => Err(TryRecvError { skipped: Some(e.skipped) }),
+            Poll::Ready(None) => Ok(None),
+            Poll::Pending => Err(TryRecvError { skipped: None }),
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut Context<'_>>) -> Poll<Option<Result<T, RecvError>>> {
+        let inner = match &self.inner {
+            None => return Poll::Ready(None),
+            Some(inner) => inner,
+        };
+        let mut state = inner.state.lock().unwrap();
+
+        // The messages this receiver was about to observe have been
+        // overwritten, so skip ahead to the oldest one still buffered.
+        if self.pos < state.head {
+            let skipped = state.head - self.pos;
+            self.pos = state.head;
+            return Poll::Ready(Some(Err(RecvError { skipped })));
+        }
+
+        if let Some(msg) = state.buffer.get((self.pos - state.head) as usize) {
+            let msg = msg.clone();
+            self.pos += 1;
+            return Poll::Ready(Some(Ok(msg)));
+        }
+
+        if !state.is_open {
+            // If the channel is closed AND there are no pending messages it
+            // means end of stream
+            drop(state);
+            self.unsubscribe();
+            return Poll::Ready(None);
+        }
+
+        // The state lock is held, so no message can be sent between the checks
+        // above and registering the task here.
+        if let Some(cx) = cx {
+            let task = &mut state.recv_tasks[self.key];
+            match task {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => *task = Some(cx.waker().clone()),
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<T: Clone> FusedStream for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.next_message(Some(cx))
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.unsubscribe();
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    fn set_closed(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_open {
+            state.is_open = false;
+            state.wake_all();
+        }
+    }
+
+    fn drop_sender(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.num_senders -= 1;
+        if state.num_senders == 0 && state.is_open {
+            state.is_open = false;
+            state.wake_all();
+        }
+    }
+}
+
+impl<T> State<T> {
+    fn wake_all(&mut self) {
+        for task in self.recv_tasks.iter_mut() {
+            if let Some(task) = task.take() {
+                task.wake();
+            }
+        }
+    }
+}
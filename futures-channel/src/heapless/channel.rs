@@ -0,0 +1,533 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::ptr;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::__internal::AtomicWaker;
+use futures_core::task::{Context, Poll};
+
+// Flags of `StaticChannel::state`.
+const SPLIT: usize = 1;
+const TX_CLOSED: usize = 1 << 1;
+const RX_CLOSED: usize = 1 << 2;
+
+/// A bounded single-producer, single-consumer channel holding up to `N`
+/// messages, which does not allocate.
+///
+/// The channel can be placed in a `static`, and is used through the
+/// [`Sender`](Sender) and [`Receiver`](Receiver) returned by
+/// [`split`](StaticChannel::split).
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::heapless::StaticChannel;
+/// use futures::executor::block_on;
+/// use futures::stream::StreamExt;
+///
+/// static CHANNEL: StaticChannel<u32, 4> = StaticChannel::new();
+///
+/// let (mut tx, mut rx) = CHANNEL.split().unwrap();
+/// tx.try_send(1).unwrap();
+/// tx.try_send(2).unwrap();
+/// drop(tx);
+///
+/// assert_eq!(block_on(rx.next()), Some(1));
+/// assert_eq!(block_on(rx.next()), Some(2));
+/// assert_eq!(block_on(rx.next()), None);
+/// ```
+pub struct StaticChannel<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+
+    // Number of messages received, only increased by the receiver. Wraps
+    // around like `tail`.
+    head: AtomicUsize,
+
+    // Number of messages sent, only increased by the sender. Wraps around at
+    // the largest multiple of `N`, so that it keeps indexing the slots in
+    // turn.
+    tail: AtomicUsize,
+
+    // Whether the channel has been split, and which halves have gone.
+    state: AtomicUsize,
+
+    // Woken when a message is sent, or the sender goes.
+    recv_task: AtomicWaker,
+
+    // Woken when a message is received, or the receiver goes.
+    send_task: AtomicWaker,
+}
+
+/// The sending half of a [`StaticChannel`](StaticChannel).
+///
+/// This value is created by the [`split`](StaticChannel::split) method.
+pub struct Sender<'a, T, const N: usize> {
+    channel: &'a StaticChannel<T, N>,
+}
+
+/// The receiving half of a [`StaticChannel`](StaticChannel).
+///
+/// This value is created by the [`split`](StaticChannel::split) method.
+pub struct Receiver<'a, T, const N: usize> {
+    channel: &'a StaticChannel<T, N>,
+    terminated: bool,
+}
+
+/// The error type for [`Sender`s](Sender) used as `Sink`s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendError {
+    kind: SendErrorKind,
+}
+
+/// The error type returned from [`try_send`](Sender::try_send).
+#[derive(Clone, PartialEq, Eq)]
+pub struct TrySendError<T> {
+    err: SendError,
+    val: T,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum SendErrorKind {
+    Full,
+    Disconnected,
+}
+
+/// The error type returned from [`try_next`](Receiver::try_next).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryRecvError {
+    _priv: (),
+}
+
+unsafe impl<T: Send, const N: usize> Send for StaticChannel<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for StaticChannel<T, N> {}
+
+impl<T, const N: usize> StaticChannel<T, N> {
+    /// Creates an empty channel.
+    pub const fn new() -> Self {
+        Self {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            state: AtomicUsize::new(0),
+            recv_task: AtomicWaker::new(),
+            send_task: AtomicWaker::new(),
+        }
+    }
+
+    /// Splits the channel into its sending and receiving halves.
+    ///
+    /// A channel can only be split once, later calls return `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn split(&self) -> Option<(Sender<'_, T, N>, Receiver<'_, T, N>)> {
+        assert!(N > 0, "a static channel needs a capacity");
+
+        if self.state.fetch_or(SPLIT, SeqCst) & SPLIT != 0 {
+            return None;
+        }
+        Some((Sender { channel: self }, Receiver { channel: self, terminated: false }))
+    }
+
+    /// Returns the number of messages the channel can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { (self.buffer.get() as *mut T).add(index % N) }
+    }
+
+    // The value at which `head` and `tail` wrap around to zero.
+    fn wrap() -> usize {
+        core::usize::MAX / N * N
+    }
+
+    fn next_index(index: usize) -> usize {
+        if index + 1 == Self::wrap() {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.load(SeqCst) & (TX_CLOSED | RX_CLOSED) != 0
+    }
+
+    fn close(&self, flag: usize) {
+        self.state.fetch_or(flag, SeqCst);
+        self.recv_task.wake();
+        self.send_task.wake();
+    }
+
+    // Called by the sender only.
+    fn is_full(&self) -> bool {
+        let tail = self.tail.load(Relaxed);
+        let head = self.head.load(Acquire);
+        let len = if tail >= head { tail - head } else { Self::wrap() - head + tail };
+        len == N
+    }
+
+    // Called by the sender only.
+    fn push(&self, msg: T) -> Result<(), TrySendError<T>> {
+        if self.is_closed() {
+            return Err(TrySendError { err: SendError::disconnected(), val: msg });
+        }
+        if self.is_full() {
+            return Err(TrySendError { err: SendError::full(), val: msg });
+        }
+
+        let tail = self.tail.load(Relaxed);
+        unsafe { ptr::write(self.slot(tail), msg) };
+        self.tail.store(Self::next_index(tail), Release);
+        self.recv_task.wake();
+        Ok(())
+    }
+
+    // Called by the receiver only.
+    fn pop(&self) -> Option<T> {
+        let head = self.head.load(Relaxed);
+        if head == self.tail.load(Acquire) {
+            return None;
+        }
+
+        let msg = unsafe { ptr::read(self.slot(head)) };
+        self.head.store(Self::next_index(head), Release);
+        self.send_task.wake();
+        Some(msg)
+    }
+}
+
+impl<T, const N: usize> Default for StaticChannel<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for StaticChannel<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for StaticChannel<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticChannel").field("capacity", &N).finish()
+    }
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T, const N: usize> Sender<'_, T, N> {
+    /// Attempts to send a message on this `Sender`, returning the message
+    /// if there was an error.
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        self.channel.push(msg)
+    }
+
+    /// Polls the channel to determine if there is capacity for a message.
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if there is capacity;
+    /// - `Poll::Pending` if the channel is full, in which case the current
+    ///   task is woken once a message has been received;
+    /// - `Poll::Ready(Err(SendError))` if the channel is closed.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        match self.poll_ready_nb() {
+            Poll::Pending => {
+                self.channel.send_task.register(cx.waker());
+                // Check again, as the receiver may have made room before
+                // `register`.
+                self.poll_ready_nb()
+            }
+            ready => ready,
+        }
+    }
+
+    fn poll_ready_nb(&self) -> Poll<Result<(), SendError>> {
+        if self.channel.is_closed() {
+            Poll::Ready(Err(SendError::disconnected()))
+        } else if self.channel.is_full() {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Send a message on the channel.
+    ///
+    /// This function should only be called after
+    /// [`poll_ready`](Sender::poll_ready) has reported that the channel is
+    /// ready to receive a message.
+    pub fn start_send(&mut self, msg: T) -> Result<(), SendError> {
+        self.try_send(msg).map_err(|e| e.err)
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&mut self) {
+        self.channel.close(TX_CLOSED);
+    }
+}
+
+impl<T, const N: usize> Drop for Sender<'_, T, N> {
+    fn drop(&mut self) {
+        self.channel.close(TX_CLOSED);
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Sender<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").field("closed", &self.is_closed()).finish()
+    }
+}
+
+#[cfg(feature = "sink")]
+impl<T, const N: usize> futures_sink::Sink<T> for Sender<'_, T, N> {
+    type Error = SendError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (*self).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, msg: T) -> Result<(), Self::Error> {
+        (*self).start_send(msg)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T, const N: usize> Receiver<'_, T, N> {
+    /// Closes the receiving half of the channel, without dropping it.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receiver to drain messages that are buffered.
+    pub fn close(&mut self) {
+        self.channel.close(RX_CLOSED);
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// This function returns:
+    /// * `Ok(Some(t))` when message is fetched
+    /// * `Ok(None)` when channel is closed and no messages left in the queue
+    /// * `Err(e)` when there are no messages available, but channel is not yet closed
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message() {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError { _priv: () }),
+        }
+    }
+
+    fn next_message(&mut self) -> Poll<Option<T>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+
+        if let Some(msg) = self.channel.pop() {
+            return Poll::Ready(Some(msg));
+        }
+
+        if self.channel.is_closed() {
+            // The sender may have sent a last message before closing.
+            let msg = self.channel.pop();
+            self.terminated = msg.is_none();
+            Poll::Ready(msg)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T, const N: usize> Stream for Receiver<'_, T, N> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.next_message() {
+            Poll::Pending => {
+                self.channel.recv_task.register(cx.waker());
+                // Check again, as a message may have been sent before
+                // `register`.
+                self.next_message()
+            }
+            ready => ready,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.terminated {
+            (0, Some(0))
+        } else {
+            let len = self.channel.tail.load(Acquire).wrapping_sub(self.channel.head.load(Relaxed));
+            (len, None)
+        }
+    }
+}
+
+impl<T, const N: usize> FusedStream for Receiver<'_, T, N> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl<T, const N: usize> Drop for Receiver<'_, T, N> {
+    fn drop(&mut self) {
+        self.channel.close(RX_CLOSED);
+        // Drop the messages which are still buffered.
+        while self.channel.pop().is_some() {}
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Receiver<'_, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").field("terminated", &self.terminated).finish()
+    }
+}
+
+/*
+ *
+ * ===== impl errors =====
+ *
+ */
+
+impl SendError {
+    fn full() -> Self {
+        Self { kind: SendErrorKind::Full }
+    }
+
+    fn disconnected() -> Self {
+        Self { kind: SendErrorKind::Disconnected }
+    }
+
+    /// Returns `true` if this error is a result of the channel being full.
+    pub fn is_full(&self) -> bool {
+        match self.kind {
+            SendErrorKind::Full => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this error is a result of the channel being closed.
+    pub fn is_disconnected(&self) -> bool {
+        match self.kind {
+            SendErrorKind::Disconnected => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_full() {
+            write!(f, "send failed because channel is full")
+        } else {
+            write!(f, "send failed because receiver is gone")
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendError {}
+
+impl<T> TrySendError<T> {
+    /// Returns `true` if this error is a result of the channel being full.
+    pub fn is_full(&self) -> bool {
+        self.err.is_full()
+    }
+
+    /// Returns `true` if this error is a result of the channel being closed.
+    pub fn is_disconnected(&self) -> bool {
+        self.err.is_disconnected()
+    }
+
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+
+    /// Drops the message and converts into a `SendError`.
+    pub fn into_send_error(self) -> SendError {
+        self.err
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrySendError").field("kind", &self.err.kind).finish()
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.err, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: core::any::Any> std::error::Error for TrySendError<T> {}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "receiver channel is empty")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TryRecvError {}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticChannel;
+    use core::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn indices_wrap_around() {
+        let channel = StaticChannel::<usize, 3>::new();
+        let start = StaticChannel::<usize, 3>::wrap() - 2;
+        channel.head.store(start, SeqCst);
+        channel.tail.store(start, SeqCst);
+        let (mut tx, mut rx) = channel.split().unwrap();
+
+        for i in 0..3 {
+            tx.try_send(i).unwrap();
+        }
+        assert!(tx.try_send(3).unwrap_err().is_full());
+
+        let mut received = Vec::new();
+        for i in 3..9 {
+            received.push(rx.try_next().unwrap().unwrap());
+            tx.try_send(i).unwrap();
+        }
+        while let Ok(Some(i)) = rx.try_next() {
+            received.push(i);
+        }
+        assert_eq!(received, (0..9).collect::<Vec<_>>());
+    }
+}
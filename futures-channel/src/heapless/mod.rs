@@ -0,0 +1,36 @@
+//! Channels which do not allocate, for `no_std` targets without an allocator.
+//!
+//! The channels in this module store their messages inline, so that they can
+//! be placed in a `static` and used without the `alloc` feature of this
+//! library. A channel is split once into a sender and a receiver, both
+//! borrowing it.
+//!
+//! - [`StaticChannel`](StaticChannel), a bounded single-producer,
+//!   single-consumer channel holding up to `N` messages. It needs const
+//!   generics, so it is only available when compiling with Rust 1.51 or later.
+//! - [`StaticOneshot`](oneshot::StaticOneshot), a channel for sending a
+//!   single value.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::channel::heapless::oneshot::StaticOneshot;
+//! use futures::executor::block_on;
+//! use futures::future::join;
+//!
+//! static ONESHOT: StaticOneshot<u32> = StaticOneshot::new();
+//!
+//! let (tx, rx) = ONESHOT.split().unwrap();
+//! let send = async move { tx.send(3).unwrap() };
+//! assert_eq!(block_on(join(send, rx)).1, Ok(3));
+//!
+//! // A channel can only be split once.
+//! assert!(ONESHOT.split().is_none());
+//! ```
+
+#[cfg(futures_const_generics)]
+mod channel;
+#[cfg(futures_const_generics)]
+pub use self::channel::{Receiver, SendError, Sender, StaticChannel, TryRecvError, TrySendError};
+
+pub mod oneshot;
@@ -0,0 +1,293 @@
+//! A channel for sending a single message, which does not allocate.
+//!
+//! This is the counterpart of [`oneshot`](crate::oneshot) which can be placed
+//! in a `static`.
+//!
+//! # Examples
+//!
+//! ```
+//! use futures::channel::heapless::oneshot::StaticOneshot;
+//! use futures::executor::block_on;
+//!
+//! static ONESHOT: StaticOneshot<u32> = StaticOneshot::new();
+//!
+//! let (tx, rx) = ONESHOT.split().unwrap();
+//! tx.send(3).unwrap();
+//! assert_eq!(block_on(rx), Ok(3));
+//! ```
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::ptr;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::SeqCst;
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::__internal::AtomicWaker;
+use futures_core::task::{Context, Poll};
+
+// Flags of `StaticOneshot::state`.
+const SPLIT: usize = 1;
+// The value has been written.
+const DATA: usize = 1 << 1;
+// The sender is gone, after sending or not.
+const TX_DONE: usize = 1 << 2;
+// The receiver is closed or gone.
+const RX_CLOSED: usize = 1 << 3;
+
+/// A channel for sending a single message, which does not allocate.
+///
+/// The channel can be placed in a `static`, and is used through the
+/// [`Sender`](Sender) and [`Receiver`](Receiver) returned by
+/// [`split`](StaticOneshot::split).
+pub struct StaticOneshot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+
+    // Whether the channel has been split, the value sent, and which halves
+    // have gone.
+    state: AtomicUsize,
+
+    // Woken when the value is sent, or the sender goes.
+    rx_task: AtomicWaker,
+
+    // Woken when the receiver is closed or goes.
+    tx_task: AtomicWaker,
+}
+
+/// A means of transmitting a single value to another task.
+///
+/// This is created by the [`split`](StaticOneshot::split) method.
+pub struct Sender<'a, T> {
+    oneshot: &'a StaticOneshot<T>,
+}
+
+/// A future for a value that will be provided by another asynchronous task.
+///
+/// This is created by the [`split`](StaticOneshot::split) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Receiver<'a, T> {
+    oneshot: &'a StaticOneshot<T>,
+
+    // Set by `close` if no value had been sent at that point, so that a value
+    // sent concurrently is left to the sender to take back.
+    closed_empty: bool,
+
+    // Set once the value has been received, or the channel was canceled.
+    done: bool,
+}
+
+/// Error returned from a [`Receiver`](Receiver) when the corresponding
+/// [`Sender`](Sender) is dropped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Canceled;
+
+unsafe impl<T: Send> Send for StaticOneshot<T> {}
+unsafe impl<T: Send> Sync for StaticOneshot<T> {}
+
+impl<T> StaticOneshot<T> {
+    /// Creates an empty channel.
+    pub const fn new() -> Self {
+        Self {
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            state: AtomicUsize::new(0),
+            rx_task: AtomicWaker::new(),
+            tx_task: AtomicWaker::new(),
+        }
+    }
+
+    /// Splits the channel into its sending and receiving halves.
+    ///
+    /// A channel can only be split once, later calls return `None`.
+    pub fn split(&self) -> Option<(Sender<'_, T>, Receiver<'_, T>)> {
+        if self.state.fetch_or(SPLIT, SeqCst) & SPLIT != 0 {
+            return None;
+        }
+        Some((
+            Sender { oneshot: self },
+            Receiver { oneshot: self, closed_empty: false, done: false },
+        ))
+    }
+}
+
+impl<T> Default for StaticOneshot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for StaticOneshot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticOneshot").finish()
+    }
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<'_, T> {
+    /// Completes this oneshot with a successful result.
+    ///
+    /// This function will consume `self` and indicate to the other end, the
+    /// [`Receiver`](Receiver), that the value provided is the result of the
+    /// computation this represents.
+    ///
+    /// If the value is successfully enqueued for the remote end to receive,
+    /// then `Ok(())` is returned. If the receiving end was dropped or closed
+    /// before this function was called, however, then `Err(t)` is returned.
+    pub fn send(self, t: T) -> Result<(), T> {
+        let oneshot = self.oneshot;
+        if oneshot.state.load(SeqCst) & RX_CLOSED != 0 {
+            return Err(t);
+        }
+
+        unsafe { ptr::write(oneshot.value.get() as *mut T, t) };
+        // If the receiver was closed in the meantime, it has not seen the
+        // value, and never will.
+        if oneshot.state.fetch_or(DATA, SeqCst) & RX_CLOSED != 0 {
+            return Err(unsafe { ptr::read(oneshot.value.get() as *const T) });
+        }
+        Ok(())
+    }
+
+    /// Polls this `Sender` half to detect whether its associated
+    /// [`Receiver`](Receiver) has been dropped or closed.
+    pub fn poll_canceled(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_canceled() {
+            return Poll::Ready(());
+        }
+
+        self.oneshot.tx_task.register(cx.waker());
+        if self.is_canceled() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Tests to see whether this `Sender`'s corresponding `Receiver` has been
+    /// dropped or closed.
+    pub fn is_canceled(&self) -> bool {
+        self.oneshot.state.load(SeqCst) & RX_CLOSED != 0
+    }
+}
+
+impl<T> Drop for Sender<'_, T> {
+    fn drop(&mut self) {
+        self.oneshot.state.fetch_or(TX_DONE, SeqCst);
+        self.oneshot.rx_task.wake();
+    }
+}
+
+impl<T> fmt::Debug for Sender<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").field("canceled", &self.is_canceled()).finish()
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<'_, T> {
+    /// Gracefully close this receiver, preventing any subsequent attempts to
+    /// send to it.
+    ///
+    /// Any `send` operation which happens after this method returns is
+    /// guaranteed to fail. After calling this method, you can use
+    /// [`Receiver::poll`](core::future::Future::poll) to determine whether a
+    /// message had previously been sent.
+    pub fn close(&mut self) {
+        if self.done || self.closed_empty {
+            return;
+        }
+
+        let prev = self.oneshot.state.fetch_or(RX_CLOSED, SeqCst);
+        self.closed_empty = prev & DATA == 0;
+        self.oneshot.tx_task.wake();
+    }
+
+    /// Attempts to receive a message outside of the context of a task.
+    ///
+    /// Does not schedule a task wakeup or have any other side effects.
+    ///
+    /// A return value of `None` must be considered immediately stale (out of
+    /// date) unless [`close`](Receiver::close) has been called first.
+    ///
+    /// Returns an error if the sender was dropped.
+    pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
+        if self.done {
+            return Err(Canceled);
+        }
+
+        let state = self.oneshot.state.load(SeqCst);
+        if state & DATA != 0 && !self.closed_empty {
+            self.done = true;
+            Ok(Some(unsafe { ptr::read(self.oneshot.value.get() as *const T) }))
+        } else if state & TX_DONE != 0 || self.closed_empty {
+            // The sender sets `DATA` before it is dropped, so this is final.
+            self.done = true;
+            Err(Canceled)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T> Future for Receiver<'_, T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T, Canceled>> {
+        if let Some(t) = self.try_recv()? {
+            return Poll::Ready(Ok(t));
+        }
+
+        self.oneshot.rx_task.register(cx.waker());
+        // Check again, as the value may have been sent before `register`.
+        match self.try_recv()? {
+            Some(t) => Poll::Ready(Ok(t)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T> FusedFuture for Receiver<'_, T> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<T> Drop for Receiver<'_, T> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let prev = self.oneshot.state.fetch_or(RX_CLOSED, SeqCst);
+        self.oneshot.tx_task.wake();
+        // Drop the value if it was sent but not received.
+        if prev & DATA != 0 && !self.closed_empty {
+            unsafe { ptr::drop_in_place(self.oneshot.value.get() as *mut T) };
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").field("done", &self.done).finish()
+    }
+}
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "oneshot canceled")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Canceled {}
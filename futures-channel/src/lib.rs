@@ -1,15 +1,26 @@
 //! Asynchronous channels.
 //!
 //! Like threads, concurrent tasks sometimes need to communicate with each
-//! other. This module contains two basic abstractions for doing so:
+//! other. This module contains several basic abstractions for doing so:
 //!
 //! - [oneshot], a way of sending a single value from one task to another.
 //! - [mpsc], a multi-producer, single-consumer channel for sending values
 //!   between tasks, analogous to the similarly-named structure in the standard
 //!   library.
+//! - [mpmc], a multi-producer, multi-consumer channel where each value sent
+//!   is received by exactly one of the receivers.
+//! - [broadcast], a multi-producer, multi-consumer channel where every
+//!   receiver observes every value sent.
+//! - [watch], a single-producer, multi-consumer channel which only retains
+//!   the most recently sent value.
+//! - [rpc], a channel for sending requests to a task and receiving its
+//!   responses, built on [mpsc] and [oneshot].
+//! - [heapless], channels with a fixed capacity which can be placed in a
+//!   `static`, for targets without an allocator.
 //!
-//! All items are only available when the `std` or `alloc` feature of this
-//! library is activated, and it is activated by default.
+//! All items except for the ones in [heapless] are only available when the
+//! `std` or `alloc` feature of this library is activated, and it is activated
+//! by default.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(
@@ -31,12 +42,29 @@
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub mod broadcast;
+#[cfg(not(futures_no_atomic_cas))]
+pub mod heapless;
 #[cfg(not(futures_no_atomic_cas))]
 #[cfg(feature = "alloc")]
 mod lock;
 #[cfg(not(futures_no_atomic_cas))]
 #[cfg(feature = "std")]
+pub mod mpmc;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
 pub mod mpsc;
 #[cfg(not(futures_no_atomic_cas))]
 #[cfg(feature = "alloc")]
 pub mod oneshot;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub mod rpc;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub mod watch;
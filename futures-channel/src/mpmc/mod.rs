@@ -0,0 +1,626 @@
+//! A multi-producer, multi-consumer queue for sending values across
+//! asynchronous tasks.
+//!
+//! Channel creation provides [`Receiver`] and [`Sender`] handles, both of
+//! which can be cloned. Every message sent into the channel is delivered to
+//! exactly one of the receivers, which makes this channel suitable for work
+//! queues where several tasks pull from the same source.
+//!
+//! [`Receiver`] implements [`Stream`] and [`Sender`] implements the `Sink`
+//! trait, in the same way as their [`mpsc`] counterparts. The channel is
+//! bounded: once `buffer` messages are queued, senders wait for a receiver to
+//! make room.
+//!
+//! # Fairness
+//!
+//! Tasks waiting on either side of the channel are queued, and are woken in
+//! the order in which they started waiting, one per message sent or
+//! received.
+//!
+//! A receiver woken for a message is expected to take it. If the future
+//! returned by [`Receiver::recv`] is dropped instead, the message is handed
+//! on to the next waiting receiver. This cannot be done for the future
+//! returned by `StreamExt::next`, so the message then waits until that
+//! receiver is polled again or dropped, or until another message is sent.
+//!
+//! # Disconnection
+//!
+//! When all [`Sender`] handles have been dropped, it is no longer possible to
+//! send values into the channel. Receivers drain the messages still buffered,
+//! after which their streams terminate.
+//!
+//! If all [`Receiver`] handles are dropped, or the channel is closed with
+//! [`Receiver::close`], all further attempts to send will result in an
+//! error.
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Receiver`]: struct.Receiver.html
+//! [`Receiver::close`]: struct.Receiver.html#method.close
+//! [`Receiver::recv`]: struct.Receiver.html#method.recv
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+//! [`mpsc`]: ../mpsc/index.html
+
+use futures_core::future::{FusedFuture, Future};
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+pub use crate::mpsc::{SendError, TryRecvError, TrySendError};
+
+#[cfg(feature = "sink")]
+mod sink_impl;
+
+#[derive(Debug)]
+struct SenderInner<T> {
+    // Channel state shared between the senders and receivers.
+    inner: Arc<Inner<T>>,
+
+    // Identifies this sender in `State::send_waiters`.
+    key: usize,
+
+    // `true` if `poll_ready` reserved a slot in the buffer for this sender.
+    reserved: bool,
+}
+
+/// The transmission end of a bounded mpmc channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T>(Option<SenderInner<T>>);
+
+/// The receiving end of a bounded mpmc channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Option<Arc<Inner<T>>>,
+
+    // Identifies this receiver in `State::recv_waiters`.
+    key: usize,
+}
+
+// Neither half ever projects `Pin` to the inner `T`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // Max buffer size of the channel.
+    buffer: usize,
+
+    // Channel state shared between the senders and receivers.
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // FIFO queue of the messages sent but not yet received.
+    messages: VecDeque<T>,
+
+    // Number of slots in `messages` reserved by senders through `poll_ready`.
+    num_reserved: usize,
+
+    // `true` when the channel is open
+    is_open: bool,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Number of receivers in existence
+    num_receivers: usize,
+
+    // Key to hand out to the next sender or receiver.
+    next_key: usize,
+
+    // Senders waiting for capacity, in the order they started waiting.
+    send_waiters: Waiters,
+
+    // Receivers waiting for a message, in the order they started waiting.
+    recv_waiters: Waiters,
+}
+
+#[derive(Debug, Default)]
+struct Waiters {
+    queue: VecDeque<(usize, Waker)>,
+}
+
+/// Creates a bounded mpmc channel for communicating between asynchronous
+/// tasks.
+///
+/// Being bounded, this channel provides backpressure to ensure that the
+/// senders outpace the receivers by only a limited amount. At most `buffer`
+/// messages are queued at any time, regardless of the number of senders.
+///
+/// The [`Receiver`](Receiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, while [`Sender`](Sender)
+/// implements `Sink`. Both can be cloned.
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::mpmc;
+/// use futures::executor::block_on;
+/// use futures::stream::StreamExt;
+///
+/// let (mut tx, rx1) = mpmc::channel(16);
+/// let rx2 = rx1.clone();
+///
+/// for i in 0..4 {
+///     tx.try_send(i).unwrap();
+/// }
+/// drop(tx);
+///
+/// block_on(async {
+///     let mut all: Vec<i32> = rx1.take(2).collect().await;
+///     all.extend(rx2.collect::<Vec<_>>().await);
+///     assert_eq!(all, vec![0, 1, 2, 3]);
+/// });
+/// ```
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(buffer > 0, "mpmc channel buffer must be greater than zero");
+
+    let inner = Arc::new(Inner {
+        buffer,
+        state: Mutex::new(State {
+            messages: VecDeque::new(),
+            num_reserved: 0,
+            is_open: true,
+            num_senders: 1,
+            num_receivers: 1,
+            next_key: 2,
+            send_waiters: Waiters::default(),
+            recv_waiters: Waiters::default(),
+        }),
+    });
+
+    let tx = SenderInner { inner: inner.clone(), key: 0, reserved: false };
+    let rx = Receiver { inner: Some(inner), key: 1 };
+
+    (Sender(Some(tx)), rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> SenderInner<T> {
+    fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if !state.is_open {
+            return Err(TrySendError::new(SendError::disconnected(), msg));
+        }
+
+        if self.reserved {
+            self.reserved = false;
+            state.num_reserved -= 1;
+        } else if state.messages.len() + state.num_reserved >= self.inner.buffer {
+            return Err(TrySendError::new(SendError::full(), msg));
+        }
+
+        state.messages.push_back(msg);
+        state.recv_waiters.wake_one();
+        Ok(())
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if !state.is_open {
+            return Poll::Ready(Err(SendError::disconnected()));
+        }
+
+        if self.reserved {
+            return Poll::Ready(Ok(()));
+        }
+
+        if state.messages.len() + state.num_reserved < self.inner.buffer {
+            self.reserved = true;
+            state.num_reserved += 1;
+            state.send_waiters.remove(self.key);
+            Poll::Ready(Ok(()))
+        } else {
+            state.send_waiters.register(self.key, cx.waker());
+            Poll::Pending
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+}
+
+impl<T> Sender<T> {
+    /// Attempts to send a message on this `Sender`, returning the message
+    /// if there was an error.
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        if let Some(inner) = &mut self.0 {
+            inner.try_send(msg)
+        } else {
+            Err(TrySendError::new(SendError::disconnected(), msg))
+        }
+    }
+
+    /// Send a message on the channel.
+    ///
+    /// This function should only be called after
+    /// [`poll_ready`](Sender::poll_ready) has reported that the channel is
+    /// ready to receive a message.
+    pub fn start_send(&mut self, msg: T) -> Result<(), SendError> {
+        self.try_send(msg).map_err(TrySendError::into_send_error)
+    }
+
+    /// Polls the channel to determine if there is guaranteed capacity to send
+    /// at least one item without waiting.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if there is sufficient capacity, in which case a
+    ///   slot is reserved for the next message sent through this `Sender`;
+    /// - `Poll::Pending` if the channel may not have
+    ///   capacity, in which case the current task is queued to be notified once
+    ///   capacity is available;
+    /// - `Poll::Ready(Err(SendError))` if the receivers have been dropped.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        let inner = self.0.as_mut().ok_or_else(SendError::disconnected)?;
+        inner.poll_ready(cx)
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        self.0.as_ref().map(SenderInner::is_closed).unwrap_or(true)
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&mut self) {
+        if let Some(inner) = &self.0 {
+            inner.inner.set_closed();
+        }
+    }
+
+    /// Disconnects this sender from the channel, closing it if there are no more senders left.
+    pub fn disconnect(&mut self) {
+        self.0 = None;
+    }
+
+    /// Returns whether the senders send to the same receivers.
+    pub fn same_receiver(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(inner), Some(other)) => Arc::ptr_eq(&inner.inner, &other.inner),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the sender sends to this receiver.
+    pub fn is_connected_to(&self, receiver: &Receiver<T>) -> bool {
+        match (&self.0, &receiver.inner) {
+            (Some(inner), Some(receiver)) => Arc::ptr_eq(&inner.inner, receiver),
+            _ => false,
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Clone for SenderInner<T> {
+    fn clone(&self) -> Self {
+        let key = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders += 1;
+            state.next_key()
+        };
+
+        Self { inner: self.inner.clone(), key, reserved: false }
+    }
+}
+
+impl<T> Drop for SenderInner<T> {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+
+        state.send_waiters.remove(self.key);
+        if self.reserved {
+            state.num_reserved -= 1;
+        }
+
+        state.num_senders -= 1;
+        if state.num_senders == 0 {
+            state.set_closed();
+        } else if state.messages.len() + state.num_reserved < self.inner.buffer {
+            // This sender may have been woken up for capacity it will never
+            // use, so pass it on.
+            state.send_waiters.wake_one();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Closes the channel for all receivers, without dropping this one.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receivers to drain messages that are buffered.
+    pub fn close(&mut self) {
+        if let Some(inner) = &self.inner {
+            inner.set_closed();
+        }
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    ///
+    /// This function returns:
+    /// * `Ok(Some(t))` when message is fetched
+    /// * `Ok(None)` when channel is closed and no messages left in the queue
+    /// * `Err(e)` when there are no messages available, but channel is not yet closed
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError::new()),
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut Context<'_>>) -> Poll<Option<T>> {
+        let inner = match &self.inner {
+            None => return Poll::Ready(None),
+            Some(inner) => inner,
+        };
+        let mut state = inner.state.lock().unwrap();
+
+        if let Some(msg) = state.messages.pop_front() {
+            state.recv_waiters.remove(self.key);
+            state.send_waiters.wake_one();
+            return Poll::Ready(Some(msg));
+        }
+
+        if !state.is_open {
+            // If closed flag is set AND there are no pending messages
+            // it means end of stream
+            state.recv_waiters.remove(self.key);
+            drop(state);
+            self.inner = None;
+            return Poll::Ready(None);
+        }
+
+        if let Some(cx) = cx {
+            state.recv_waiters.register(self.key, cx.waker());
+        }
+        Poll::Pending
+    }
+
+    /// Receives the next message, or `None` once the channel is closed and
+    /// no messages are left.
+    ///
+    /// This behaves like `StreamExt::next`, except that dropping the future
+    /// after this receiver was woken for a message hands the message on to
+    /// another waiting receiver.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpmc;
+    /// use futures::executor::block_on;
+    ///
+    /// let (mut tx, mut rx) = mpmc::channel(1);
+    /// tx.try_send(1).unwrap();
+    /// drop(tx);
+    ///
+    /// assert_eq!(block_on(rx.recv()), Some(1));
+    /// assert_eq!(block_on(rx.recv()), None);
+    /// ```
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { receiver: Some(self), is_waiting: false }
+    }
+
+    // Called when this receiver stops waiting for a message. If it was woken
+    // for a message it will not take, the wakeup is passed on.
+    fn cancel_wait(&mut self) {
+        if let Some(inner) = &self.inner {
+            let mut state = inner.state.lock().unwrap();
+            if !state.recv_waiters.remove(self.key) && !state.messages.is_empty() {
+                state.recv_waiters.wake_one();
+            }
+        }
+    }
+
+    /// Returns whether the receivers receive from the same senders.
+    pub fn same_channel(&self, other: &Self) -> bool {
+        match (&self.inner, &other.inner) {
+            (Some(inner), Some(other)) => Arc::ptr_eq(inner, other),
+            _ => false,
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        match &self.inner {
+            Some(inner) => {
+                let key = {
+                    let mut state = inner.state.lock().unwrap();
+                    state.num_receivers += 1;
+                    state.next_key()
+                };
+                Self { inner: Some(inner.clone()), key }
+            }
+            None => Self { inner: None, key: self.key },
+        }
+    }
+}
+
+impl<T> FusedStream for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.next_message(Some(cx))
+    }
+}
+
+/// Future for the [`recv`](Receiver::recv) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'a, T> {
+    receiver: Option<&'a mut Receiver<T>>,
+
+    // Whether the receiver is waiting for a message for this future.
+    is_waiting: bool,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let receiver = self.receiver.as_mut().expect("polled Recv after completion");
+        match receiver.next_message(Some(cx)) {
+            Poll::Ready(msg) => {
+                self.receiver = None;
+                Poll::Ready(msg)
+            }
+            Poll::Pending => {
+                self.is_waiting = true;
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> FusedFuture for Recv<'_, T> {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_none()
+    }
+}
+
+impl<T> Drop for Recv<'_, T> {
+    fn drop(&mut self) {
+        if let Some(receiver) = self.receiver.take() {
+            if self.is_waiting {
+                receiver.cancel_wait();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return,
+        };
+        let mut messages = VecDeque::new();
+        {
+            let mut state = inner.state.lock().unwrap();
+
+            state.recv_waiters.remove(self.key);
+
+            state.num_receivers -= 1;
+            if state.num_receivers == 0 {
+                // Nobody is left to receive the buffered messages, so close
+                // the channel and drop them outside of the lock.
+                state.set_closed();
+                messages = mem::replace(&mut state.messages, VecDeque::new());
+            } else if !state.messages.is_empty() {
+                // This receiver may have been woken up for a message it will
+                // never take, so pass it on.
+                state.recv_waiters.wake_one();
+            }
+        }
+        drop(messages);
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    fn set_closed(&self) {
+        self.state.lock().unwrap().set_closed();
+    }
+}
+
+impl<T> State<T> {
+    fn next_key(&mut self) -> usize {
+        let key = self.next_key;
+        self.next_key += 1;
+        key
+    }
+
+    // Clear the `open` flag, keeping buffered messages intact, and wake up
+    // every waiting task so that it observes the change.
+    fn set_closed(&mut self) {
+        if self.is_open {
+            self.is_open = false;
+            self.send_waiters.wake_all();
+            self.recv_waiters.wake_all();
+        }
+    }
+}
+
+impl Waiters {
+    // Queue the task identified by `key`, keeping its position in the queue
+    // if it was already waiting.
+    fn register(&mut self, key: usize, waker: &Waker) {
+        match self.queue.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, w)) => {
+                if !w.will_wake(waker) {
+                    *w = waker.clone();
+                }
+            }
+            None => self.queue.push_back((key, waker.clone())),
+        }
+    }
+
+    // Dequeue the task identified by `key`. Returns whether it was queued.
+    fn remove(&mut self, key: usize) -> bool {
+        match self.queue.iter().position(|(k, _)| *k == key) {
+            Some(pos) => {
+                self.queue.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn wake_one(&mut self) {
+        if let Some((_, waker)) = self.queue.pop_front() {
+            waker.wake();
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for (_, waker) in self.queue.drain(..) {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use super::{ReceiverMut, SendError, Sender};
+use futures_core::future::Future;
+use futures_core::task::{Context, Poll};
+use std::pin::Pin;
+use std::vec;
+
+/// Future for the [`recv_many`](super::Receiver::recv_many) and
+/// [`recv_many`](super::UnboundedReceiver::recv_many) methods.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RecvMany<'a, T> {
+    receiver: ReceiverMut<'a, T>,
+    buf: &'a mut Vec<T>,
+    limit: usize,
+}
+
+/// Future for the [`send_batch`](Sender::send_batch) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendBatch<'a, T> {
+    sender: &'a mut Sender<T>,
+    items: vec::IntoIter<T>,
+
+    // Permits the sender held before the batch and has not used for it yet.
+    // These are used first, and kept if the batch is dropped.
+    held_permits: usize,
+}
+
+// Neither future ever projects its pin onto a message.
+impl<T> Unpin for RecvMany<'_, T> {}
+impl<T> Unpin for SendBatch<'_, T> {}
+
+impl<'a, T> RecvMany<'a, T> {
+    pub(super) fn new(receiver: ReceiverMut<'a, T>, buf: &'a mut Vec<T>, limit: usize) -> Self {
+        Self { receiver, buf, limit }
+    }
+}
+
+impl<'a, T> SendBatch<'a, T> {
+    pub(super) fn new(sender: &'a mut Sender<T>, items: Vec<T>) -> Self {
+        let held_permits = sender.num_permits();
+        Self { sender, items: items.into_iter(), held_permits }
+    }
+}
+
+impl<T> Future for RecvMany<'_, T> {
+    type Output = usize;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<usize> {
+        let this = &mut *self;
+        this.receiver.poll_recv_many(cx, this.buf, this.limit)
+    }
+}
+
+impl<T> Future for SendBatch<'_, T> {
+    type Output = Result<(), SendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        while this.items.len() > 0 {
+            let n = match this.sender.poll_reserve_many(cx, this.items.len()) {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(e)) => {
+                    this.items = Vec::new().into_iter();
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            for msg in this.items.by_ref().take(n) {
+                if let Err(e) = this.sender.start_send(msg) {
+                    this.items = Vec::new().into_iter();
+                    return Poll::Ready(Err(e));
+                }
+                this.held_permits = this.held_permits.saturating_sub(1);
+            }
+        }
+
+        match this.sender.poll_flush(cx) {
+            // If the receiver disconnected, we consider the batch to be sent.
+            Poll::Ready(Err(ref e)) if e.is_disconnected() => Poll::Ready(Ok(())),
+            res => res,
+        }
+    }
+}
+
+impl<T> Drop for SendBatch<'_, T> {
+    fn drop(&mut self) {
+        // Give back the capacity reserved for messages which were not sent,
+        // but not the capacity the sender had reserved before.
+        self.sender.release_permits_over(self.held_permits);
+    }
+}
@@ -0,0 +1,26 @@
+use super::ReceiverMut;
+use futures_core::future::Future;
+use futures_core::task::{Context, Poll};
+use std::pin::Pin;
+
+/// Future for the [`closed`](super::Receiver::closed) and
+/// [`closed`](super::UnboundedReceiver::closed) methods.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Closed<'a, T> {
+    receiver: ReceiverMut<'a, T>,
+}
+
+impl<'a, T> Closed<'a, T> {
+    pub(super) fn new(receiver: ReceiverMut<'a, T>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl<T> Future for Closed<'_, T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.receiver.poll_closed(cx)
+    }
+}
@@ -10,7 +10,9 @@
 //! the task will be notified when additional capacity is available. In other
 //! words, the channel provides backpressure.
 //!
-//! Unbounded channels are also available using the `unbounded` constructor.
+//! Unbounded channels are also available using the `unbounded` constructor,
+//! and channels delivering messages in order of priority using the
+//! `priority_channel` constructor.
 //!
 //! # Disconnection
 //!
@@ -40,37 +42,42 @@
 
 // At the core, the channel uses an atomic FIFO queue for message passing. This
 // queue is used as the primary coordination primitive. In order to enforce
-// capacity limits and handle back pressure, a secondary FIFO queue is used to
-// send parked task handles.
+// capacity limits and handle back pressure, bounded channels also keep a
+// semaphore-style counter of free slots, shared by all senders.
 //
 // The general idea is that the channel is created with a `buffer` size of `n`.
-// The channel capacity is `n + num-senders`. Each sender gets one "guaranteed"
-// slot to hold a message. This allows `Sender` to know for a fact that a send
-// will succeed *before* starting to do the actual work of sending the value.
-// Since most of this work is lock-free, once the work starts, it is impossible
-// to safely revert.
+// The channel capacity is `n`, no matter how many senders exist, except that a
+// channel with no buffer still lets one message be handed over. A sender
+// acquires a permit for one slot *before* starting to do the actual work of
+// sending the value, which allows it to know for a fact that the send will
+// succeed. Since most of this work is lock-free, once the work starts, it is
+// impossible to safely revert. The permit travels with the message and is
+// given back when the receiver reads the message out of the channel.
 //
-// If the sender is unable to process a send operation, then the current
-// task is parked and the handle is sent on the parked task queue.
+// If no permit is available, the sender's task handle is queued on the
+// semaphore. Permits given back while senders are queued are handed to them
+// directly in FIFO order, so a sender that has been waiting cannot be starved
+// by senders that arrive later.
 //
-// Note that the implementation guarantees that the channel capacity will never
-// exceed the configured limit, however there is no *strict* guarantee that the
-// receiver will wake up a parked task *immediately* when a slot becomes
-// available. However, it will almost always unpark a task when a slot becomes
-// available and it is *guaranteed* that a sender will be unparked when the
-// message that caused the sender to become parked is read out of the channel.
+// A sender whose message takes the channel over `n` messages, which only
+// happens when `n` is zero, is additionally parked on the parked task queue
+// until a message is read out of the channel. This is what makes flushing the
+// `Sender` of a rendezvous channel wait for the message to be received.
 //
 // The steps for sending a message are roughly:
 //
-// 1) Increment the channel message count
-// 2) If the channel is at capacity, push the task handle onto the wait queue
-// 3) Push the message onto the message queue.
+// 1) Acquire a permit, queueing on the semaphore if there is none
+// 2) Increment the channel message count
+// 3) If the channel is over `n` messages, push the task handle onto the
+//    parked task queue
+// 4) Push the message onto the message queue.
 //
 // The steps for receiving a message are roughly:
 //
 // 1) Pop a message from the message queue
-// 2) Pop a task handle from the wait queue
+// 2) Pop a task handle from the parked task queue
 // 3) Decrement the channel message count.
+// 4) Give the message's permit back to the semaphore
 //
 // It's important for the order of operations on lock-free structures to happen
 // in reverse order between the sender and receiver. This makes the message
@@ -81,15 +88,31 @@
 use futures_core::stream::{FusedStream, Stream};
 use futures_core::task::__internal::AtomicWaker;
 use futures_core::task::{Context, Poll, Waker};
+use std::cmp;
+use std::collections::VecDeque;
 use std::fmt;
+use std::mem;
 use std::pin::Pin;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
 
+use crate::blocking;
 use crate::mpsc::queue::Queue;
 
+mod batch;
+pub use self::batch::{RecvMany, SendBatch};
+
+mod closed;
+pub use self::closed::Closed;
+
+mod permit;
+pub use self::permit::{OwnedPermit, Permit, Reserve, ReserveOwned};
+
+mod priority;
+pub use self::priority::{priority_channel, PriorityReceiver, PrioritySender, SendWithPriority};
+
 mod queue;
 #[cfg(feature = "sink")]
 mod sink_impl;
@@ -113,6 +136,9 @@ struct BoundedSenderInner<T> {
     // `true` if the sender might be blocked. This is an optimization to avoid
     // having to lock the mutex most of the time.
     maybe_parked: bool,
+
+    // Number of permits this sender holds for the next messages it sends.
+    num_permits: usize,
 }
 
 // We never project Pin<&mut SenderInner> to `Pin<&mut T>`
@@ -121,7 +147,11 @@ impl<T> Unpin for BoundedSenderInner<T> {}
 
 /// The transmission end of a bounded mpsc channel.
 ///
-/// This value is created by the [`channel`](channel) function.
+/// This value is created by the [`channel`](channel) and
+/// [`channel_with_policy`](channel_with_policy) functions.
+///
+/// Once [`poll_ready`](Sender::poll_ready) has returned `Ready(Ok(()))`, the
+/// sender holds a slot of the channel until it sends a message or is dropped.
 #[derive(Debug)]
 pub struct Sender<T>(Option<BoundedSenderInner<T>>);
 
@@ -131,15 +161,46 @@ pub struct Sender<T>(Option<BoundedSenderInner<T>>);
 #[derive(Debug)]
 pub struct UnboundedSender<T>(Option<UnboundedSenderInner<T>>);
 
+/// A bounded mpsc channel sender that does not keep the channel open.
+///
+/// This value is created by the [`downgrade`](Sender::downgrade) method. It
+/// does not count as a sender of the channel, so the receiver sees the end of
+/// the stream once all [`Sender`s](Sender) are gone, and it has to be
+/// [`upgrade`d](WeakSender::upgrade) before it can send.
+#[derive(Debug)]
+pub struct WeakSender<T> {
+    inner: Weak<BoundedInner<T>>,
+}
+
+/// An unbounded mpsc channel sender that does not keep the channel open.
+///
+/// This value is created by the [`downgrade`](UnboundedSender::downgrade)
+/// method. It does not count as a sender of the channel, so the receiver sees
+/// the end of the stream once all [`UnboundedSender`s](UnboundedSender) are
+/// gone, and it has to be [`upgrade`d](WeakUnboundedSender::upgrade) before it
+/// can send.
+#[derive(Debug)]
+pub struct WeakUnboundedSender<T> {
+    inner: Weak<UnboundedInner<T>>,
+}
+
 trait AssertKinds: Send + Sync + Clone {}
 impl AssertKinds for UnboundedSender<u32> {}
 
 /// The receiving end of a bounded mpsc channel.
 ///
-/// This value is created by the [`channel`](channel) function.
+/// This value is created by the [`channel`](channel) and
+/// [`channel_with_policy`](channel_with_policy) functions.
 #[derive(Debug)]
 pub struct Receiver<T> {
     inner: Option<Arc<BoundedInner<T>>>,
+
+    // Number of dropped messages, saved once the channel is done with.
+    num_dropped: usize,
+
+    // The reason the channel was closed with, saved once the channel is done
+    // with.
+    close_reason: Option<CloseReason>,
 }
 
 /// The receiving end of an unbounded mpsc channel.
@@ -148,15 +209,50 @@ pub struct Receiver<T> {
 #[derive(Debug)]
 pub struct UnboundedReceiver<T> {
     inner: Option<Arc<UnboundedInner<T>>>,
+
+    // The reason the channel was closed with, saved once the channel is done
+    // with.
+    close_reason: Option<CloseReason>,
 }
 
 // `Pin<&mut UnboundedReceiver<T>>` is never projected to `Pin<&mut T>`
 impl<T> Unpin for UnboundedReceiver<T> {}
 
+// A borrowed receiver of either kind, for the futures both kinds share.
+#[derive(Debug)]
+enum ReceiverMut<'a, T> {
+    Bounded(&'a mut Receiver<T>),
+    Unbounded(&'a mut UnboundedReceiver<T>),
+}
+
+impl<T> ReceiverMut<'_, T> {
+    fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self {
+            Self::Bounded(receiver) => receiver.poll_closed(cx),
+            Self::Unbounded(receiver) => receiver.poll_closed(cx),
+        }
+    }
+
+    fn poll_recv_many(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut Vec<T>,
+        limit: usize,
+    ) -> Poll<usize> {
+        match self {
+            Self::Bounded(receiver) => receiver.poll_recv_many(cx, buf, limit),
+            Self::Unbounded(receiver) => receiver.poll_recv_many(cx, buf, limit),
+        }
+    }
+}
+
 /// The error type for [`Sender`s](Sender) used as `Sink`s.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct SendError {
     kind: SendErrorKind,
+
+    // Why the channel was closed, if it was closed by `close_with`.
+    reason: Option<CloseReason>,
 }
 
 /// The error type returned from [`try_send`](Sender::try_send).
@@ -172,24 +268,65 @@ enum SendErrorKind {
     Disconnected,
 }
 
+// The reason passed to `close_with`.
+type CloseReason = Arc<dyn std::error::Error + Send + Sync>;
+
+fn close_reason<E>(reason: E) -> CloseReason
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Arc::from(reason.into())
+}
+
 /// The error type returned from [`try_next`](Receiver::try_next).
 pub struct TryRecvError {
     _priv: (),
 }
 
+impl PartialEq for SendError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && match (&self.reason, &other.reason) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl Eq for SendError {}
+
 impl fmt::Display for SendError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.is_full() {
             write!(f, "send failed because channel is full")
+        } else if let Some(reason) = &self.reason {
+            write!(f, "send failed because channel was closed: {}", reason)
         } else {
             write!(f, "send failed because receiver is gone")
         }
     }
 }
 
-impl std::error::Error for SendError {}
+impl std::error::Error for SendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.reason.as_ref().map(|reason| &**reason as &(dyn std::error::Error + 'static))
+    }
+}
 
 impl SendError {
+    pub(crate) fn full() -> Self {
+        Self { kind: SendErrorKind::Full, reason: None }
+    }
+
+    pub(crate) fn disconnected() -> Self {
+        Self { kind: SendErrorKind::Disconnected, reason: None }
+    }
+
+    fn closed(reason: Option<CloseReason>) -> Self {
+        Self { kind: SendErrorKind::Disconnected, reason }
+    }
+
     /// Returns `true` if this error is a result of the channel being full.
     pub fn is_full(&self) -> bool {
         match self.kind {
@@ -205,6 +342,13 @@ impl SendError {
             _ => false,
         }
     }
+
+    /// Returns the reason the channel was closed with, if it was closed by
+    /// [`Sender::close_with`](Sender::close_with) or
+    /// [`Receiver::close_with`](Receiver::close_with).
+    pub fn reason(&self) -> Option<&(dyn std::error::Error + Send + Sync + 'static)> {
+        self.reason.as_ref().map(|reason| &**reason)
+    }
 }
 
 impl<T> fmt::Debug for TrySendError<T> {
@@ -215,17 +359,21 @@ impl<T> fmt::Debug for TrySendError<T> {
 
 impl<T> fmt::Display for TrySendError<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.is_full() {
-            write!(f, "send failed because channel is full")
-        } else {
-            write!(f, "send failed because receiver is gone")
-        }
+        fmt::Display::fmt(&self.err, f)
     }
 }
 
-impl<T: core::any::Any> std::error::Error for TrySendError<T> {}
+impl<T: core::any::Any> std::error::Error for TrySendError<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.err)
+    }
+}
 
 impl<T> TrySendError<T> {
+    pub(crate) fn new(err: SendError, val: T) -> Self {
+        Self { err, val }
+    }
+
     /// Returns `true` if this error is a result of the channel being full.
     pub fn is_full(&self) -> bool {
         self.err.is_full()
@@ -236,6 +384,13 @@ impl<T> TrySendError<T> {
         self.err.is_disconnected()
     }
 
+    /// Returns the reason the channel was closed with, if any.
+    ///
+    /// See [`SendError::reason`](SendError::reason).
+    pub fn reason(&self) -> Option<&(dyn std::error::Error + Send + Sync + 'static)> {
+        self.err.reason()
+    }
+
     /// Returns the message that was attempted to be sent but failed.
     pub fn into_inner(self) -> T {
         self.val
@@ -247,6 +402,12 @@ impl<T> TrySendError<T> {
     }
 }
 
+impl TryRecvError {
+    pub(crate) fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
 impl fmt::Debug for TryRecvError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("TryRecvError").finish()
@@ -275,6 +436,12 @@ struct UnboundedInner<T> {
 
     // Handle to the receiver's task.
     recv_task: AtomicWaker,
+
+    // Handle to the task waiting for the channel to be closed.
+    close_task: AtomicWaker,
+
+    // Set by `close_with` before the channel is closed.
+    close_reason: Mutex<Option<CloseReason>>,
 }
 
 #[derive(Debug)]
@@ -282,6 +449,16 @@ struct BoundedInner<T> {
     // Max buffer size of the channel.
     buffer: usize,
 
+    // What senders do when the channel is full.
+    policy: OverflowPolicy,
+
+    // Number of messages dropped because of `policy`.
+    num_dropped: AtomicUsize,
+
+    // Taken by everyone popping off `message_queue` under
+    // `OverflowPolicy::DropOldest`, where senders pop messages too.
+    pop_lock: Mutex<()>,
+
     // Internal channel state. Consists of the number of messages stored in the
     // channel as well as a flag signalling that the channel is closed.
     state: AtomicUsize,
@@ -292,11 +469,39 @@ struct BoundedInner<T> {
     // Atomic, FIFO queue used to send parked task handles to the receiver.
     parked_queue: Queue<Arc<Mutex<SenderTask>>>,
 
+    // Taken by everyone popping off `parked_queue` of a rendezvous channel,
+    // where a sender closing the channel pops task handles too.
+    unpark_lock: Mutex<()>,
+
+    // Free slots of the channel, shared by all senders.
+    semaphore: Semaphore,
+
     // Number of senders in existence
     num_senders: AtomicUsize,
 
     // Handle to the receiver's task.
     recv_task: AtomicWaker,
+
+    // Handle to the task waiting for the channel to be closed.
+    close_task: AtomicWaker,
+
+    // Set by `close_with` before the channel is closed.
+    close_reason: Mutex<Option<CloseReason>>,
+}
+
+// Semaphore-style counter of the free slots of a bounded channel.
+#[derive(Debug)]
+struct Semaphore {
+    // Number of permits not held by any sender or message.
+    permits: AtomicUsize,
+
+    // Number of senders in `waiters`, so that giving a permit back only has
+    // to take the lock when somebody is waiting for it.
+    num_waiters: AtomicUsize,
+
+    // Senders waiting for permits, in the order they started waiting, along
+    // with the number of permits they wait for.
+    waiters: Mutex<VecDeque<(Arc<Mutex<SenderTask>>, usize)>>,
 }
 
 // Struct representation of `Inner::state`.
@@ -320,7 +525,7 @@ const INIT_STATE: usize = OPEN_MASK;
 const MAX_CAPACITY: usize = !(OPEN_MASK);
 
 // The maximum requested buffer size must be less than the maximum capacity of
-// a channel. This is because each sender gets a guaranteed slot.
+// a channel. This also bounds the number of senders.
 const MAX_BUFFER: usize = MAX_CAPACITY >> 1;
 
 // Sent to the consumer to wake up blocked producers
@@ -328,11 +533,15 @@ const MAX_BUFFER: usize = MAX_CAPACITY >> 1;
 struct SenderTask {
     task: Option<Waker>,
     is_parked: bool,
+
+    // Number of permits handed to this sender while it was queued on the
+    // semaphore, which the sender has not taken yet.
+    num_permits: usize,
 }
 
 impl SenderTask {
     fn new() -> Self {
-        Self { task: None, is_parked: false }
+        Self { task: None, is_parked: false, num_permits: 0 }
     }
 
     fn notify(&mut self) {
@@ -344,38 +553,119 @@ impl SenderTask {
     }
 }
 
+/// What the senders of a bounded mpsc channel do with a message that does not
+/// fit in the channel.
+///
+/// This is passed to [`channel_with_policy`](channel_with_policy).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for the receiver to make room, as [`channel`](channel) does.
+    Block,
+
+    /// Drop the oldest message in the channel to make room.
+    DropOldest,
+
+    /// Drop the message that does not fit.
+    DropNewest,
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        Self::Block
+    }
+}
+
+impl OverflowPolicy {
+    // The number of messages a channel with this policy can hold, counting
+    // the message being handed over by a rendezvous channel.
+    fn capacity(self, buffer: usize) -> usize {
+        match self {
+            Self::Block => cmp::max(buffer, 1),
+            Self::DropOldest | Self::DropNewest => buffer,
+        }
+    }
+}
+
 /// Creates a bounded mpsc channel for communicating between asynchronous tasks.
 ///
 /// Being bounded, this channel provides backpressure to ensure that the sender
-/// outpaces the receiver by only a limited amount. The channel's capacity is
-/// equal to `buffer + num-senders`. In other words, each sender gets a
-/// guaranteed slot in the channel capacity, and on top of that there are
-/// `buffer` "first come, first serve" slots available to all senders.
+/// outpaces the receiver by only a limited amount. The channel holds at most
+/// `buffer` messages, no matter how many senders exist. Senders waiting for
+/// capacity are served in the order they started waiting.
+///
+/// A `buffer` of zero gives a rendezvous channel: a single message can be in
+/// flight, and flushing the sender that sent it, as sending it with
+/// `SinkExt::send` does, waits until the receiver has read it.
 ///
 /// The [`Receiver`](Receiver) returned implements the
 /// [`Stream`](futures_core::stream::Stream) trait, while [`Sender`](Sender) implements
 /// `Sink`.
 pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with_policy(buffer, OverflowPolicy::Block)
+}
+
+/// Creates a bounded mpsc channel which handles a full buffer according to
+/// `policy`.
+///
+/// With [`OverflowPolicy::Block`](OverflowPolicy::Block), this is the same as
+/// [`channel`](channel).
+///
+/// With the other policies, the channel holds at most `buffer` messages and
+/// never applies backpressure: `poll_ready` is always ready while the receiver
+/// is alive, and sending a message that does not fit drops either the oldest
+/// message in the channel or the new one. The dropped messages are counted by
+/// [`Receiver::dropped_count`](Receiver::dropped_count).
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero and `policy` drops messages.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::mpsc::{self, OverflowPolicy};
+///
+/// let (mut tx, mut rx) = mpsc::channel_with_policy(2, OverflowPolicy::DropOldest);
+///
+/// for sample in 0..5 {
+///     tx.try_send(sample).unwrap();
+/// }
+/// assert_eq!(rx.try_next().unwrap(), Some(3));
+/// assert_eq!(rx.try_next().unwrap(), Some(4));
+/// assert_eq!(rx.dropped_count(), 3);
+/// ```
+pub fn channel_with_policy<T>(buffer: usize, policy: OverflowPolicy) -> (Sender<T>, Receiver<T>) {
     // Check that the requested buffer size does not exceed the maximum buffer
     // size permitted by the system.
     assert!(buffer < MAX_BUFFER, "requested buffer size too large");
 
+    let capacity = policy.capacity(buffer);
+    assert!(capacity > 0, "a channel dropping messages needs a buffer");
+
     let inner = Arc::new(BoundedInner {
         buffer,
+        policy,
+        num_dropped: AtomicUsize::new(0),
+        pop_lock: Mutex::new(()),
         state: AtomicUsize::new(INIT_STATE),
         message_queue: Queue::new(),
         parked_queue: Queue::new(),
+        unpark_lock: Mutex::new(()),
+        semaphore: Semaphore::new(capacity),
         num_senders: AtomicUsize::new(1),
         recv_task: AtomicWaker::new(),
+        close_task: AtomicWaker::new(),
+        close_reason: Mutex::new(None),
     });
 
     let tx = BoundedSenderInner {
         inner: inner.clone(),
         sender_task: Arc::new(Mutex::new(SenderTask::new())),
         maybe_parked: false,
+        num_permits: 0,
     };
 
-    let rx = Receiver { inner: Some(inner) };
+    let rx = Receiver { inner: Some(inner), num_dropped: 0, close_reason: None };
 
     (Sender(Some(tx)), rx)
 }
@@ -396,11 +686,13 @@ pub fn unbounded<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
         message_queue: Queue::new(),
         num_senders: AtomicUsize::new(1),
         recv_task: AtomicWaker::new(),
+        close_task: AtomicWaker::new(),
+        close_reason: Mutex::new(None),
     });
 
     let tx = UnboundedSenderInner { inner: inner.clone() };
 
-    let rx = UnboundedReceiver { inner: Some(inner) };
+    let rx = UnboundedReceiver { inner: Some(inner), close_reason: None };
 
     (UnboundedSender(Some(tx)), rx)
 }
@@ -417,7 +709,7 @@ impl<T> UnboundedSenderInner<T> {
         if state.is_open {
             Poll::Ready(Ok(()))
         } else {
-            Poll::Ready(Err(SendError { kind: SendErrorKind::Disconnected }))
+            Poll::Ready(Err(self.inner.send_error()))
         }
     }
 
@@ -492,6 +784,7 @@ impl<T> UnboundedSenderInner<T> {
 
         self.inner.set_closed();
         self.inner.recv_task.wake();
+        self.inner.close_task.wake();
     }
 }
 
@@ -501,11 +794,48 @@ impl<T> BoundedSenderInner<T> {
     fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
         // If the sender is currently blocked, reject the message
         if !self.poll_unparked(None).is_ready() {
-            return Err(TrySendError { err: SendError { kind: SendErrorKind::Full }, val: msg });
+            return Err(TrySendError { err: SendError::full(), val: msg });
+        }
+
+        // Under `OverflowPolicy::DropOldest`, messages are only pushed and
+        // popped with the pop lock held. A sender making room thus finds
+        // either a message to drop or a free permit.
+        let locked_inner = match self.inner.policy {
+            OverflowPolicy::DropOldest => Some(self.inner.clone()),
+            OverflowPolicy::Block | OverflowPolicy::DropNewest => None,
+        };
+        let guard = locked_inner.as_ref().map(|inner| inner.pop_lock.lock().unwrap());
+        let mut dropped = None;
+
+        // Unless `poll_ready` already reserved a permit, take a free one
+        if self.num_permits == 0 {
+            if !self.inner.semaphore.try_acquire_fair(1) {
+                if self.is_closed() {
+                    return Err(TrySendError { err: self.inner.send_error(), val: msg });
+                }
+
+                match self.inner.policy {
+                    OverflowPolicy::Block => {
+                        return Err(TrySendError { err: SendError::full(), val: msg });
+                    }
+                    OverflowPolicy::DropOldest => dropped = Some(self.drop_oldest()),
+                    OverflowPolicy::DropNewest => {
+                        self.inner.num_dropped.fetch_add(1, SeqCst);
+                        return Ok(());
+                    }
+                }
+            }
+            self.num_permits = 1;
         }
 
         // The channel has capacity to accept the message, so send it
-        self.do_send_b(msg)
+        let res = self.do_send_b(msg);
+
+        // The dropped message may run arbitrary code when dropped, so it is
+        // only dropped once the lock is released.
+        drop(guard);
+        drop(dropped);
+        res
     }
 
     // Do the send without failing.
@@ -513,7 +843,7 @@ impl<T> BoundedSenderInner<T> {
     fn do_send_b(&mut self, msg: T) -> Result<(), TrySendError<T>> {
         // Anyone calling do_send *should* make sure there is room first,
         // but assert here for tests as a sanity check.
-        debug_assert!(self.poll_unparked(None).is_ready());
+        debug_assert!(self.num_permits > 0);
 
         // First, increment the number of messages contained by the channel.
         // This operation will also atomically determine if the sender task
@@ -528,21 +858,16 @@ impl<T> BoundedSenderInner<T> {
                 // the configured buffer size
                 num_messages > self.inner.buffer
             }
-            None => {
-                return Err(TrySendError {
-                    err: SendError { kind: SendErrorKind::Disconnected },
-                    val: msg,
-                })
-            }
+            None => return Err(TrySendError { err: self.inner.send_error(), val: msg }),
         };
 
-        // If the channel has reached capacity, then the sender task needs to
-        // be parked. This will send the task handle on the parked task queue.
-        //
-        // However, when `do_send` is called while dropping the `Sender`,
-        // `task::current()` can't be called safely. In this case, in order to
-        // maintain internal consistency, a blank message is pushed onto the
-        // parked task queue.
+        // The permit now travels with the message, and is given back by the
+        // receiver once the message has been read.
+        self.num_permits -= 1;
+
+        // If the channel has gone over its buffer size, then the sender task
+        // needs to be parked until the receiver catches up. This will send
+        // the task handle on the parked task queue.
         if park_self {
             self.park();
         }
@@ -593,6 +918,21 @@ impl<T> BoundedSenderInner<T> {
         }
     }
 
+    // Makes room for a message by taking the oldest one out of the channel,
+    // and takes over its permit. Must be called with the pop lock held.
+    fn drop_oldest(&self) -> T {
+        // The channel is full and no sender is between taking a permit and
+        // pushing its message, so there is a message to take.
+        let msg = unsafe { self.inner.message_queue.pop_spin() }.unwrap();
+
+        // OPEN_MASK is highest bit, so it's unaffected by subtraction
+        // unless there's underflow, and we know there's no underflow
+        // because the message was counted.
+        self.inner.state.fetch_sub(1, SeqCst);
+        self.inner.num_dropped.fetch_add(1, SeqCst);
+        msg
+    }
+
     fn park(&mut self) {
         {
             let mut sender = self.sender_task.lock().unwrap();
@@ -611,7 +951,8 @@ impl<T> BoundedSenderInner<T> {
     }
 
     /// Polls the channel to determine if there is guaranteed capacity to send
-    /// at least one item without waiting.
+    /// at least one item without waiting, and reserves that capacity for this
+    /// sender.
     ///
     /// # Return value
     ///
@@ -623,14 +964,93 @@ impl<T> BoundedSenderInner<T> {
     ///   capacity is available;
     /// - `Poll::Ready(Err(SendError))` if the receiver has been dropped.
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.poll_reserve(cx, 1)
+    }
+
+    // Like `poll_ready`, but reserves capacity for `n` messages at once. `n`
+    // must not exceed the capacity of the channel.
+    fn poll_reserve(&mut self, cx: &mut Context<'_>, n: usize) -> Poll<Result<(), SendError>> {
+        let state = decode_state(self.inner.state.load(SeqCst));
+        if !state.is_open {
+            return Poll::Ready(Err(self.inner.send_error()));
+        }
+
+        if self.poll_unparked(Some(cx)).is_pending() {
+            return Poll::Pending;
+        }
+
+        // Channels dropping messages never apply backpressure, their policy
+        // is applied when the message is sent.
+        if self.inner.policy != OverflowPolicy::Block || self.num_permits >= n {
+            return Poll::Ready(Ok(()));
+        }
+
+        let wanted = n - self.num_permits;
+        if self.inner.semaphore.try_acquire_fair(wanted)
+            || self.inner.semaphore.acquire_or_park(&self.sender_task, cx.waker(), wanted)
+        {
+            self.num_permits = n;
+            return Poll::Ready(Ok(()));
+        }
+        self.maybe_parked = true;
+
+        // Check to make sure we weren't closed after we queued on the
+        // semaphore, as closing wakes only the senders already queued.
+        if self.is_closed() {
+            self.cancel_permit_wait();
+            return Poll::Ready(Err(self.inner.send_error()));
+        }
+
+        Poll::Pending
+    }
+
+    /// Polls whether the receiver has caught up with this sender, which is
+    /// only not the case if its last message took the channel over its buffer
+    /// size and no message has been read since.
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
         let state = decode_state(self.inner.state.load(SeqCst));
         if !state.is_open {
-            return Poll::Ready(Err(SendError { kind: SendErrorKind::Disconnected }));
+            return Poll::Ready(Err(self.inner.send_error()));
         }
 
         self.poll_unparked(Some(cx)).map(Ok)
     }
 
+    // Gives the permits reserved by `poll_ready` back without sending.
+    fn release_permit(&mut self) {
+        if self.num_permits > 0 {
+            self.inner.semaphore.release(self.num_permits);
+            self.num_permits = 0;
+        }
+    }
+
+    // Stops waiting for a permit on the semaphore. A permit that was handed
+    // to this sender in the meantime is kept. A sender waiting for the
+    // receiver to catch up keeps waiting.
+    fn cancel_permit_wait(&mut self) {
+        let was_queued = self.inner.semaphore.cancel(&self.sender_task);
+
+        let mut task = self.sender_task.lock().unwrap();
+        if was_queued || !task.is_parked {
+            task.is_parked = false;
+            task.task = None;
+            self.num_permits += mem::replace(&mut task.num_permits, 0);
+            self.maybe_parked = false;
+        }
+    }
+
+    // Gives back the permits this sender holds beyond the first `keep`,
+    // including those handed to it while it waits for more.
+    fn release_permits_over(&mut self, keep: usize) {
+        if self.maybe_parked {
+            self.cancel_permit_wait();
+        }
+        if self.num_permits > keep {
+            self.inner.semaphore.release(self.num_permits - keep);
+            self.num_permits = keep;
+        }
+    }
+
     /// Returns whether the senders send to the same receiver.
     fn same_receiver(&self, other: &Self) -> bool {
         Arc::ptr_eq(&self.inner, &other.inner)
@@ -661,6 +1081,11 @@ impl<T> BoundedSenderInner<T> {
 
         self.inner.set_closed();
         self.inner.recv_task.wake();
+        self.inner.close_task.wake();
+
+        // Wake up the other senders, so that they see the channel is closed.
+        self.inner.unpark_all();
+        self.inner.semaphore.close();
     }
 
     fn poll_unparked(&mut self, cx: Option<&mut Context<'_>>) -> Poll<()> {
@@ -672,6 +1097,9 @@ impl<T> BoundedSenderInner<T> {
 
             if !task.is_parked {
                 self.maybe_parked = false;
+
+                // Take the permits handed over by the semaphore, if any
+                self.num_permits += mem::replace(&mut task.num_permits, 0);
                 return Poll::Ready(());
             }
 
@@ -697,7 +1125,7 @@ impl<T> Sender<T> {
         if let Some(inner) = &mut self.0 {
             inner.try_send(msg)
         } else {
-            Err(TrySendError { err: SendError { kind: SendErrorKind::Disconnected }, val: msg })
+            Err(TrySendError { err: SendError::disconnected(), val: msg })
         }
     }
 
@@ -722,11 +1150,171 @@ impl<T> Sender<T> {
     ///   capacity, in which case the current task is queued to be notified once
     ///   capacity is available;
     /// - `Poll::Ready(Err(SendError))` if the receiver has been dropped.
+    ///
+    /// The capacity stays reserved for this sender until it sends a message or
+    /// is dropped, and is not available to other senders in the meantime. This
+    /// includes capacity handed to this sender while a `Send` future from
+    /// `SinkExt::send` was pending: dropping that future before it completes
+    /// leaves the capacity reserved for the next message of this sender.
     pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
-        let inner = self.0.as_mut().ok_or(SendError { kind: SendErrorKind::Disconnected })?;
+        let inner = self.0.as_mut().ok_or_else(SendError::disconnected)?;
         inner.poll_ready(cx)
     }
 
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        let inner = self.0.as_mut().ok_or_else(SendError::disconnected)?;
+        inner.poll_flush(cx)
+    }
+
+    // Reserves capacity for as many of the next `n` messages as fit in the
+    // channel at once, returning how many that is.
+    fn poll_reserve_many(
+        &mut self,
+        cx: &mut Context<'_>,
+        n: usize,
+    ) -> Poll<Result<usize, SendError>> {
+        let inner = self.0.as_mut().ok_or_else(SendError::disconnected)?;
+        let n = cmp::min(n, inner.inner.policy.capacity(inner.inner.buffer));
+        inner.poll_reserve(cx, n).map(|res| res.map(|()| n))
+    }
+
+    fn release_permit(&mut self) {
+        if let Some(inner) = &mut self.0 {
+            inner.release_permit();
+        }
+    }
+
+    // The number of permits this sender holds for its next messages.
+    fn num_permits(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.num_permits)
+    }
+
+    fn release_permits_over(&mut self, keep: usize) {
+        if let Some(inner) = &mut self.0 {
+            inner.release_permits_over(keep);
+        }
+    }
+
+    /// Sends a message on the channel, blocking the current thread until
+    /// there is capacity for it.
+    ///
+    /// This behaves like `block_on(sender.send(msg))`, except that the thread
+    /// is parked on the channel directly rather than running an executor. It
+    /// can therefore be called while an executor is running on the current
+    /// thread, but it should not be called from an async task, as it blocks
+    /// the thread the task runs on.
+    ///
+    /// Returns an error if the receiver has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use std::thread;
+    ///
+    /// let (mut tx, mut rx) = mpsc::channel(0);
+    ///
+    /// let t = thread::spawn(move || {
+    ///     for i in 0..3 {
+    ///         tx.send_blocking(i).unwrap();
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(rx.recv_blocking(), Some(0));
+    /// assert_eq!(rx.recv_blocking(), Some(1));
+    /// assert_eq!(rx.recv_blocking(), Some(2));
+    /// assert_eq!(rx.recv_blocking(), None);
+    /// t.join().unwrap();
+    /// ```
+    pub fn send_blocking(&mut self, msg: T) -> Result<(), SendError> {
+        blocking::block_on(|cx| self.poll_ready(cx))?;
+        self.start_send(msg)?;
+
+        match blocking::block_on(|cx| self.poll_flush(cx)) {
+            // The message was sent, whether it is received is out of our hands.
+            Err(ref e) if e.is_disconnected() => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Waits for capacity in the channel and reserves it for a single
+    /// message.
+    ///
+    /// The returned [`Permit`](Permit) sends the message without any chance
+    /// of failing for lack of capacity, so an expensive message only needs to
+    /// be built once it is certain to fit. Dropping the permit without
+    /// sending gives the capacity back.
+    ///
+    /// Channels created with a policy dropping messages never apply
+    /// backpressure, so this completes at once without reserving anything.
+    /// The message sent through the permit may then be dropped, or cause an
+    /// older one to be dropped, as the policy says.
+    ///
+    /// The future fails with a [`SendError`](SendError) if the receiver has
+    /// been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    /// use futures::stream::StreamExt;
+    ///
+    /// let (mut tx, mut rx) = mpsc::channel(1);
+    ///
+    /// block_on(async {
+    ///     let permit = tx.reserve().await.unwrap();
+    ///     permit.send(String::from("expensive"));
+    ///     assert_eq!(rx.next().await.as_deref(), Some("expensive"));
+    /// });
+    /// ```
+    pub fn reserve(&mut self) -> Reserve<'_, T> {
+        Reserve::new(self)
+    }
+
+    /// Waits for capacity in the channel and reserves it for a single
+    /// message, taking ownership of this `Sender`.
+    ///
+    /// This is like [`reserve`](Sender::reserve), except that the returned
+    /// [`OwnedPermit`](OwnedPermit) can be moved into another task. The
+    /// `Sender` is handed back once the permit is used or released.
+    pub fn reserve_owned(self) -> ReserveOwned<T> {
+        ReserveOwned::new(self)
+    }
+
+    /// Sends all messages of `items` in order, waiting for capacity for the
+    /// whole batch at once.
+    ///
+    /// Since the capacity is reserved up front, the messages of a batch are
+    /// not interleaved with the messages of other senders. A batch which is
+    /// larger than the capacity of the channel is sent in parts which each
+    /// fill the channel.
+    ///
+    /// The future fails with a [`SendError`](SendError) if the receiver has
+    /// been dropped, in which case the messages not sent yet are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    /// use futures::stream::StreamExt;
+    ///
+    /// let (mut tx, rx) = mpsc::channel(4);
+    ///
+    /// block_on(async {
+    ///     tx.send_batch(vec![1, 2, 3]).await.unwrap();
+    ///     drop(tx);
+    ///     assert_eq!(rx.collect::<Vec<_>>().await, [1, 2, 3]);
+    /// });
+    /// ```
+    pub fn send_batch<I>(&mut self, items: I) -> SendBatch<'_, T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        SendBatch::new(self, items.into_iter().collect())
+    }
+
     /// Returns whether this channel is closed without needing a context.
     pub fn is_closed(&self) -> bool {
         self.0.as_ref().map(BoundedSenderInner::is_closed).unwrap_or(true)
@@ -739,6 +1327,39 @@ impl<T> Sender<T> {
         }
     }
 
+    /// Closes this channel from the sender side like
+    /// [`close_channel`](Sender::close_channel), recording why.
+    ///
+    /// Once it has received all buffered messages, the receiver can get the
+    /// reason from [`close_reason`](Receiver::close_reason), and sends on the
+    /// closed channel fail with a [`SendError`](SendError) carrying it. If the
+    /// channel is closed already, the reason is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    /// use futures::stream::StreamExt;
+    ///
+    /// let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+    /// tx.try_send(1).unwrap();
+    /// tx.close_with("peer crashed");
+    ///
+    /// assert_eq!(block_on(rx.next()), Some(1));
+    /// assert_eq!(block_on(rx.next()), None);
+    /// assert_eq!(rx.close_reason().unwrap().to_string(), "peer crashed");
+    /// ```
+    pub fn close_with<E>(&mut self, reason: E)
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if let Some(inner) = &mut self.0 {
+            inner.inner.set_close_reason(close_reason(reason));
+            inner.close_channel();
+        }
+    }
+
     /// Disconnects this sender from the channel, closing it if there are no more senders left.
     pub fn disconnect(&mut self) {
         self.0 = None;
@@ -770,12 +1391,112 @@ impl<T> Sender<T> {
         let ptr = self.0.as_ref().map(|inner| inner.ptr());
         ptr.hash(hasher);
     }
+
+    /// Returns the number of messages in the channel.
+    ///
+    /// This is zero if this sender is disconnected.
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.len())
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the channel has no capacity left, so that sending
+    /// without a reservation would wait or, depending on the channel's
+    /// [`OverflowPolicy`](OverflowPolicy), drop a message.
+    ///
+    /// This is also true if this sender is disconnected.
+    pub fn is_full(&self) -> bool {
+        self.capacity() == 0
+    }
+
+    /// Returns the number of messages that can currently be sent without
+    /// waiting.
+    ///
+    /// This is the [maximum capacity](Sender::max_capacity) minus the messages
+    /// in the channel and the capacity reserved by senders, e.g. through
+    /// [`poll_ready`](Sender::poll_ready). It is zero if this sender is
+    /// disconnected.
+    ///
+    /// A rendezvous channel, created with a `buffer` of zero, has a capacity
+    /// of one while no message is being handed over, even though it does not
+    /// buffer any messages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    ///
+    /// let (mut tx, _rx) = mpsc::channel::<i32>(2);
+    /// assert_eq!(tx.max_capacity(), 2);
+    /// assert_eq!(tx.capacity(), 2);
+    ///
+    /// tx.try_send(1).unwrap();
+    /// assert_eq!(tx.len(), 1);
+    /// assert_eq!(tx.capacity(), 1);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.capacity())
+    }
+
+    /// Returns the number of messages the channel can hold.
+    ///
+    /// This is the `buffer` passed to [`channel`](channel) or
+    /// [`channel_with_policy`](channel_with_policy). It is zero if this sender
+    /// is disconnected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    ///
+    /// let (tx, _rx) = mpsc::channel::<i32>(1);
+    /// assert_eq!(tx.max_capacity(), 1);
+    /// ```
+    pub fn max_capacity(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.max_capacity())
+    }
+
+    /// Returns the number of senders of the channel, not counting
+    /// [`WeakSender`s](WeakSender).
+    ///
+    /// This is zero if this sender is disconnected.
+    pub fn sender_count(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.sender_count())
+    }
+
+    /// Creates a [`WeakSender`](WeakSender) for this channel, which does not
+    /// keep the channel open.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    /// use futures::stream::StreamExt;
+    ///
+    /// let (tx, mut rx) = mpsc::channel::<i32>(1);
+    /// let weak = tx.downgrade();
+    ///
+    /// weak.upgrade().unwrap().try_send(1).unwrap();
+    /// drop(tx);
+    /// assert_eq!(block_on(rx.next()), Some(1));
+    /// assert_eq!(block_on(rx.next()), None);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn downgrade(&self) -> WeakSender<T> {
+        let inner = self.0.as_ref().map(|inner| Arc::downgrade(&inner.inner));
+        WeakSender { inner: inner.unwrap_or_default() }
+    }
 }
 
 impl<T> UnboundedSender<T> {
     /// Check if the channel is ready to receive a message.
     pub fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), SendError>> {
-        let inner = self.0.as_ref().ok_or(SendError { kind: SendErrorKind::Disconnected })?;
+        let inner = self.0.as_ref().ok_or_else(SendError::disconnected)?;
         inner.poll_ready_nb()
     }
 
@@ -784,31 +1505,85 @@ impl<T> UnboundedSender<T> {
         self.0.as_ref().map(UnboundedSenderInner::is_closed).unwrap_or(true)
     }
 
-    /// Closes this channel from the sender side, preventing any new messages.
-    pub fn close_channel(&self) {
-        if let Some(inner) = &self.0 {
-            inner.close_channel();
-        }
+    /// Returns the number of messages in the channel.
+    ///
+    /// This is zero if this sender is disconnected.
+    pub fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.len())
     }
 
-    /// Disconnects this sender from the channel, closing it if there are no more senders left.
-    pub fn disconnect(&mut self) {
-        self.0 = None;
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    // Do the send without parking current task.
-    fn do_send_nb(&self, msg: T) -> Result<(), TrySendError<T>> {
-        if let Some(inner) = &self.0 {
-            if inner.inc_num_messages().is_some() {
-                inner.queue_push_and_signal(msg);
-                return Ok(());
-            }
-        }
+    /// Returns the number of messages that can currently be sent.
+    ///
+    /// An unbounded channel is only limited by the number of messages it can
+    /// keep track of, so this is [`max_capacity`](UnboundedSender::max_capacity)
+    /// minus the messages in the channel. It is zero if this sender is
+    /// disconnected.
+    pub fn capacity(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.capacity())
+    }
 
-        Err(TrySendError { err: SendError { kind: SendErrorKind::Disconnected }, val: msg })
+    /// Returns the number of messages the channel can keep track of.
+    ///
+    /// This is zero if this sender is disconnected.
+    pub fn max_capacity(&self) -> usize {
+        self.0.as_ref().map_or(0, |_| MAX_CAPACITY)
     }
 
-    /// Send a message on the channel.
+    /// Returns the number of senders of the channel, not counting
+    /// [`WeakUnboundedSender`s](WeakUnboundedSender).
+    ///
+    /// This is zero if this sender is disconnected.
+    pub fn sender_count(&self) -> usize {
+        self.0.as_ref().map_or(0, |inner| inner.inner.sender_count())
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&self) {
+        if let Some(inner) = &self.0 {
+            inner.close_channel();
+        }
+    }
+
+    /// Closes this channel from the sender side like
+    /// [`close_channel`](UnboundedSender::close_channel), recording why.
+    ///
+    /// See [`Sender::close_with`](Sender::close_with).
+    pub fn close_with<E>(&self, reason: E)
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if let Some(inner) = &self.0 {
+            inner.inner.set_close_reason(close_reason(reason));
+            inner.close_channel();
+        }
+    }
+
+    /// Disconnects this sender from the channel, closing it if there are no more senders left.
+    pub fn disconnect(&mut self) {
+        self.0 = None;
+    }
+
+    // Do the send without parking current task.
+    fn do_send_nb(&self, msg: T) -> Result<(), TrySendError<T>> {
+        let inner = match &self.0 {
+            Some(inner) => inner,
+            None => return Err(TrySendError { err: SendError::disconnected(), val: msg }),
+        };
+
+        if inner.inc_num_messages().is_some() {
+            inner.queue_push_and_signal(msg);
+            Ok(())
+        } else {
+            Err(TrySendError { err: inner.inner.send_error(), val: msg })
+        }
+    }
+
+    /// Send a message on the channel.
     ///
     /// This method should only be called after `poll_ready` has been used to
     /// verify that the channel is ready to receive a message.
@@ -851,6 +1626,61 @@ impl<T> UnboundedSender<T> {
         let ptr = self.0.as_ref().map(|inner| inner.ptr());
         ptr.hash(hasher);
     }
+
+    /// Creates a [`WeakUnboundedSender`](WeakUnboundedSender) for this
+    /// channel, which does not keep the channel open.
+    pub fn downgrade(&self) -> WeakUnboundedSender<T> {
+        let inner = self.0.as_ref().map(|inner| Arc::downgrade(&inner.inner));
+        WeakUnboundedSender { inner: inner.unwrap_or_default() }
+    }
+}
+
+impl<T> WeakSender<T> {
+    /// Tries to turn this `WeakSender` into a [`Sender`](Sender).
+    ///
+    /// Returns `None` if all senders of the channel have been dropped, in
+    /// which case the channel is closed for good.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let inner = self.inner.upgrade()?;
+        if !inc_num_senders(&inner.num_senders) {
+            return None;
+        }
+
+        Some(Sender(Some(BoundedSenderInner {
+            inner,
+            sender_task: Arc::new(Mutex::new(SenderTask::new())),
+            maybe_parked: false,
+            num_permits: 0,
+        })))
+    }
+}
+
+impl<T> WeakUnboundedSender<T> {
+    /// Tries to turn this `WeakUnboundedSender` into an
+    /// [`UnboundedSender`](UnboundedSender).
+    ///
+    /// Returns `None` if all senders of the channel have been dropped, in
+    /// which case the channel is closed for good.
+    pub fn upgrade(&self) -> Option<UnboundedSender<T>> {
+        let inner = self.inner.upgrade()?;
+        if !inc_num_senders(&inner.num_senders) {
+            return None;
+        }
+
+        Some(UnboundedSender(Some(UnboundedSenderInner { inner })))
+    }
+}
+
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Clone for WeakUnboundedSender<T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
 }
 
 impl<T> Clone for Sender<T> {
@@ -902,11 +1732,11 @@ impl<T> Clone for BoundedSenderInner<T> {
 
         loop {
             // If the maximum number of senders has been reached, then fail
-            if curr == self.inner.max_senders() {
+            if curr == MAX_BUFFER {
                 panic!("cannot clone `Sender` -- too many outstanding senders");
             }
 
-            debug_assert!(curr < self.inner.max_senders());
+            debug_assert!(curr < MAX_BUFFER);
 
             let next = curr + 1;
             match self.inner.num_senders.compare_exchange(curr, next, SeqCst, SeqCst) {
@@ -917,6 +1747,7 @@ impl<T> Clone for BoundedSenderInner<T> {
                         inner: self.inner.clone(),
                         sender_task: Arc::new(Mutex::new(SenderTask::new())),
                         maybe_parked: false,
+                        num_permits: 0,
                     };
                 }
                 Err(actual) => curr = actual,
@@ -938,6 +1769,13 @@ impl<T> Drop for UnboundedSenderInner<T> {
 
 impl<T> Drop for BoundedSenderInner<T> {
     fn drop(&mut self) {
+        // Give back the permit this sender holds or is about to be handed, so
+        // that it is not lost to the other senders.
+        if self.maybe_parked {
+            self.cancel_permit_wait();
+        }
+        self.release_permit();
+
         // Ordering between variables don't matter here
         let prev = self.inner.num_senders.fetch_sub(1, SeqCst);
 
@@ -964,9 +1802,50 @@ impl<T> Receiver<T> {
 
             // Wake up any threads waiting as they'll see that we've closed the
             // channel and will continue on their merry way.
-            while let Some(task) = unsafe { inner.parked_queue.pop_spin() } {
-                task.lock().unwrap().notify();
-            }
+            inner.unpark_all();
+            inner.semaphore.close();
+        }
+    }
+
+    /// Closes the receiving half of a channel like
+    /// [`close`](Receiver::close), recording why.
+    ///
+    /// Sends on the closed channel, including the ones waiting for capacity,
+    /// fail with a [`SendError`](SendError) carrying the reason. If the
+    /// channel is closed already, the reason is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    ///
+    /// let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+    /// rx.close_with("shutting down");
+    ///
+    /// let err = tx.try_send(1).unwrap_err();
+    /// assert!(err.is_disconnected());
+    /// assert_eq!(err.reason().unwrap().to_string(), "shutting down");
+    /// ```
+    pub fn close_with<E>(&mut self, reason: E)
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if let Some(inner) = &self.inner {
+            inner.set_close_reason(close_reason(reason));
+        }
+        self.close();
+    }
+
+    /// Returns the reason the channel was closed with by
+    /// [`close_with`](Sender::close_with) on either half, if any.
+    ///
+    /// This stays available after the stream has ended, so that a channel
+    /// whose senders were dropped can be told apart from one which was closed
+    /// because of an error.
+    pub fn close_reason(&self) -> Option<Arc<dyn std::error::Error + Send + Sync>> {
+        match &self.inner {
+            Some(inner) => inner.close_reason(),
+            None => self.close_reason.clone(),
         }
     }
 
@@ -987,59 +1866,280 @@ impl<T> Receiver<T> {
         }
     }
 
+    /// Receives the next message, blocking the current thread until one is
+    /// available.
+    ///
+    /// Returns `None` once the channel is closed and no messages are left.
+    ///
+    /// The thread is parked on the channel directly rather than running an
+    /// executor, so this can be called while an executor is running on the
+    /// current thread. It should not be called from an async task though, as
+    /// it blocks the thread the task runs on.
+    pub fn recv_blocking(&mut self) -> Option<T> {
+        blocking::block_on(|cx| Pin::new(&mut *self).poll_next(cx))
+    }
+
+    /// Returns the number of messages that the senders dropped because the
+    /// channel was full.
+    ///
+    /// This is always zero unless the channel was created by
+    /// [`channel_with_policy`](channel_with_policy) with a policy that drops
+    /// messages.
+    pub fn dropped_count(&self) -> usize {
+        match &self.inner {
+            Some(inner) => inner.num_dropped.load(SeqCst),
+            None => self.num_dropped,
+        }
+    }
+
+    /// Returns the number of messages in the channel.
+    pub fn len(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.len())
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of messages that can currently be sent without
+    /// waiting.
+    ///
+    /// See [`Sender::capacity`](Sender::capacity). Once the channel is closed
+    /// and all messages are received, this is zero.
+    pub fn capacity(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.capacity())
+    }
+
+    /// Returns the number of messages the channel can hold.
+    ///
+    /// See [`Sender::max_capacity`](Sender::max_capacity). Once the channel is
+    /// closed and all messages are received, this is zero.
+    pub fn max_capacity(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.max_capacity())
+    }
+
+    /// Returns the number of senders of the channel, not counting
+    /// [`WeakSender`s](WeakSender).
+    pub fn sender_count(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.sender_count())
+    }
+
+    /// Polls whether the channel is closed.
+    ///
+    /// This returns `Poll::Ready` once every sender is gone, one of them called
+    /// [`close_channel`](Sender::close_channel), or this receiver was closed.
+    /// Messages which are still buffered can be received afterwards.
+    ///
+    /// The task waiting for the channel to be closed is kept apart from the
+    /// task receiving messages, so each of them is woken only for its own
+    /// event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    /// use futures::future::poll_fn;
+    ///
+    /// let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+    /// tx.try_send(1).unwrap();
+    /// drop(tx);
+    ///
+    /// block_on(poll_fn(|cx| rx.poll_closed(cx)));
+    /// assert_eq!(rx.try_next().unwrap(), Some(1));
+    /// ```
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &self.inner {
+            Some(inner) => inner.poll_closed(cx),
+            None => Poll::Ready(()),
+        }
+    }
+
+    /// Waits for the channel to be closed.
+    ///
+    /// This is the future counterpart of
+    /// [`poll_closed`](Receiver::poll_closed).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    ///
+    /// let (tx, mut rx) = mpsc::channel::<i32>(1);
+    /// drop(tx);
+    ///
+    /// block_on(rx.closed());
+    /// ```
+    pub fn closed(&mut self) -> Closed<'_, T> {
+        Closed::new(ReceiverMut::Bounded(self))
+    }
+
+    /// Receives up to `limit` messages at once, appending them to `buf`.
+    ///
+    /// The returned future resolves to the number of messages received, as
+    /// soon as at least one is available. It only resolves to zero once the
+    /// channel is closed and no messages are left, or if `limit` is zero.
+    ///
+    /// Receiving messages in batches updates the channel state once per batch
+    /// rather than once per message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    ///
+    /// let (mut tx, mut rx) = mpsc::channel(8);
+    /// for i in 0..5 {
+    ///     tx.try_send(i).unwrap();
+    /// }
+    /// drop(tx);
+    ///
+    /// let mut buf = Vec::new();
+    /// assert_eq!(block_on(rx.recv_many(&mut buf, 3)), 3);
+    /// assert_eq!(block_on(rx.recv_many(&mut buf, 3)), 2);
+    /// assert_eq!(block_on(rx.recv_many(&mut buf, 3)), 0);
+    /// assert_eq!(buf, [0, 1, 2, 3, 4]);
+    /// ```
+    pub fn recv_many<'a>(&'a mut self, buf: &'a mut Vec<T>, limit: usize) -> RecvMany<'a, T> {
+        RecvMany::new(ReceiverMut::Bounded(self), buf, limit)
+    }
+
+    /// Polls for up to `limit` messages at once, appending them to `buf`.
+    ///
+    /// This is the polling counterpart of [`recv_many`](Receiver::recv_many).
+    /// It returns `Poll::Ready` with the number of messages received as soon
+    /// as at least one is available, and `Poll::Ready(0)` once the channel is
+    /// closed and no messages are left, or if `limit` is zero.
+    pub fn poll_recv_many(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut Vec<T>,
+        limit: usize,
+    ) -> Poll<usize> {
+        if limit == 0 {
+            return Poll::Ready(0);
+        }
+
+        // Try to read messages off of the message queue.
+        match self.next_messages(buf, limit) {
+            Poll::Ready(n) => Poll::Ready(n),
+            Poll::Pending => {
+                // There are no messages to read, in this case, park.
+                self.inner.as_ref().unwrap().recv_task.register(cx.waker());
+                // Check queue again after parking to prevent race condition:
+                // a message could be added to the queue after previous
+                // `next_messages` before `register` call.
+                self.next_messages(buf, limit)
+            }
+        }
+    }
+
     fn next_message(&mut self) -> Poll<Option<T>> {
         let inner = match self.inner.as_mut() {
             None => return Poll::Ready(None),
             Some(inner) => inner,
         };
         // Pop off a message
-        match unsafe { inner.message_queue.pop_spin() } {
+        match inner.pop_message() {
             Some(msg) => {
-                // If there are any parked task handles in the parked queue,
-                // pop one and unpark it.
-                self.unpark_one();
-
-                // Decrement number of messages
-                self.dec_num_messages();
-
+                self.finish_recv(1);
                 Poll::Ready(Some(msg))
             }
-            None => {
-                let state = decode_state(inner.state.load(SeqCst));
-                if state.is_closed() {
-                    // If closed flag is set AND there are no pending messages
-                    // it means end of stream
-                    self.inner = None;
-                    Poll::Ready(None)
-                } else {
-                    // If queue is open, we need to return Pending
-                    // to be woken up when new messages arrive.
-                    // If queue is closed but num_messages is non-zero,
-                    // it means that senders updated the state,
-                    // but didn't put message to queue yet,
-                    // so we need to park until sender unparks the task
-                    // after queueing the message.
-                    Poll::Pending
-                }
+            None => self.poll_end().map(|()| None),
+        }
+    }
+
+    // Like `next_message`, but pops up to `limit` messages at once.
+    fn next_messages(&mut self, buf: &mut Vec<T>, limit: usize) -> Poll<usize> {
+        let inner = match self.inner.as_mut() {
+            None => return Poll::Ready(0),
+            Some(inner) => inner,
+        };
+
+        let mut n = 0;
+        while n < limit {
+            match inner.pop_message() {
+                Some(msg) => buf.push(msg),
+                None => break,
             }
+            n += 1;
+        }
+
+        if n > 0 {
+            self.finish_recv(n);
+            Poll::Ready(n)
+        } else {
+            self.poll_end().map(|()| 0)
+        }
+    }
+
+    // Updates the channel after `n` messages have been popped off the queue.
+    fn finish_recv(&mut self, n: usize) {
+        // If there are any parked task handles in the parked queue,
+        // pop one for each message and unpark it.
+        for _ in 0..n {
+            self.unpark_one();
+        }
+
+        // Under `OverflowPolicy::DropOldest`, this was done when popping.
+        if self.inner.as_ref().map_or(false, |inner| inner.policy == OverflowPolicy::DropOldest) {
+            return;
+        }
+
+        // Decrement number of messages
+        self.dec_num_messages(n);
+
+        // Give the messages' permits back to the senders
+        self.release_permits(n);
+    }
+
+    // Checks whether the stream has ended once the message queue is empty.
+    fn poll_end(&mut self) -> Poll<()> {
+        let inner = self.inner.as_ref().unwrap();
+        let state = decode_state(inner.state.load(SeqCst));
+        if state.is_closed() {
+            // If closed flag is set AND there are no pending messages
+            // it means end of stream
+            self.num_dropped = inner.num_dropped.load(SeqCst);
+            self.close_reason = inner.close_reason();
+            self.inner = None;
+            Poll::Ready(())
+        } else {
+            // If queue is open, we need to return Pending
+            // to be woken up when new messages arrive.
+            // If queue is closed but num_messages is non-zero,
+            // it means that senders updated the state,
+            // but didn't put message to queue yet,
+            // so we need to park until sender unparks the task
+            // after queueing the message.
+            Poll::Pending
         }
     }
 
     // Unpark a single task handle if there is one pending in the parked queue
     fn unpark_one(&mut self) {
         if let Some(inner) = &mut self.inner {
-            if let Some(task) = unsafe { inner.parked_queue.pop_spin() } {
+            if let Some(task) = inner.pop_parked_task() {
                 task.lock().unwrap().notify();
             }
         }
     }
 
-    fn dec_num_messages(&self) {
+    fn dec_num_messages(&self, n: usize) {
         if let Some(inner) = &self.inner {
             // OPEN_MASK is highest bit, so it's unaffected by subtraction
             // unless there's underflow, and we know there's no underflow
-            // because number of messages at this point is always > 0.
-            inner.state.fetch_sub(1, SeqCst);
+            // because number of messages at this point is always >= n.
+            inner.state.fetch_sub(n, SeqCst);
+        }
+    }
+
+    fn release_permits(&self, n: usize) {
+        if let Some(inner) = &self.inner {
+            inner.semaphore.release(n);
         }
     }
 }
@@ -1118,6 +2218,31 @@ impl<T> UnboundedReceiver<T> {
         }
     }
 
+    /// Closes the receiving half of a channel like
+    /// [`close`](UnboundedReceiver::close), recording why.
+    ///
+    /// See [`Receiver::close_with`](Receiver::close_with).
+    pub fn close_with<E>(&mut self, reason: E)
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        if let Some(inner) = &self.inner {
+            inner.set_close_reason(close_reason(reason));
+        }
+        self.close();
+    }
+
+    /// Returns the reason the channel was closed with by
+    /// [`close_with`](UnboundedSender::close_with) on either half, if any.
+    ///
+    /// See [`Receiver::close_reason`](Receiver::close_reason).
+    pub fn close_reason(&self) -> Option<Arc<dyn std::error::Error + Send + Sync>> {
+        match &self.inner {
+            Some(inner) => inner.close_reason(),
+            None => self.close_reason.clone(),
+        }
+    }
+
     /// Tries to receive the next message without notifying a context if empty.
     ///
     /// It is not recommended to call this function from inside of a future,
@@ -1135,6 +2260,120 @@ impl<T> UnboundedReceiver<T> {
         }
     }
 
+    /// Receives the next message, blocking the current thread until one is
+    /// available.
+    ///
+    /// Returns `None` once the channel is closed and no messages are left.
+    ///
+    /// The thread is parked on the channel directly rather than running an
+    /// executor, so this can be called while an executor is running on the
+    /// current thread. It should not be called from an async task though, as
+    /// it blocks the thread the task runs on.
+    pub fn recv_blocking(&mut self) -> Option<T> {
+        blocking::block_on(|cx| Pin::new(&mut *self).poll_next(cx))
+    }
+
+    /// Returns the number of messages in the channel.
+    pub fn len(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.len())
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of messages that can currently be sent.
+    ///
+    /// See [`UnboundedSender::capacity`](UnboundedSender::capacity). Once the
+    /// channel is closed and all messages are received, this is zero.
+    pub fn capacity(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.capacity())
+    }
+
+    /// Returns the number of messages the channel can keep track of.
+    ///
+    /// Once the channel is closed and all messages are received, this is zero.
+    pub fn max_capacity(&self) -> usize {
+        self.inner.as_ref().map_or(0, |_| MAX_CAPACITY)
+    }
+
+    /// Returns the number of senders of the channel, not counting
+    /// [`WeakUnboundedSender`s](WeakUnboundedSender).
+    pub fn sender_count(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.sender_count())
+    }
+
+    /// Polls whether the channel is closed.
+    ///
+    /// See [`Receiver::poll_closed`](Receiver::poll_closed).
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &self.inner {
+            Some(inner) => inner.poll_closed(cx),
+            None => Poll::Ready(()),
+        }
+    }
+
+    /// Waits for the channel to be closed.
+    ///
+    /// See [`Receiver::closed`](Receiver::closed).
+    pub fn closed(&mut self) -> Closed<'_, T> {
+        Closed::new(ReceiverMut::Unbounded(self))
+    }
+
+    /// Receives up to `limit` messages at once, appending them to `buf`.
+    ///
+    /// See [`Receiver::recv_many`](Receiver::recv_many).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use futures::executor::block_on;
+    ///
+    /// let (tx, mut rx) = mpsc::unbounded();
+    /// for i in 0..5 {
+    ///     tx.unbounded_send(i).unwrap();
+    /// }
+    /// drop(tx);
+    ///
+    /// let mut buf = Vec::new();
+    /// assert_eq!(block_on(rx.recv_many(&mut buf, 3)), 3);
+    /// assert_eq!(block_on(rx.recv_many(&mut buf, 3)), 2);
+    /// assert_eq!(block_on(rx.recv_many(&mut buf, 3)), 0);
+    /// assert_eq!(buf, [0, 1, 2, 3, 4]);
+    /// ```
+    pub fn recv_many<'a>(&'a mut self, buf: &'a mut Vec<T>, limit: usize) -> RecvMany<'a, T> {
+        RecvMany::new(ReceiverMut::Unbounded(self), buf, limit)
+    }
+
+    /// Polls for up to `limit` messages at once, appending them to `buf`.
+    ///
+    /// See [`Receiver::poll_recv_many`](Receiver::poll_recv_many).
+    pub fn poll_recv_many(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut Vec<T>,
+        limit: usize,
+    ) -> Poll<usize> {
+        if limit == 0 {
+            return Poll::Ready(0);
+        }
+
+        // Try to read messages off of the message queue.
+        match self.next_messages(buf, limit) {
+            Poll::Ready(n) => Poll::Ready(n),
+            Poll::Pending => {
+                // There are no messages to read, in this case, park.
+                self.inner.as_ref().unwrap().recv_task.register(cx.waker());
+                // Check queue again after parking to prevent race condition:
+                // a message could be added to the queue after previous
+                // `next_messages` before `register` call.
+                self.next_messages(buf, limit)
+            }
+        }
+    }
+
     fn next_message(&mut self) -> Poll<Option<T>> {
         let inner = match self.inner.as_mut() {
             None => return Poll::Ready(None),
@@ -1144,37 +2383,66 @@ impl<T> UnboundedReceiver<T> {
         match unsafe { inner.message_queue.pop_spin() } {
             Some(msg) => {
                 // Decrement number of messages
-                self.dec_num_messages();
+                self.dec_num_messages(1);
 
                 Poll::Ready(Some(msg))
             }
-            None => {
-                let state = decode_state(inner.state.load(SeqCst));
-                if state.is_closed() {
-                    // If closed flag is set AND there are no pending messages
-                    // it means end of stream
-                    self.inner = None;
-                    Poll::Ready(None)
-                } else {
-                    // If queue is open, we need to return Pending
-                    // to be woken up when new messages arrive.
-                    // If queue is closed but num_messages is non-zero,
-                    // it means that senders updated the state,
-                    // but didn't put message to queue yet,
-                    // so we need to park until sender unparks the task
-                    // after queueing the message.
-                    Poll::Pending
-                }
+            None => self.poll_end().map(|()| None),
+        }
+    }
+
+    // Like `next_message`, but pops up to `limit` messages at once.
+    fn next_messages(&mut self, buf: &mut Vec<T>, limit: usize) -> Poll<usize> {
+        let inner = match self.inner.as_mut() {
+            None => return Poll::Ready(0),
+            Some(inner) => inner,
+        };
+
+        let mut n = 0;
+        while n < limit {
+            match unsafe { inner.message_queue.pop_spin() } {
+                Some(msg) => buf.push(msg),
+                None => break,
             }
+            n += 1;
+        }
+
+        if n > 0 {
+            self.dec_num_messages(n);
+            Poll::Ready(n)
+        } else {
+            self.poll_end().map(|()| 0)
+        }
+    }
+
+    // Checks whether the stream has ended once the message queue is empty.
+    fn poll_end(&mut self) -> Poll<()> {
+        let inner = self.inner.as_ref().unwrap();
+        let state = decode_state(inner.state.load(SeqCst));
+        if state.is_closed() {
+            // If closed flag is set AND there are no pending messages
+            // it means end of stream
+            self.close_reason = inner.close_reason();
+            self.inner = None;
+            Poll::Ready(())
+        } else {
+            // If queue is open, we need to return Pending
+            // to be woken up when new messages arrive.
+            // If queue is closed but num_messages is non-zero,
+            // it means that senders updated the state,
+            // but didn't put message to queue yet,
+            // so we need to park until sender unparks the task
+            // after queueing the message.
+            Poll::Pending
         }
     }
 
-    fn dec_num_messages(&self) {
+    fn dec_num_messages(&self, n: usize) {
         if let Some(inner) = &self.inner {
             // OPEN_MASK is highest bit, so it's unaffected by subtraction
             // unless there's underflow, and we know there's no underflow
-            // because number of messages at this point is always > 0.
-            inner.state.fetch_sub(1, SeqCst);
+            // because number of messages at this point is always >= n.
+            inner.state.fetch_sub(n, SeqCst);
         }
     }
 }
@@ -1255,13 +2523,194 @@ impl<T> UnboundedInner<T> {
 
         self.state.fetch_and(!OPEN_MASK, SeqCst);
     }
+
+    // Records why the channel is being closed, unless it is closed already.
+    fn set_close_reason(&self, reason: CloseReason) {
+        let mut close_reason = self.close_reason.lock().unwrap();
+        if close_reason.is_none() && decode_state(self.state.load(SeqCst)).is_open {
+            *close_reason = Some(reason);
+        }
+    }
+
+    fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason.lock().unwrap().clone()
+    }
+
+    // The error for a send on the closed channel.
+    fn send_error(&self) -> SendError {
+        SendError::closed(self.close_reason())
+    }
+
+    fn len(&self) -> usize {
+        decode_state(self.state.load(SeqCst)).num_messages
+    }
+
+    // The channel is only limited by the number of messages its state can
+    // keep track of.
+    fn capacity(&self) -> usize {
+        MAX_CAPACITY - self.len()
+    }
+
+    fn sender_count(&self) -> usize {
+        self.num_senders.load(SeqCst)
+    }
+
+    // Registers the receiver to be woken once the channel is closed.
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if !decode_state(self.state.load(SeqCst)).is_open {
+            return Poll::Ready(());
+        }
+
+        self.close_task.register(cx.waker());
+        // Check again, as the last sender may have gone before `register`.
+        if decode_state(self.state.load(SeqCst)).is_open {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Self {
+            permits: AtomicUsize::new(permits),
+            num_waiters: AtomicUsize::new(0),
+            waiters: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    // Takes `n` free permits, if there are enough.
+    fn try_acquire(&self, n: usize) -> bool {
+        let mut curr = self.permits.load(SeqCst);
+
+        loop {
+            if curr < n {
+                return false;
+            }
+
+            match self.permits.compare_exchange(curr, curr - n, SeqCst, SeqCst) {
+                Ok(_) => return true,
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+
+    // Takes `n` free permits, unless other senders are already waiting.
+    fn try_acquire_fair(&self, n: usize) -> bool {
+        self.num_waiters.load(SeqCst) == 0 && self.try_acquire(n)
+    }
+
+    // Takes `n` free permits, or queues `task` to be handed them as they are
+    // given back. Returns whether the permits were taken.
+    fn acquire_or_park(&self, task: &Arc<Mutex<SenderTask>>, waker: &Waker, n: usize) -> bool {
+        let mut waiters = self.waiters.lock().unwrap();
+
+        // Announce the waiter before looking at the permits, while `release`
+        // gives the permit back before looking at the waiters. This way at
+        // least one of the two sees the other.
+        self.num_waiters.fetch_add(1, SeqCst);
+        if waiters.is_empty() && self.try_acquire(n) {
+            self.num_waiters.fetch_sub(1, SeqCst);
+            return true;
+        }
+
+        {
+            let mut task = task.lock().unwrap();
+            task.task = Some(waker.clone());
+            task.is_parked = true;
+        }
+        waiters.push_back((task.clone(), n));
+
+        false
+    }
+
+    // Gives `n` permits back, handing them to the longest waiting senders if
+    // any.
+    fn release(&self, n: usize) {
+        self.permits.fetch_add(n, SeqCst);
+
+        if self.num_waiters.load(SeqCst) == 0 {
+            return;
+        }
+
+        let mut waiters = self.waiters.lock().unwrap();
+        while let Some(&(_, wanted)) = waiters.front() {
+            if !self.try_acquire(wanted) {
+                break;
+            }
+
+            let (task, _) = waiters.pop_front().unwrap();
+            self.num_waiters.fetch_sub(1, SeqCst);
+
+            let mut task = task.lock().unwrap();
+            task.num_permits += wanted;
+            task.notify();
+        }
+    }
+
+    // Removes `task` from the waiters, returning whether it was still queued.
+    fn cancel(&self, task: &Arc<Mutex<SenderTask>>) -> bool {
+        let mut waiters = self.waiters.lock().unwrap();
+        match waiters.iter().position(|(t, _)| Arc::ptr_eq(t, task)) {
+            Some(pos) => {
+                waiters.remove(pos);
+                self.num_waiters.fetch_sub(1, SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Wakes all waiting senders without handing them a permit, so that they
+    // see the channel has been closed.
+    fn close(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        self.num_waiters.fetch_sub(waiters.len(), SeqCst);
+        for (task, _) in waiters.drain(..) {
+            task.lock().unwrap().notify();
+        }
+    }
 }
 
 impl<T> BoundedInner<T> {
-    // The return value is such that the total number of messages that can be
-    // enqueued into the channel will never exceed MAX_CAPACITY
-    fn max_senders(&self) -> usize {
-        MAX_CAPACITY - self.buffer
+    // Pops the oldest message off the message queue.
+    //
+    // This must only be called by the receiver. Under
+    // `OverflowPolicy::DropOldest`, it also gives the message's permit back,
+    // see `BoundedSenderInner::try_send`.
+    fn pop_message(&self) -> Option<T> {
+        match self.policy {
+            OverflowPolicy::DropOldest => {
+                let _guard = self.pop_lock.lock().unwrap();
+                let msg = unsafe { self.message_queue.pop_spin() }?;
+                self.state.fetch_sub(1, SeqCst);
+                self.semaphore.release(1);
+                Some(msg)
+            }
+            OverflowPolicy::Block | OverflowPolicy::DropNewest => unsafe {
+                self.message_queue.pop_spin()
+            },
+        }
+    }
+
+    // Pops the oldest task handle off the parked task queue.
+    //
+    // Only senders of a rendezvous channel are ever parked there, and these
+    // are unparked by the receiver as well as by a sender closing the channel.
+    fn pop_parked_task(&self) -> Option<Arc<Mutex<SenderTask>>> {
+        if self.buffer != 0 {
+            return None;
+        }
+        let _guard = self.unpark_lock.lock().unwrap();
+        unsafe { self.parked_queue.pop_spin() }
+    }
+
+    // Unparks all senders waiting for the receiver to catch up.
+    fn unpark_all(&self) {
+        while let Some(task) = self.pop_parked_task() {
+            task.lock().unwrap().notify();
+        }
     }
 
     // Clear `open` flag in the state, keep `num_messages` intact.
@@ -1273,6 +2722,55 @@ impl<T> BoundedInner<T> {
 
         self.state.fetch_and(!OPEN_MASK, SeqCst);
     }
+
+    // Records why the channel is being closed, unless it is closed already.
+    fn set_close_reason(&self, reason: CloseReason) {
+        let mut close_reason = self.close_reason.lock().unwrap();
+        if close_reason.is_none() && decode_state(self.state.load(SeqCst)).is_open {
+            *close_reason = Some(reason);
+        }
+    }
+
+    fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason.lock().unwrap().clone()
+    }
+
+    // The error for a send on the closed channel.
+    fn send_error(&self) -> SendError {
+        SendError::closed(self.close_reason())
+    }
+
+    fn len(&self) -> usize {
+        decode_state(self.state.load(SeqCst)).num_messages
+    }
+
+    // Permits which are neither held by a message nor reserved by a sender.
+    fn capacity(&self) -> usize {
+        self.semaphore.permits.load(SeqCst)
+    }
+
+    fn max_capacity(&self) -> usize {
+        self.buffer
+    }
+
+    fn sender_count(&self) -> usize {
+        self.num_senders.load(SeqCst)
+    }
+
+    // Registers the receiver to be woken once the channel is closed.
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if !decode_state(self.state.load(SeqCst)).is_open {
+            return Poll::Ready(());
+        }
+
+        self.close_task.register(cx.waker());
+        // Check again, as the last sender may have gone before `register`.
+        if decode_state(self.state.load(SeqCst)).is_open {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
 }
 
 unsafe impl<T: Send> Send for UnboundedInner<T> {}
@@ -1293,6 +2791,29 @@ impl State {
  *
  */
 
+// Registers one more sender, unless the last sender is already gone.
+fn inc_num_senders(num_senders: &AtomicUsize) -> bool {
+    let mut curr = num_senders.load(SeqCst);
+
+    loop {
+        // Once all senders are gone the channel has been closed, and it must
+        // not be revived.
+        if curr == 0 {
+            return false;
+        }
+
+        // If the maximum number of senders has been reached, then fail
+        if curr == MAX_BUFFER {
+            panic!("cannot upgrade to `Sender` -- too many outstanding senders");
+        }
+
+        match num_senders.compare_exchange(curr, curr + 1, SeqCst, SeqCst) {
+            Ok(_) => return true,
+            Err(actual) => curr = actual,
+        }
+    }
+}
+
 fn decode_state(num: usize) -> State {
     State { is_open: num & OPEN_MASK == OPEN_MASK, num_messages: num & MAX_CAPACITY }
 }
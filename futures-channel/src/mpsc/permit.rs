@@ -0,0 +1,158 @@
+use super::{SendError, Sender};
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll};
+use std::pin::Pin;
+
+/// Capacity reserved in a bounded mpsc channel, borrowed from a
+/// [`Sender`](Sender).
+///
+/// This value is created by the [`reserve`](Sender::reserve) method. Sending
+/// through a permit cannot fail for lack of capacity. Dropping the permit
+/// without sending gives the capacity back to the channel.
+///
+/// On a channel created by [`channel_with_policy`](super::channel_with_policy)
+/// with a policy dropping messages, no capacity is reserved, and a message
+/// sent through the permit is subject to the policy like any other.
+#[derive(Debug)]
+#[must_use = "dropping a permit without sending gives its capacity back to the channel"]
+pub struct Permit<'a, T> {
+    sender: &'a mut Sender<T>,
+}
+
+/// Capacity reserved in a bounded mpsc channel, owning its
+/// [`Sender`](Sender).
+///
+/// This value is created by the [`reserve_owned`](Sender::reserve_owned)
+/// method. Unlike [`Permit`](Permit), it can be moved into another task.
+#[derive(Debug)]
+#[must_use = "dropping a permit without sending gives its capacity back to the channel"]
+pub struct OwnedPermit<T> {
+    sender: Option<Sender<T>>,
+}
+
+/// Future for the [`reserve`](Sender::reserve) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Reserve<'a, T> {
+    sender: Option<&'a mut Sender<T>>,
+}
+
+/// Future for the [`reserve_owned`](Sender::reserve_owned) method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReserveOwned<T> {
+    sender: Option<Sender<T>>,
+}
+
+impl<'a, T> Reserve<'a, T> {
+    pub(super) fn new(sender: &'a mut Sender<T>) -> Self {
+        Self { sender: Some(sender) }
+    }
+}
+
+impl<T> ReserveOwned<T> {
+    pub(super) fn new(sender: Sender<T>) -> Self {
+        Self { sender: Some(sender) }
+    }
+}
+
+impl<'a, T> Future for Reserve<'a, T> {
+    type Output = Result<Permit<'a, T>, SendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let sender = self.sender.as_mut().expect("polled Reserve after completion");
+        match sender.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                let sender = self.sender.take().unwrap();
+                Poll::Ready(Ok(Permit { sender }))
+            }
+            Poll::Ready(Err(e)) => {
+                self.sender = None;
+                Poll::Ready(Err(e))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> FusedFuture for Reserve<'_, T> {
+    fn is_terminated(&self) -> bool {
+        self.sender.is_none()
+    }
+}
+
+impl<T> Future for ReserveOwned<T> {
+    type Output = Result<OwnedPermit<T>, SendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let sender = self.sender.as_mut().expect("polled ReserveOwned after completion");
+        match sender.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                let sender = self.sender.take().unwrap();
+                Poll::Ready(Ok(OwnedPermit { sender: Some(sender) }))
+            }
+            Poll::Ready(Err(e)) => {
+                self.sender = None;
+                Poll::Ready(Err(e))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> FusedFuture for ReserveOwned<T> {
+    fn is_terminated(&self) -> bool {
+        self.sender.is_none()
+    }
+}
+
+impl<T> Permit<'_, T> {
+    /// Sends a message using the reserved capacity.
+    ///
+    /// If the receiver has been dropped or closed since the capacity was
+    /// reserved, the message is dropped.
+    pub fn send(self, msg: T) {
+        // The capacity reserved by `poll_ready` guarantees that this cannot
+        // fail because the channel is full, and a disconnected channel drops
+        // the message as documented. Channels dropping messages reserve
+        // nothing, and apply their policy here instead.
+        let _ = self.sender.start_send(msg);
+    }
+}
+
+impl<T> OwnedPermit<T> {
+    /// Sends a message using the reserved capacity, and returns the
+    /// [`Sender`](Sender) it was reserved from.
+    ///
+    /// If the receiver has been dropped or closed since the capacity was
+    /// reserved, the message is dropped.
+    pub fn send(mut self, msg: T) -> Sender<T> {
+        let mut sender = self.sender.take().unwrap();
+        // See `Permit::send`.
+        let _ = sender.start_send(msg);
+        sender
+    }
+
+    /// Gives the reserved capacity back to the channel without sending, and
+    /// returns the [`Sender`](Sender) it was reserved from.
+    pub fn release(mut self) -> Sender<T> {
+        let mut sender = self.sender.take().unwrap();
+        sender.release_permit();
+        sender
+    }
+}
+
+impl<T> Drop for Permit<'_, T> {
+    fn drop(&mut self) {
+        // Does nothing if the permit was used by `send`.
+        self.sender.release_permit();
+    }
+}
+
+impl<T> Drop for OwnedPermit<T> {
+    fn drop(&mut self) {
+        if let Some(sender) = &mut self.sender {
+            sender.release_permit();
+        }
+    }
+}
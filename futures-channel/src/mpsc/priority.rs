@@ -0,0 +1,651 @@
+use super::{SendError, TryRecvError, TrySendError};
+use futures_core::future::Future;
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+struct PrioritySenderInner<T> {
+    // Channel state shared between the senders and the receiver.
+    inner: Arc<Inner<T>>,
+
+    // Identifies this sender in `Level::send_waiters`.
+    key: usize,
+
+    // The level in which `poll_ready_with_priority` reserved a slot for this
+    // sender, if any.
+    reserved: Option<usize>,
+}
+
+/// The transmission end of a priority channel.
+///
+/// This value is created by the [`priority_channel`](priority_channel)
+/// function.
+#[derive(Debug)]
+pub struct PrioritySender<T>(Option<PrioritySenderInner<T>>);
+
+/// The receiving end of a priority channel.
+///
+/// This value is created by the [`priority_channel`](priority_channel)
+/// function.
+#[derive(Debug)]
+pub struct PriorityReceiver<T> {
+    inner: Option<Arc<Inner<T>>>,
+}
+
+/// Future for the [`send_with_priority`](PrioritySender::send_with_priority)
+/// method.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendWithPriority<'a, T> {
+    sender: &'a mut PrioritySender<T>,
+    item: Option<T>,
+    level: usize,
+
+    // Whether the sender is waiting for capacity for this future.
+    is_waiting: bool,
+}
+
+// None of these types ever projects `Pin` to the inner `T`
+impl<T> Unpin for PrioritySender<T> {}
+impl<T> Unpin for PriorityReceiver<T> {}
+impl<T> Unpin for SendWithPriority<'_, T> {}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // Number of priority levels.
+    levels: usize,
+
+    // Max buffer size of each level.
+    buffer: usize,
+
+    // Channel state shared between the senders and the receiver.
+    state: Mutex<State<T>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    // The queues of the channel, from the highest priority to the lowest.
+    levels: Vec<Level<T>>,
+
+    // `true` when the channel is open
+    is_open: bool,
+
+    // Number of senders in existence
+    num_senders: usize,
+
+    // Key to hand out to the next sender.
+    next_key: usize,
+
+    // Handle to the receiver's task.
+    recv_task: Option<Waker>,
+}
+
+struct Level<T> {
+    // FIFO queue of the messages sent at this level but not yet received.
+    messages: VecDeque<T>,
+
+    // Number of slots in `messages` reserved by senders through
+    // `poll_ready_with_priority`.
+    num_reserved: usize,
+
+    // Senders waiting for capacity at this level, in the order they started
+    // waiting.
+    send_waiters: VecDeque<(usize, Waker)>,
+}
+
+/// Creates a bounded mpsc channel whose messages are received in order of
+/// priority.
+///
+/// Messages are sent at one of `levels` priority levels, `0` being the
+/// highest. The receiver always gets the oldest message of the highest level
+/// which has any, so messages sent at the same level are received in the
+/// order they were sent.
+///
+/// Each level is bounded separately and holds at most `buffer` messages, so
+/// that a full low-priority level does not keep senders from sending at a
+/// higher one.
+///
+/// The [`PriorityReceiver`](PriorityReceiver) returned implements the
+/// [`Stream`](futures_core::stream::Stream) trait, and
+/// [`PrioritySender`](PrioritySender) can be cloned.
+///
+/// # Panics
+///
+/// Panics if `levels` or `buffer` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::mpsc;
+/// use futures::executor::block_on;
+/// use futures::stream::StreamExt;
+///
+/// let (mut tx, rx) = mpsc::priority_channel(2, 16);
+///
+/// block_on(async {
+///     tx.send_with_priority("data", 1).await.unwrap();
+///     tx.send_with_priority("more data", 1).await.unwrap();
+///     tx.send_with_priority("shutdown", 0).await.unwrap();
+///     drop(tx);
+///
+///     let received: Vec<_> = rx.collect().await;
+///     assert_eq!(received, vec!["shutdown", "data", "more data"]);
+/// });
+/// ```
+pub fn priority_channel<T>(
+    levels: usize,
+    buffer: usize,
+) -> (PrioritySender<T>, PriorityReceiver<T>) {
+    assert!(levels > 0, "priority channel must have at least one level");
+    assert!(buffer > 0, "priority channel buffer must be greater than zero");
+
+    let inner = Arc::new(Inner {
+        levels,
+        buffer,
+        state: Mutex::new(State {
+            levels: (0..levels)
+                .map(|_| Level {
+                    messages: VecDeque::new(),
+                    num_reserved: 0,
+                    send_waiters: VecDeque::new(),
+                })
+                .collect(),
+            is_open: true,
+            num_senders: 1,
+            next_key: 1,
+            recv_task: None,
+        }),
+    });
+
+    let tx = PrioritySenderInner { inner: inner.clone(), key: 0, reserved: None };
+    let rx = PriorityReceiver { inner: Some(inner) };
+
+    (PrioritySender(Some(tx)), rx)
+}
+
+/*
+ *
+ * ===== impl PrioritySender =====
+ *
+ */
+
+impl<T> PrioritySenderInner<T> {
+    fn try_send(&mut self, msg: T, level: usize) -> Result<(), TrySendError<T>> {
+        self.inner.check_level(level);
+        let mut state = self.inner.state.lock().unwrap();
+        let buffer = self.inner.buffer;
+
+        if !state.is_open {
+            return Err(TrySendError::new(SendError::disconnected(), msg));
+        }
+
+        if self.reserved == Some(level) {
+            self.reserved = None;
+            state.level(level).num_reserved -= 1;
+        } else if state.level(level).is_full(buffer) {
+            return Err(TrySendError::new(SendError::full(), msg));
+        }
+
+        state.level(level).messages.push_back(msg);
+        if let Some(task) = state.recv_task.take() {
+            task.wake();
+        }
+        Ok(())
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>, level: usize) -> Poll<Result<(), SendError>> {
+        self.inner.check_level(level);
+        let mut state = self.inner.state.lock().unwrap();
+        let buffer = self.inner.buffer;
+
+        if !state.is_open {
+            return Poll::Ready(Err(SendError::disconnected()));
+        }
+
+        if self.reserved == Some(level) {
+            return Poll::Ready(Ok(()));
+        }
+
+        // A slot reserved at another level is given up, as the next message
+        // goes to this one.
+        if let Some(reserved) = self.reserved.take() {
+            let level = state.level(reserved);
+            level.num_reserved -= 1;
+            level.wake_one();
+        }
+
+        let key = self.key;
+        let slot = state.level(level);
+        if slot.is_full(buffer) {
+            slot.register(key, cx.waker());
+            Poll::Pending
+        } else {
+            slot.num_reserved += 1;
+            slot.remove(key);
+            self.reserved = Some(level);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    // Called when this sender stops waiting for capacity at `level`. If it
+    // was woken for capacity it will not use, the wakeup is passed on.
+    fn cancel_wait(&self, level: usize) {
+        let mut state = self.inner.state.lock().unwrap();
+        let buffer = self.inner.buffer;
+        let level = state.level(level);
+        if !level.remove(self.key) && !level.is_full(buffer) {
+            level.wake_one();
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        !self.inner.state.lock().unwrap().is_open
+    }
+}
+
+impl<T> PrioritySender<T> {
+    /// Attempts to send a message at the given priority level on this
+    /// `PrioritySender`, returning the message if there was an error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not lower than the number of levels of the
+    /// channel.
+    pub fn try_send_with_priority(&mut self, msg: T, level: usize) -> Result<(), TrySendError<T>> {
+        if let Some(inner) = &mut self.0 {
+            inner.try_send(msg, level)
+        } else {
+            Err(TrySendError::new(SendError::disconnected(), msg))
+        }
+    }
+
+    /// Send a message at the given priority level on the channel.
+    ///
+    /// This function should only be called after
+    /// [`poll_ready_with_priority`](PrioritySender::poll_ready_with_priority)
+    /// has reported that the level is ready to receive a message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not lower than the number of levels of the
+    /// channel.
+    pub fn start_send_with_priority(&mut self, msg: T, level: usize) -> Result<(), SendError> {
+        self.try_send_with_priority(msg, level).map_err(TrySendError::into_send_error)
+    }
+
+    /// Polls the channel to determine if there is guaranteed capacity to send
+    /// at least one item at the given priority level without waiting.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(_))` if there is sufficient capacity, in which case a
+    ///   slot of `level` is reserved for the next message sent at that level
+    ///   through this `PrioritySender`;
+    /// - `Poll::Pending` if the level may not have capacity, in which case the
+    ///   current task is queued to be notified once capacity is available;
+    /// - `Poll::Ready(Err(SendError))` if the receiver has been dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not lower than the number of levels of the
+    /// channel.
+    pub fn poll_ready_with_priority(
+        &mut self,
+        cx: &mut Context<'_>,
+        level: usize,
+    ) -> Poll<Result<(), SendError>> {
+        let inner = self.0.as_mut().ok_or_else(SendError::disconnected)?;
+        inner.poll_ready(cx, level)
+    }
+
+    /// Sends a message at the given priority level, waiting for the level to
+    /// have capacity for it.
+    ///
+    /// # Panics
+    ///
+    /// The returned future panics if `level` is not lower than the number of
+    /// levels of the channel.
+    pub fn send_with_priority(&mut self, item: T, level: usize) -> SendWithPriority<'_, T> {
+        SendWithPriority { sender: self, item: Some(item), level, is_waiting: false }
+    }
+
+    /// Returns the number of priority levels of the channel, or 0 if this
+    /// sender is disconnected.
+    pub fn levels(&self) -> usize {
+        self.0.as_ref().map(|inner| inner.inner.levels).unwrap_or(0)
+    }
+
+    /// Returns whether this channel is closed without needing a context.
+    pub fn is_closed(&self) -> bool {
+        self.0.as_ref().map(PrioritySenderInner::is_closed).unwrap_or(true)
+    }
+
+    /// Closes this channel from the sender side, preventing any new messages.
+    pub fn close_channel(&mut self) {
+        if let Some(inner) = &self.0 {
+            inner.inner.state.lock().unwrap().set_closed();
+        }
+    }
+
+    /// Disconnects this sender from the channel, closing it if there are no more senders left.
+    pub fn disconnect(&mut self) {
+        self.0 = None;
+    }
+
+    /// Returns whether the senders send to the same receiver.
+    pub fn same_receiver(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(inner), Some(other)) => Arc::ptr_eq(&inner.inner, &other.inner),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the sender sends to this receiver.
+    pub fn is_connected_to(&self, receiver: &PriorityReceiver<T>) -> bool {
+        match (&self.0, &receiver.inner) {
+            (Some(inner), Some(receiver)) => Arc::ptr_eq(&inner.inner, receiver),
+            _ => false,
+        }
+    }
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Clone for PrioritySenderInner<T> {
+    fn clone(&self) -> Self {
+        let key = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.num_senders += 1;
+            let key = state.next_key;
+            state.next_key += 1;
+            key
+        };
+
+        Self { inner: self.inner.clone(), key, reserved: None }
+    }
+}
+
+impl<T> Drop for PrioritySenderInner<T> {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+
+        for level in &mut state.levels {
+            level.remove(self.key);
+        }
+        if let Some(reserved) = self.reserved {
+            // Pass the reserved slot on to a sender waiting for it.
+            let level = state.level(reserved);
+            level.num_reserved -= 1;
+            level.wake_one();
+        }
+
+        state.num_senders -= 1;
+        if state.num_senders == 0 {
+            state.set_closed();
+        }
+    }
+}
+
+impl<T> Future for SendWithPriority<'_, T> {
+    type Output = Result<(), SendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        if this.item.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+
+        match this.sender.poll_ready_with_priority(cx, this.level) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => {
+                this.item = None;
+                return Poll::Ready(Err(e));
+            }
+            Poll::Pending => {
+                this.is_waiting = true;
+                return Poll::Pending;
+            }
+        }
+
+        let item = this.item.take().unwrap();
+        Poll::Ready(this.sender.start_send_with_priority(item, this.level))
+    }
+}
+
+impl<T> Drop for SendWithPriority<'_, T> {
+    fn drop(&mut self) {
+        if self.item.is_some() && self.is_waiting {
+            if let Some(inner) = &self.sender.0 {
+                inner.cancel_wait(self.level);
+            }
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl PriorityReceiver =====
+ *
+ */
+
+impl<T> PriorityReceiver<T> {
+    /// Closes the receiving half of a channel, without dropping it.
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receiver to drain messages that are buffered.
+    pub fn close(&mut self) {
+        if let Some(inner) = &self.inner {
+            inner.state.lock().unwrap().set_closed();
+        }
+    }
+
+    /// Tries to receive the next message without notifying a context if empty.
+    ///
+    /// It is not recommended to call this function from inside of a future,
+    /// only when you've otherwise arranged to be notified when the channel is
+    /// no longer empty.
+    ///
+    /// This function returns:
+    /// * `Ok(Some(t))` when message is fetched
+    /// * `Ok(None)` when channel is closed and no messages left in the queue
+    /// * `Err(e)` when there are no messages available, but channel is not yet closed
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        match self.next_message(None) {
+            Poll::Ready(msg) => Ok(msg),
+            Poll::Pending => Err(TryRecvError::new()),
+        }
+    }
+
+    /// Returns the number of messages waiting at the given priority level, or
+    /// 0 if the channel has terminated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not lower than the number of levels of the
+    /// channel.
+    pub fn len_of(&self, level: usize) -> usize {
+        match &self.inner {
+            Some(inner) => {
+                inner.check_level(level);
+                inner.state.lock().unwrap().level(level).messages.len()
+            }
+            None => 0,
+        }
+    }
+
+    fn next_message(&mut self, cx: Option<&mut Context<'_>>) -> Poll<Option<T>> {
+        let inner = match &self.inner {
+            None => return Poll::Ready(None),
+            Some(inner) => inner,
+        };
+        let mut state = inner.state.lock().unwrap();
+
+        let msg = state.levels.iter_mut().find_map(|level| {
+            let msg = level.messages.pop_front()?;
+            level.wake_one();
+            Some(msg)
+        });
+        if let Some(msg) = msg {
+            return Poll::Ready(Some(msg));
+        }
+
+        if !state.is_open {
+            // If closed flag is set AND there are no pending messages
+            // it means end of stream
+            drop(state);
+            self.inner = None;
+            return Poll::Ready(None);
+        }
+
+        if let Some(cx) = cx {
+            match &state.recv_task {
+                Some(task) if task.will_wake(cx.waker()) => {}
+                _ => state.recv_task = Some(cx.waker().clone()),
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> FusedStream for PriorityReceiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+impl<T> Stream for PriorityReceiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.next_message(Some(cx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            Some(inner) => {
+                let state = inner.state.lock().unwrap();
+                let len = state.levels.iter().map(|level| level.messages.len()).sum();
+                (len, if state.is_open { None } else { Some(len) })
+            }
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<T> Drop for PriorityReceiver<T> {
+    fn drop(&mut self) {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return,
+        };
+        // Nobody is left to receive the buffered messages, so close the
+        // channel and drop them outside of the lock.
+        let messages: Vec<_> = {
+            let mut state = inner.state.lock().unwrap();
+            state.set_closed();
+            state
+                .levels
+                .iter_mut()
+                .map(|level| mem::replace(&mut level.messages, VecDeque::new()))
+                .collect()
+        };
+        drop(messages);
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    // Checked before taking the lock, so that a bad level does not poison it.
+    fn check_level(&self, level: usize) {
+        assert!(
+            level < self.levels,
+            "priority level {} out of range for {} levels",
+            level,
+            self.levels
+        );
+    }
+}
+
+impl<T> State<T> {
+    fn level(&mut self, level: usize) -> &mut Level<T> {
+        &mut self.levels[level]
+    }
+
+    // Clear the `open` flag, keeping buffered messages intact, and wake up
+    // every waiting task so that it observes the change.
+    fn set_closed(&mut self) {
+        if self.is_open {
+            self.is_open = false;
+            for level in &mut self.levels {
+                for (_, waker) in level.send_waiters.drain(..) {
+                    waker.wake();
+                }
+            }
+            if let Some(task) = self.recv_task.take() {
+                task.wake();
+            }
+        }
+    }
+}
+
+impl<T> Level<T> {
+    fn is_full(&self, buffer: usize) -> bool {
+        self.messages.len() + self.num_reserved >= buffer
+    }
+
+    // Queue the sender identified by `key`, keeping its position in the queue
+    // if it was already waiting.
+    fn register(&mut self, key: usize, waker: &Waker) {
+        match self.send_waiters.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, w)) => {
+                if !w.will_wake(waker) {
+                    *w = waker.clone();
+                }
+            }
+            None => self.send_waiters.push_back((key, waker.clone())),
+        }
+    }
+
+    // Dequeue the sender identified by `key`. Returns whether it was queued.
+    fn remove(&mut self, key: usize) -> bool {
+        match self.send_waiters.iter().position(|(k, _)| *k == key) {
+            Some(pos) => {
+                self.send_waiters.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn wake_one(&mut self) {
+        if let Some((_, waker)) = self.send_waiters.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Level<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Level")
+            .field("num_messages", &self.messages.len())
+            .field("num_reserved", &self.num_reserved)
+            .finish()
+    }
+}
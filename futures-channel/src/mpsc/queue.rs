@@ -1,53 +1,36 @@
-/* Copyright (c) 2010-2011 Dmitry Vyukov. All rights reserved.
- * Redistribution and use in source and binary forms, with or without
- * modification, are permitted provided that the following conditions are met:
- *
- *    1. Redistributions of source code must retain the above copyright notice,
- *       this list of conditions and the following disclaimer.
- *
- *    2. Redistributions in binary form must reproduce the above copyright
- *       notice, this list of conditions and the following disclaimer in the
- *       documentation and/or other materials provided with the distribution.
- *
- * THIS SOFTWARE IS PROVIDED BY DMITRY VYUKOV "AS IS" AND ANY EXPRESS OR IMPLIED
- * WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED WARRANTIES OF
- * MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT
- * SHALL DMITRY VYUKOV OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT,
- * INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
- * LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
- * PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
- * LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING NEGLIGENCE
- * OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS SOFTWARE, EVEN IF
- * ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
- *
- * The views and conclusions contained in the software and documentation are
- * those of the authors and should not be interpreted as representing official
- * policies, either expressed or implied, of Dmitry Vyukov.
- */
-
 //! A mostly lock-free multi-producer, single consumer queue for sending
 //! messages between asynchronous tasks.
 //!
-//! The queue implementation is essentially the same one used for mpsc channels
-//! in the standard library.
+//! Messages are stored in fixed-size blocks of `BLOCK_CAP` slots which are
+//! linked together, so that the queue allocates once per block rather than
+//! once per message. A block which has been read completely is kept to be
+//! reused as a later block, so a queue which never holds more than a block's
+//! worth of messages stops allocating altogether.
+//!
+//! The layout follows the unbounded list channel of crossbeam: every message
+//! takes the next index of the tail, and the last index of each block's lap
+//! is reserved for installing the block after it.
 //!
 //! Note that the current implementation of this queue has a caveat of the `pop`
 //! method, and see the method for more information about it. Due to this
 //! caveat, this queue may not be appropriate for all use-cases.
 
-// http://www.1024cores.net/home/lock-free-algorithms
-//                         /queues/non-intrusive-mpsc-node-based-queue
-
-// NOTE: this implementation is lifted from the standard library and only
-//       slightly modified
-
 pub(super) use self::PopResult::*;
 
 use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
 use std::ptr;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
 use std::thread;
 
+// Number of indices per block. The last one does not refer to a slot: a tail
+// pointing at it means that the next block is being installed.
+const LAP: usize = 32;
+
+// Number of messages a block holds.
+const BLOCK_CAP: usize = LAP - 1;
+
 /// A result of the `pop` function.
 pub(super) enum PopResult<T> {
     /// Some data has been popped
@@ -61,44 +44,122 @@ pub(super) enum PopResult<T> {
     Inconsistent,
 }
 
-#[derive(Debug)]
-struct Node<T> {
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+
+    // Set once `value` has been written.
+    ready: AtomicBool,
+}
+
+struct Block<T> {
+    // The block after this one, set before the last slot is written.
     next: AtomicPtr<Self>,
-    value: Option<T>,
+
+    slots: [Slot<T>; BLOCK_CAP],
+}
+
+impl<T> Block<T> {
+    fn new() -> *mut Self {
+        // A zeroed block is empty: `next` is null, and no slot is ready.
+        Box::into_raw(Box::new(unsafe { MaybeUninit::<Self>::zeroed().assume_init() }))
+    }
+
+    // Makes a block whose messages have all been read empty again.
+    unsafe fn reset(this: *mut Self) {
+        (*this).next.store(ptr::null_mut(), Ordering::Relaxed);
+        for slot in (*this).slots.iter() {
+            slot.ready.store(false, Ordering::Relaxed);
+        }
+    }
 }
 
 /// The multi-producer single-consumer structure. This is not cloneable, but it
 /// may be safely shared so long as it is guaranteed that there is only one
 /// popper at a time (many pushers are allowed).
-#[derive(Debug)]
 pub(super) struct Queue<T> {
-    head: AtomicPtr<Node<T>>,
-    tail: UnsafeCell<*mut Node<T>>,
+    // Index of the next slot to push to, and the block it is in.
+    tail_index: AtomicUsize,
+    tail_block: AtomicPtr<Block<T>>,
+
+    // Index of the next slot to pop from, and the block it is in. Only the
+    // popper accesses these.
+    head_index: UnsafeCell<usize>,
+    head_block: UnsafeCell<*mut Block<T>>,
+
+    // An empty block kept to be reused.
+    spare: AtomicPtr<Block<T>>,
 }
 
 unsafe impl<T: Send> Send for Queue<T> {}
 unsafe impl<T: Send> Sync for Queue<T> {}
 
-impl<T> Node<T> {
-    unsafe fn new(v: Option<T>) -> *mut Self {
-        Box::into_raw(Box::new(Self { next: AtomicPtr::new(ptr::null_mut()), value: v }))
-    }
-}
-
 impl<T> Queue<T> {
     /// Creates a new queue that is safe to share among multiple producers and
     /// one consumer.
     pub(super) fn new() -> Self {
-        let stub = unsafe { Node::new(None) };
-        Self { head: AtomicPtr::new(stub), tail: UnsafeCell::new(stub) }
+        let block = Block::new();
+        Self {
+            tail_index: AtomicUsize::new(0),
+            tail_block: AtomicPtr::new(block),
+            head_index: UnsafeCell::new(0),
+            head_block: UnsafeCell::new(block),
+            spare: AtomicPtr::new(ptr::null_mut()),
+        }
     }
 
     /// Pushes a new value onto this queue.
     pub(super) fn push(&self, t: T) {
-        unsafe {
-            let n = Node::new(Some(t));
-            let prev = self.head.swap(n, Ordering::AcqRel);
-            (*prev).next.store(n, Ordering::Release);
+        let mut next_block: *mut Block<T> = ptr::null_mut();
+        let mut tail = self.tail_index.load(Ordering::Acquire);
+        let mut block = self.tail_block.load(Ordering::Acquire);
+
+        loop {
+            let offset = tail % LAP;
+
+            // Another push is installing the next block, wait for it.
+            if offset == BLOCK_CAP {
+                thread::yield_now();
+                tail = self.tail_index.load(Ordering::Acquire);
+                block = self.tail_block.load(Ordering::Acquire);
+                continue;
+            }
+
+            // Whoever takes the last slot of a block installs the next one, so
+            // have it at hand before trying to.
+            if offset + 1 == BLOCK_CAP && next_block.is_null() {
+                next_block = self.take_spare();
+            }
+
+            // The block was loaded after the index, so it is the block of
+            // `tail` if the index has not moved on in the meantime.
+            match self.tail_index.compare_exchange_weak(
+                tail,
+                tail + 1,
+                Ordering::SeqCst,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => unsafe {
+                    if offset + 1 == BLOCK_CAP {
+                        self.tail_block.store(next_block, Ordering::Release);
+                        self.tail_index.store(tail + 2, Ordering::Release);
+                        (*block).next.store(next_block, Ordering::Release);
+                        next_block = ptr::null_mut();
+                    }
+
+                    let slot = (*block).slots.get_unchecked(offset);
+                    (*slot.value.get()).as_mut_ptr().write(t);
+                    slot.ready.store(true, Ordering::Release);
+
+                    if !next_block.is_null() {
+                        self.recycle(next_block);
+                    }
+                    return;
+                },
+                Err(actual) => {
+                    tail = actual;
+                    block = self.tail_block.load(Ordering::Acquire);
+                }
+            }
         }
     }
 
@@ -115,23 +176,32 @@ impl<T> Queue<T> {
     ///
     /// This function is unsafe because only one thread can call it at a time.
     pub(super) unsafe fn pop(&self) -> PopResult<T> {
-        let tail = *self.tail.get();
-        let next = (*tail).next.load(Ordering::Acquire);
-
-        if !next.is_null() {
-            *self.tail.get() = next;
-            assert!((*tail).value.is_none());
-            assert!((*next).value.is_some());
-            let ret = (*next).value.take().unwrap();
-            drop(Box::from_raw(tail));
-            return Data(ret);
+        let head = *self.head_index.get();
+        let block = *self.head_block.get();
+        let offset = head % LAP;
+
+        let slot = (*block).slots.get_unchecked(offset);
+        if !slot.ready.load(Ordering::Acquire) {
+            return if self.tail_index.load(Ordering::Acquire) == head {
+                Empty
+            } else {
+                Inconsistent
+            };
         }
+        let ret = (*slot.value.get()).as_ptr().read();
 
-        if self.head.load(Ordering::Acquire) == tail {
-            Empty
+        if offset + 1 == BLOCK_CAP {
+            // The push that wrote the last slot installed the next block
+            // before, and no push touches this block anymore.
+            *self.head_block.get() = (*block).next.load(Ordering::Acquire);
+            *self.head_index.get() = head + 2;
+            Block::reset(block);
+            self.recycle(block);
         } else {
-            Inconsistent
+            *self.head_index.get() = head + 1;
         }
+
+        Data(ret)
     }
 
     /// Pop an element similarly to `pop` function, but spin-wait on inconsistent
@@ -160,16 +230,62 @@ impl<T> Queue<T> {
             }
         }
     }
+
+    // Takes the spare block, or allocates a new one if there is none.
+    fn take_spare(&self) -> *mut Block<T> {
+        let block = self.spare.swap(ptr::null_mut(), Ordering::Acquire);
+        if block.is_null() {
+            Block::new()
+        } else {
+            block
+        }
+    }
+
+    // Keeps an empty block to be reused, or frees it if there is a spare block
+    // already.
+    unsafe fn recycle(&self, block: *mut Block<T>) {
+        if self
+            .spare
+            .compare_exchange(ptr::null_mut(), block, Ordering::Release, Ordering::Relaxed)
+            .is_err()
+        {
+            drop(Box::from_raw(block));
+        }
+    }
+}
+
+impl<T> fmt::Debug for Queue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue").finish()
+    }
 }
 
 impl<T> Drop for Queue<T> {
     fn drop(&mut self) {
         unsafe {
-            let mut cur = *self.tail.get();
-            while !cur.is_null() {
-                let next = (*cur).next.load(Ordering::Relaxed);
-                drop(Box::from_raw(cur));
-                cur = next;
+            // Every push has finished, so all slots from the head up to the
+            // tail hold a message.
+            let tail = *self.tail_index.get_mut();
+            let mut head = *self.head_index.get();
+            let mut block = *self.head_block.get();
+
+            while head != tail {
+                let offset = head % LAP;
+                if offset == BLOCK_CAP {
+                    let next = *(*block).next.get_mut();
+                    drop(Box::from_raw(block));
+                    block = next;
+                } else {
+                    let slot = (*block).slots.get_unchecked(offset);
+                    ptr::drop_in_place((*slot.value.get()).as_mut_ptr());
+                }
+                head += 1;
+            }
+            drop(Box::from_raw(block));
+
+            let spare = *self.spare.get_mut();
+            if !spare.is_null() {
+                drop(Box::from_raw(spare));
             }
         }
     }
@@ -450,6 +450,19 @@ impl<T> Receiver<T> {
     pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
         self.inner.try_recv()
     }
+
+    /// Blocks the current thread until the value is received.
+    ///
+    /// The thread is parked on the channel directly rather than running an
+    /// executor, so unlike `block_on(receiver)` this can be called while an
+    /// executor is running on the current thread. It should not be called
+    /// from an async task though, as it blocks the thread the task runs on.
+    ///
+    /// Returns an error if the sender was dropped.
+    #[cfg(feature = "std")]
+    pub fn recv_blocking(self) -> Result<T, Canceled> {
+        crate::blocking::block_on(|cx| self.inner.recv(cx))
+    }
 }
 
 impl<T> Future for Receiver<T> {
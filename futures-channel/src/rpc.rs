@@ -0,0 +1,272 @@
+//! A channel for sending requests to a task and receiving its responses.
+//!
+//! Channel creation provides a [`Client`] and a [`Server`] handle. Every
+//! request sent with [`Client::call`] reaches the [`Server`] paired with a
+//! [`Responder`], through which the server sends the response back to the
+//! caller. This is the pattern of sending a [`oneshot`] sender along with
+//! each message of an [`mpsc`] channel, which is what the channel is made of.
+//!
+//! # Disconnection
+//!
+//! If a [`Responder`] is dropped without sending a response, or the
+//! [`Server`] is dropped before receiving the request, the call fails with
+//! [`Canceled`].
+//!
+//! When all [`Client`] handles have been dropped, the [`Server`] stream
+//! terminates once it has received the requests still buffered.
+//!
+//! [`Client`]: struct.Client.html
+//! [`Client::call`]: struct.Client.html#method.call
+//! [`Server`]: struct.Server.html
+//! [`Responder`]: struct.Responder.html
+//! [`Canceled`]: struct.Canceled.html
+//! [`oneshot`]: ../oneshot/index.html
+//! [`mpsc`]: ../mpsc/index.html
+
+use crate::mpsc::{self, TryRecvError};
+use crate::oneshot;
+use futures_core::future::{FusedFuture, Future};
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll};
+use std::fmt;
+use std::pin::Pin;
+
+pub use crate::oneshot::Canceled;
+
+/// The calling end of a request/response channel.
+///
+/// This value is created by the [`channel`](channel) function and can be
+/// cloned to make calls from several tasks.
+pub struct Client<Req, Resp> {
+    sender: mpsc::Sender<(Req, Responder<Resp>)>,
+}
+
+/// The serving end of a request/response channel.
+///
+/// This value is created by the [`channel`](channel) function. It is a
+/// [`Stream`](futures_core::stream::Stream) of the requests, each paired with
+/// the [`Responder`](Responder) used to send its response.
+pub struct Server<Req, Resp> {
+    receiver: mpsc::Receiver<(Req, Responder<Resp>)>,
+}
+
+/// A means of sending the response to a single request.
+///
+/// Dropping a `Responder` without calling [`send`](Responder::send) cancels
+/// the call.
+pub struct Responder<Resp> {
+    sender: oneshot::Sender<Resp>,
+}
+
+/// Future for the [`call`](Client::call) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Call<'a, Req, Resp> {
+    client: &'a mut Client<Req, Resp>,
+
+    // The request, until it has been sent.
+    request: Option<(Req, Responder<Resp>)>,
+
+    response: oneshot::Receiver<Resp>,
+}
+
+// None of these types ever projects `Pin` to a request or response.
+impl<Req, Resp> Unpin for Client<Req, Resp> {}
+impl<Req, Resp> Unpin for Server<Req, Resp> {}
+impl<Req, Resp> Unpin for Call<'_, Req, Resp> {}
+
+/// Creates a request/response channel which buffers up to `buffer` requests,
+/// in the same way as [`mpsc::channel`](crate::mpsc::channel).
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::rpc;
+/// use futures::executor::block_on;
+/// use futures::future;
+/// use futures::stream::StreamExt;
+/// use std::thread;
+///
+/// let (mut client, server) = rpc::channel::<u32, u32>(16);
+///
+/// let t = thread::spawn(move || {
+///     block_on(server.for_each(|(req, responder)| {
+///         let _ = responder.send(req * 2);
+///         future::ready(())
+///     }))
+/// });
+///
+/// assert_eq!(block_on(client.call(21)), Ok(42));
+/// drop(client);
+/// t.join().unwrap();
+/// ```
+pub fn channel<Req, Resp>(buffer: usize) -> (Client<Req, Resp>, Server<Req, Resp>) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    (Client { sender }, Server { receiver })
+}
+
+/*
+ *
+ * ===== impl Client =====
+ *
+ */
+
+impl<Req, Resp> Client<Req, Resp> {
+    /// Sends a request to the server and waits for its response.
+    ///
+    /// The returned future waits for capacity in the channel before sending
+    /// the request. It resolves to `Err(Canceled)` if the server is gone, or
+    /// drops the [`Responder`](Responder) of the request without responding.
+    ///
+    /// Dropping the future before it completes abandons the call, which the
+    /// server can detect with [`Responder::is_canceled`].
+    pub fn call(&mut self, request: Req) -> Call<'_, Req, Resp> {
+        let (sender, response) = oneshot::channel();
+        Call { client: self, request: Some((request, Responder { sender })), response }
+    }
+
+    /// Returns whether the server has stopped receiving requests.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// Returns whether the clients send requests to the same server.
+    pub fn same_server(&self, other: &Self) -> bool {
+        self.sender.same_receiver(&other.sender)
+    }
+}
+
+impl<Req, Resp> Clone for Client<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self { sender: self.sender.clone() }
+    }
+}
+
+impl<Req, Resp> fmt::Debug for Client<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client").field("closed", &self.is_closed()).finish()
+    }
+}
+
+impl<Req, Resp> Future for Call<'_, Req, Resp> {
+    type Output = Result<Resp, Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        if this.request.is_some() {
+            match this.client.sender.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(_)) => {
+                    // Dropping the request drops its responder, which makes
+                    // the response below `Canceled`.
+                    this.request = None;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+
+            if let Some(request) = this.request.take() {
+                // The request is dropped along with the error if the server
+                // went away since `poll_ready`.
+                let _ = this.client.sender.start_send(request);
+            }
+        }
+
+        Pin::new(&mut this.response).poll(cx)
+    }
+}
+
+impl<Req, Resp> FusedFuture for Call<'_, Req, Resp> {
+    fn is_terminated(&self) -> bool {
+        self.response.is_terminated()
+    }
+}
+
+impl<Req, Resp> fmt::Debug for Call<'_, Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Call").field("sent", &self.request.is_none()).finish()
+    }
+}
+
+/*
+ *
+ * ===== impl Server =====
+ *
+ */
+
+impl<Req, Resp> Server<Req, Resp> {
+    /// Closes the serving end of the channel, without dropping it.
+    ///
+    /// This prevents any further requests from being sent while still
+    /// enabling the server to receive the requests that are buffered.
+    pub fn close(&mut self) {
+        self.receiver.close()
+    }
+
+    /// Tries to receive the next request without notifying a context if
+    /// there is none.
+    ///
+    /// This function returns:
+    /// * `Ok(Some(_))` when a request is received
+    /// * `Ok(None)` when the channel is closed and no requests are left
+    /// * `Err(e)` when there are no requests available, but the channel is
+    ///   not yet closed
+    pub fn try_next(&mut self) -> Result<Option<(Req, Responder<Resp>)>, TryRecvError> {
+        self.receiver.try_next()
+    }
+}
+
+impl<Req, Resp> Stream for Server<Req, Resp> {
+    type Item = (Req, Responder<Resp>);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.receiver.size_hint()
+    }
+}
+
+impl<Req, Resp> FusedStream for Server<Req, Resp> {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_terminated()
+    }
+}
+
+impl<Req, Resp> fmt::Debug for Server<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Server").field("terminated", &self.is_terminated()).finish()
+    }
+}
+
+/*
+ *
+ * ===== impl Responder =====
+ *
+ */
+
+impl<Resp> Responder<Resp> {
+    /// Sends the response to the caller.
+    ///
+    /// Returns the response back if the caller abandoned the call.
+    pub fn send(self, response: Resp) -> Result<(), Resp> {
+        self.sender.send(response)
+    }
+
+    /// Polls this `Responder` to detect whether the caller abandoned the
+    /// call, in which case no response is needed.
+    pub fn poll_canceled(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.sender.poll_canceled(cx)
+    }
+
+    /// Returns whether the caller abandoned the call.
+    pub fn is_canceled(&self) -> bool {
+        self.sender.is_canceled()
+    }
+}
+
+impl<Resp> fmt::Debug for Responder<Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Responder").field("canceled", &self.is_canceled()).finish()
+    }
+}
@@ -0,0 +1,463 @@
+//! A single-producer, multi-consumer channel that only retains the most
+//! recently sent value.
+//!
+//! Channel creation provides a [`Sender`] and a [`Receiver`] handle, along
+//! with the initial value held by the channel. [`Sender::send`] replaces the
+//! value and notifies every receiver. Receivers are cloneable and each keeps
+//! track of the last version it has seen, so that [`Receiver::changed`] and
+//! the [`Stream`] implementation only complete once a newer value is
+//! available. Intermediate values are never queued: a receiver which falls
+//! behind simply observes the latest value.
+//!
+//! # Disconnection
+//!
+//! When the [`Sender`] is dropped, receivers first observe a change that was
+//! not seen yet, if any, and then [`Receiver::changed`] fails and the stream
+//! terminates. The last value remains accessible through
+//! [`Receiver::borrow`].
+//!
+//! [`Sender`]: struct.Sender.html
+//! [`Sender::send`]: struct.Sender.html#method.send
+//! [`Receiver`]: struct.Receiver.html
+//! [`Receiver::changed`]: struct.Receiver.html#method.changed
+//! [`Receiver::borrow`]: struct.Receiver.html#method.borrow
+//! [`Stream`]: ../../futures_core/stream/trait.Stream.html
+
+use futures_core::future::Future;
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll, Waker};
+use std::fmt;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+
+/// The transmission end of a watch channel.
+///
+/// This value is created by the [`channel`](channel) function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving end of a watch channel.
+///
+/// This value is created by the [`channel`](channel) function and can be
+/// cloned to create more receivers.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+
+    // Last version of the value observed by this receiver.
+    version: usize,
+
+    // Index of this receiver's slot in `RecvTasks::tasks`.
+    key: usize,
+
+    // `true` once the stream has returned `None`.
+    is_terminated: bool,
+}
+
+// Neither half ever projects `Pin` to the inner `T`
+impl<T> Unpin for Sender<T> {}
+impl<T> Unpin for Receiver<T> {}
+
+/// A reference to the value held by a watch channel.
+///
+/// While this reference is alive, the [`Sender`](Sender) cannot update the
+/// value, so it should not be held for long.
+pub struct Ref<'a, T> {
+    inner: RwLockReadGuard<'a, T>,
+}
+
+/// The error type returned from [`send`](Sender::send).
+#[derive(Clone, PartialEq, Eq)]
+pub struct SendError<T> {
+    val: T,
+}
+
+/// The error type returned from [`changed`](Receiver::changed) when the
+/// [`Sender`](Sender) has been dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvError {
+    _priv: (),
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "send failed because all receivers are gone")
+    }
+}
+
+impl<T: core::any::Any> std::error::Error for SendError<T> {}
+
+impl<T> SendError<T> {
+    /// Returns the value that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "watch sender is gone")
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+#[derive(Debug)]
+struct Inner<T> {
+    // The most recently sent value.
+    value: RwLock<T>,
+
+    // Internal channel state. Consists of the version of `value`, which is
+    // bumped on every update, as well as a flag signalling that the sender
+    // is gone.
+    state: AtomicUsize,
+
+    // Receivers waiting for the next version.
+    recv_tasks: Mutex<RecvTasks>,
+}
+
+#[derive(Debug)]
+struct RecvTasks {
+    // Handles to the receivers' tasks, indexed by `Receiver::key`.
+    tasks: Vec<Option<Waker>>,
+
+    // Indices of `tasks` left behind by dropped receivers.
+    free_keys: Vec<usize>,
+
+    // Number of receivers in existence
+    num_receivers: usize,
+}
+
+// The `is_closed` flag is stored in the lowest bit of `Inner::state`, the
+// version in the remaining bits.
+const CLOSED: usize = 1;
+const VERSION_ONE: usize = 1 << 1;
+
+/// Creates a watch channel holding `init` as its initial value.
+///
+/// Receivers consider `init` as already seen, so they only wake up once the
+/// [`Sender`](Sender) publishes a new value.
+///
+/// # Examples
+///
+/// ```
+/// use futures::channel::watch;
+/// use futures::executor::block_on;
+///
+/// let (tx, mut rx) = watch::channel("initial");
+/// assert_eq!(*rx.borrow(), "initial");
+///
+/// tx.send("updated").unwrap();
+/// block_on(async {
+///     rx.changed().await.unwrap();
+///     assert_eq!(*rx.borrow(), "updated");
+///
+///     drop(tx);
+///     assert!(rx.changed().await.is_err());
+/// });
+/// ```
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        value: RwLock::new(init),
+        state: AtomicUsize::new(0),
+        recv_tasks: Mutex::new(RecvTasks {
+            tasks: Vec::new(),
+            free_keys: Vec::new(),
+            num_receivers: 0,
+        }),
+    });
+
+    let rx = Receiver::new(inner.clone());
+
+    (Sender { inner }, rx)
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    /// Replaces the value held by the channel and notifies every receiver.
+    ///
+    /// If all receivers have been dropped, the value is returned in the error
+    /// and the channel is left unchanged.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.is_closed() {
+            return Err(SendError { val: value });
+        }
+
+        self.send_modify(|old| *old = value);
+        Ok(())
+    }
+
+    /// Modifies the value held by the channel in place and notifies every
+    /// receiver.
+    ///
+    /// Unlike [`send`](Sender::send), the update happens even if all
+    /// receivers have been dropped.
+    pub fn send_modify<F>(&self, modify: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        {
+            let mut value = self.inner.value.write().unwrap();
+            modify(&mut value);
+
+            // Bump the version while still holding the write lock, so that a
+            // receiver never sees the new version alongside the old value.
+            self.inner.state.fetch_add(VERSION_ONE, SeqCst);
+        }
+
+        self.inner.wake_all();
+    }
+
+    /// Returns a reference to the value currently held by the channel.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref { inner: self.inner.value.read().unwrap() }
+    }
+
+    /// Creates a new [`Receiver`](Receiver) which considers the current value
+    /// as already seen.
+    pub fn subscribe(&self) -> Receiver<T> {
+        Receiver::new(self.inner.clone())
+    }
+
+    /// Returns the number of receivers currently connected to the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.inner.recv_tasks.lock().unwrap().num_receivers
+    }
+
+    /// Returns whether all receivers have been dropped, without needing a
+    /// context.
+    pub fn is_closed(&self) -> bool {
+        self.receiver_count() == 0
+    }
+
+    /// Returns whether the sender sends to this receiver.
+    pub fn is_connected_to(&self, receiver: &Receiver<T>) -> bool {
+        Arc::ptr_eq(&self.inner, &receiver.inner)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.inner.state.fetch_or(CLOSED, SeqCst);
+        self.inner.wake_all();
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    fn new(inner: Arc<Inner<T>>) -> Self {
+        let key = {
+            let mut recv_tasks = inner.recv_tasks.lock().unwrap();
+            recv_tasks.num_receivers += 1;
+            match recv_tasks.free_keys.pop() {
+                Some(key) => key,
+                None => {
+                    recv_tasks.tasks.push(None);
+                    recv_tasks.tasks.len() - 1
+                }
+            }
+        };
+        let version = inner.state.load(SeqCst) & !CLOSED;
+
+        Self { inner, version, key, is_terminated: false }
+    }
+
+    /// Returns a reference to the value currently held by the channel.
+    ///
+    /// This does not mark the value as seen.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref { inner: self.inner.value.read().unwrap() }
+    }
+
+    /// Returns a reference to the value currently held by the channel and
+    /// marks it as seen.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let value = self.inner.value.read().unwrap();
+        self.version = self.inner.state.load(SeqCst) & !CLOSED;
+        Ref { inner: value }
+    }
+
+    /// Returns whether a value that was not seen yet is held by the channel.
+    pub fn has_changed(&self) -> bool {
+        self.inner.state.load(SeqCst) & !CLOSED != self.version
+    }
+
+    /// Creates a future that resolves once a value that was not seen yet is
+    /// held by the channel, and marks it as seen.
+    ///
+    /// The future fails with a [`RecvError`](RecvError) if the
+    /// [`Sender`](Sender) is dropped before that happens.
+    pub fn changed(&mut self) -> Changed<'_, T> {
+        Changed { receiver: self }
+    }
+
+    /// Polls the channel for a value that was not seen yet, and marks it as
+    /// seen.
+    ///
+    /// # Return value
+    ///
+    /// This method returns:
+    ///
+    /// - `Poll::Ready(Ok(()))` if a new value is held by the channel;
+    /// - `Poll::Pending` if the value was already seen, in which case the
+    ///   current task is queued to be notified once it changes;
+    /// - `Poll::Ready(Err(RecvError))` if the sender has been dropped.
+    pub fn poll_changed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), RecvError>> {
+        match self.inner.poll_version(&mut self.version) {
+            Poll::Pending => {}
+            ready => return ready,
+        }
+
+        let mut recv_tasks = self.inner.recv_tasks.lock().unwrap();
+        let task = &mut recv_tasks.tasks[self.key];
+        match task {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            _ => *task = Some(cx.waker().clone()),
+        }
+
+        // Check the state again while the task is registered, as the sender
+        // may have updated the value in the meantime, and only wakes the tasks
+        // after doing so.
+        self.inner.poll_version(&mut self.version)
+    }
+
+    /// Returns whether the receivers receive from the same sender.
+    pub fn same_channel(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut rx = Self::new(self.inner.clone());
+        rx.version = self.version;
+        rx.is_terminated = self.is_terminated;
+        rx
+    }
+}
+
+impl<T: Clone> FusedStream for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.is_terminated
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if self.is_terminated {
+            return Poll::Ready(None);
+        }
+
+        match self.poll_changed(cx) {
+            // The value may have been replaced again since `poll_changed`, so
+            // mark the version seen together with the value that is yielded.
+            Poll::Ready(Ok(())) => Poll::Ready(Some(self.borrow_and_update().clone())),
+            Poll::Ready(Err(_)) => {
+                self.is_terminated = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut recv_tasks = self.inner.recv_tasks.lock().unwrap();
+        recv_tasks.tasks[self.key] = None;
+        recv_tasks.free_keys.push(self.key);
+        recv_tasks.num_receivers -= 1;
+    }
+}
+
+/// A future that resolves when the value held by a watch channel changes.
+///
+/// This is an `.await`-friendly interface around
+/// [`poll_changed`](Receiver::poll_changed).
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct Changed<'a, T> {
+    receiver: &'a mut Receiver<T>,
+}
+
+impl<T> Future for Changed<'_, T> {
+    type Output = Result<(), RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.receiver.poll_changed(cx)
+    }
+}
+
+/*
+ *
+ * ===== impl Ref =====
+ *
+ */
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Ref<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/*
+ *
+ * ===== impl Inner =====
+ *
+ */
+
+impl<T> Inner<T> {
+    // Checks whether the value is newer than `version`, and if so updates
+    // `version` to match it.
+    fn poll_version(&self, version: &mut usize) -> Poll<Result<(), RecvError>> {
+        let state = self.state.load(SeqCst);
+        if state & !CLOSED != *version {
+            *version = state & !CLOSED;
+            Poll::Ready(Ok(()))
+        } else if state & CLOSED == CLOSED {
+            Poll::Ready(Err(RecvError { _priv: () }))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn wake_all(&self) {
+        let mut recv_tasks = self.recv_tasks.lock().unwrap();
+        for task in recv_tasks.tasks.iter_mut() {
+            if let Some(task) = task.take() {
+                task.wake();
+            }
+        }
+    }
+}
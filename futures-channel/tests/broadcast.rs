@@ -0,0 +1,201 @@
+use futures::channel::broadcast;
+use futures::executor::{block_on, block_on_stream};
+use futures::stream::{FusedStream, StreamExt};
+use futures::task::Poll;
+use futures_test::task::new_count_waker;
+use std::thread;
+
+#[test]
+fn send_recv() {
+    let (tx, rx) = broadcast::channel::<i32>(16);
+
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    drop(tx);
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![Ok(1), Ok(2)]);
+}
+
+#[test]
+fn every_receiver_sees_every_message() {
+    let (tx, rx1) = broadcast::channel::<i32>(16);
+    let rx2 = tx.subscribe();
+    let rx3 = tx.subscribe();
+    assert_eq!(tx.receiver_count(), 3);
+
+    for i in 0..10 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+
+    let expected: Vec<_> = (0..10).map(Ok).collect();
+    for rx in vec![rx1, rx2, rx3] {
+        let v: Vec<_> = block_on(rx.collect());
+        assert_eq!(v, expected);
+    }
+}
+
+#[test]
+fn subscribe_sees_only_later_messages() {
+    let (tx, mut rx1) = broadcast::channel::<i32>(16);
+
+    tx.send(1).unwrap();
+    let mut rx2 = tx.subscribe();
+    tx.send(2).unwrap();
+
+    assert_eq!(rx1.try_next().unwrap(), Some(1));
+    assert_eq!(rx1.try_next().unwrap(), Some(2));
+    assert_eq!(rx2.try_next().unwrap(), Some(2));
+    assert!(rx2.try_next().unwrap_err().is_empty());
+}
+
+#[test]
+fn lagged_receiver() {
+    let (tx, mut rx) = broadcast::channel::<i32>(2);
+
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+
+    let err = rx.try_next().unwrap_err();
+    assert!(err.is_lagged());
+    assert_eq!(err.skipped(), Some(3));
+    assert_eq!(rx.try_next().unwrap(), Some(3));
+    assert_eq!(rx.try_next().unwrap(), Some(4));
+    assert!(rx.try_next().unwrap_err().is_empty());
+
+    tx.send(5).unwrap();
+    tx.send(6).unwrap();
+    tx.send(7).unwrap();
+    drop(tx);
+
+    let mut rx = block_on_stream(rx);
+    assert_eq!(rx.next().unwrap().unwrap_err().skipped(), 1);
+    assert_eq!(rx.next(), Some(Ok(6)));
+    assert_eq!(rx.next(), Some(Ok(7)));
+    assert_eq!(rx.next(), None);
+}
+
+#[test]
+fn send_without_receivers_fails() {
+    let (tx, rx) = broadcast::channel::<i32>(4);
+    drop(rx);
+
+    assert_eq!(tx.receiver_count(), 0);
+    assert_eq!(tx.send(1).unwrap_err().into_inner(), 1);
+    assert!(!tx.is_closed());
+
+    let mut rx = tx.subscribe();
+    tx.send(2).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+}
+
+#[test]
+fn receiver_close_drains_buffered_messages() {
+    let (tx, mut rx1) = broadcast::channel::<i32>(4);
+    let mut rx2 = tx.subscribe();
+
+    tx.send(1).unwrap();
+    rx1.close();
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(2).unwrap_err().into_inner(), 2);
+
+    assert_eq!(rx1.try_next().unwrap(), Some(1));
+    assert_eq!(rx1.try_next().unwrap(), None);
+    assert!(rx1.is_terminated());
+    assert_eq!(rx2.try_next().unwrap(), Some(1));
+    assert_eq!(rx2.try_next().unwrap(), None);
+}
+
+#[test]
+fn stream_ends_when_all_senders_dropped() {
+    let (tx1, mut rx) = broadcast::channel::<i32>(4);
+    let mut tx2 = tx1.clone();
+    assert!(tx1.same_receiver(&tx2));
+    assert!(tx2.is_connected_to(&rx));
+
+    drop(tx1);
+    tx2.send(1).unwrap();
+    tx2.disconnect();
+    assert!(tx2.is_closed());
+
+    assert_eq!(block_on(rx.next()), Some(Ok(1)));
+    assert_eq!(block_on(rx.next()), None);
+    assert!(rx.is_terminated());
+    assert_eq!(block_on(rx.next()), None);
+}
+
+#[test]
+fn send_wakes_receivers() {
+    let (tx, mut rx1) = broadcast::channel::<i32>(4);
+    let mut rx2 = tx.subscribe();
+    let (waker, count) = new_count_waker();
+    let cx = &mut futures::task::Context::from_waker(&waker);
+
+    assert_eq!(rx1.poll_next_unpin(cx), Poll::Pending);
+    assert_eq!(rx2.poll_next_unpin(cx), Poll::Pending);
+    assert_eq!(count, 0);
+
+    tx.send(1).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(rx1.poll_next_unpin(cx), Poll::Ready(Some(Ok(1))));
+    assert_eq!(rx2.poll_next_unpin(cx), Poll::Ready(Some(Ok(1))));
+
+    assert_eq!(rx1.poll_next_unpin(cx), Poll::Pending);
+    drop(tx);
+    assert_eq!(count, 3);
+    assert_eq!(rx1.poll_next_unpin(cx), Poll::Ready(None));
+}
+
+#[test]
+fn send_recv_threads() {
+    const AMT: usize = 1000;
+
+    let (tx, rx1) = broadcast::channel::<usize>(AMT);
+    let rx2 = tx.subscribe();
+
+    let t = thread::spawn(move || {
+        for i in 0..AMT {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let readers: Vec<_> = vec![rx1, rx2]
+        .into_iter()
+        .map(|rx| {
+            thread::spawn(move || {
+                let v: Vec<_> = block_on(rx.map(Result::unwrap).collect());
+                assert_eq!(v, (0..AMT).collect::<Vec<_>>());
+            })
+        })
+        .collect();
+
+    t.join().unwrap();
+    for reader in readers {
+        reader.join().unwrap();
+    }
+}
+
+#[test]
+fn stress_lagging_receivers() {
+    const AMT: usize = 10000;
+
+    let (tx, rx) = broadcast::channel::<usize>(8);
+
+    let t = thread::spawn(move || {
+        for i in 0..AMT {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let mut last = None;
+    for i in block_on_stream(rx).filter_map(Result::ok) {
+        if let Some(last) = last {
+            assert!(i > last);
+        }
+        last = Some(i);
+    }
+    assert_eq!(last, Some(AMT - 1));
+
+    t.join().unwrap();
+}
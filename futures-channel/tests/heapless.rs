@@ -0,0 +1,208 @@
+use futures::channel::heapless::oneshot::{Canceled, StaticOneshot};
+use futures::channel::heapless::StaticChannel;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::{FusedFuture, FutureExt};
+use futures::sink::SinkExt;
+use futures::stream::{FusedStream, StreamExt};
+use futures::task::{Context, Poll};
+use futures_test::task::{new_count_waker, noop_context};
+use std::sync::Arc;
+use std::thread;
+
+trait AssertSendSync: Send + Sync {}
+impl AssertSendSync for StaticChannel<i32, 4> {}
+impl AssertSendSync for StaticOneshot<i32> {}
+
+#[test]
+fn split_once() {
+    static CHANNEL: StaticChannel<i32, 2> = StaticChannel::new();
+    static ONESHOT: StaticOneshot<i32> = StaticOneshot::new();
+
+    assert!(CHANNEL.split().is_some());
+    assert!(CHANNEL.split().is_none());
+    assert!(ONESHOT.split().is_some());
+    assert!(ONESHOT.split().is_none());
+}
+
+#[test]
+fn send_recv() {
+    let channel = StaticChannel::<i32, 2>::new();
+    let (mut tx, mut rx) = channel.split().unwrap();
+
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    let err = tx.try_send(3).unwrap_err();
+    assert!(err.is_full());
+    assert_eq!(err.into_inner(), 3);
+
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    tx.try_send(3).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+    assert_eq!(rx.try_next().unwrap(), Some(3));
+    assert!(rx.try_next().is_err());
+
+    drop(tx);
+    assert_eq!(rx.try_next().unwrap(), None);
+    assert!(rx.is_terminated());
+}
+
+#[test]
+fn sink_and_stream() {
+    static CHANNEL: StaticChannel<i32, 1> = StaticChannel::new();
+    let (mut tx, rx) = CHANNEL.split().unwrap();
+
+    let t = thread::spawn(move || block_on(rx.collect::<Vec<_>>()));
+    block_on(async {
+        for i in 0..100 {
+            tx.send(i).await.unwrap();
+        }
+        tx.close().await.unwrap();
+    });
+    assert_eq!(t.join().unwrap(), (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn poll_ready_wakes_sender() {
+    let channel = StaticChannel::<i32, 1>::new();
+    let (mut tx, mut rx) = channel.split().unwrap();
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    tx.try_send(1).unwrap();
+    assert_eq!(tx.poll_ready(cx), Poll::Pending);
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(count, 1);
+    assert_eq!(tx.poll_ready(cx), Poll::Ready(Ok(())));
+}
+
+#[test]
+fn receiver_drop_disconnects() {
+    let msg = Arc::new(());
+    let channel = StaticChannel::<Arc<()>, 4>::new();
+    let (mut tx, rx) = channel.split().unwrap();
+
+    tx.try_send(msg.clone()).unwrap();
+    tx.try_send(msg.clone()).unwrap();
+    drop(rx);
+
+    // The buffered messages are dropped with the receiver.
+    assert_eq!(Arc::strong_count(&msg), 1);
+    assert!(tx.is_closed());
+    assert!(tx.try_send(msg.clone()).unwrap_err().is_disconnected());
+    assert_eq!(
+        tx.poll_ready(&mut noop_context()).map_err(|e| e.is_disconnected()),
+        Poll::Ready(Err(true))
+    );
+}
+
+#[test]
+fn receiver_close_drains() {
+    let channel = StaticChannel::<i32, 4>::new();
+    let (mut tx, mut rx) = channel.split().unwrap();
+
+    tx.try_send(1).unwrap();
+    rx.close();
+    assert!(tx.try_send(2).unwrap_err().is_disconnected());
+    assert_eq!(block_on(rx.next()), Some(1));
+    assert_eq!(block_on(rx.next()), None);
+}
+
+#[test]
+fn unread_messages_dropped_with_channel() {
+    let msg = Arc::new(());
+    {
+        let channel = StaticChannel::<Arc<()>, 4>::new();
+        let (mut tx, rx) = channel.split().unwrap();
+        std::mem::forget(rx);
+        tx.try_send(msg.clone()).unwrap();
+        drop(tx);
+    }
+    assert_eq!(Arc::strong_count(&msg), 1);
+}
+
+#[test]
+fn stress_wrapping_indices() {
+    const AMT: usize = 10000;
+    static CHANNEL: StaticChannel<usize, 3> = StaticChannel::new();
+    let (mut tx, rx) = CHANNEL.split().unwrap();
+
+    let t = thread::spawn(move || {
+        for (i, msg) in block_on_stream(rx).enumerate() {
+            assert_eq!(i, msg);
+        }
+    });
+    block_on(async {
+        for i in 0..AMT {
+            tx.send(i).await.unwrap();
+        }
+    });
+    drop(tx);
+    t.join().unwrap();
+}
+
+#[test]
+fn oneshot_send_recv() {
+    static ONESHOT: StaticOneshot<i32> = StaticOneshot::new();
+    let (tx, rx) = ONESHOT.split().unwrap();
+
+    let t = thread::spawn(move || block_on(rx));
+    tx.send(1).unwrap();
+    assert_eq!(t.join().unwrap(), Ok(1));
+}
+
+#[test]
+fn oneshot_sender_dropped() {
+    let oneshot = StaticOneshot::<i32>::new();
+    let (tx, mut rx) = oneshot.split().unwrap();
+    let (waker, count) = new_count_waker();
+
+    assert_eq!(rx.poll_unpin(&mut Context::from_waker(&waker)), Poll::Pending);
+    drop(tx);
+    assert_eq!(count, 1);
+    assert_eq!(rx.poll_unpin(&mut noop_context()), Poll::Ready(Err(Canceled)));
+    assert!(rx.is_terminated());
+}
+
+#[test]
+fn oneshot_close() {
+    let oneshot = StaticOneshot::<i32>::new();
+    let (mut tx, mut rx) = oneshot.split().unwrap();
+    let (waker, count) = new_count_waker();
+
+    assert_eq!(tx.poll_canceled(&mut Context::from_waker(&waker)), Poll::Pending);
+    rx.close();
+    assert_eq!(count, 1);
+    assert!(tx.is_canceled());
+    assert_eq!(tx.send(1), Err(1));
+    assert_eq!(rx.try_recv(), Err(Canceled));
+}
+
+#[test]
+fn oneshot_close_after_send() {
+    let oneshot = StaticOneshot::<i32>::new();
+    let (tx, mut rx) = oneshot.split().unwrap();
+
+    tx.send(1).unwrap();
+    rx.close();
+    assert_eq!(rx.try_recv(), Ok(Some(1)));
+}
+
+#[test]
+fn oneshot_unreceived_value_dropped() {
+    let msg = Arc::new(());
+    let oneshot = StaticOneshot::<Arc<()>>::new();
+    let (tx, rx) = oneshot.split().unwrap();
+
+    tx.send(msg.clone()).unwrap();
+    drop(rx);
+    assert_eq!(Arc::strong_count(&msg), 1);
+}
+
+#[test]
+fn oneshot_send_after_receiver_dropped() {
+    let oneshot = StaticOneshot::<i32>::new();
+    let (tx, rx) = oneshot.split().unwrap();
+
+    drop(rx);
+    assert_eq!(tx.send(1), Err(1));
+}
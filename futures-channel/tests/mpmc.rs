@@ -0,0 +1,276 @@
+use futures::channel::mpmc;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::{poll_fn, FusedFuture, Future, FutureExt};
+use futures::pin_mut;
+use futures::sink::{Sink, SinkExt};
+use futures::stream::{FusedStream, Stream, StreamExt};
+use futures::task::{Context, Poll};
+use futures_test::task::{new_count_waker, noop_context};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn send_recv() {
+    let (mut tx, rx) = mpmc::channel::<i32>(16);
+
+    block_on(tx.send(1)).unwrap();
+    drop(tx);
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![1]);
+}
+
+#[test]
+fn each_message_delivered_once() {
+    let (mut tx, mut rx1) = mpmc::channel::<i32>(16);
+    let mut rx2 = rx1.clone();
+
+    for i in 0..4 {
+        tx.try_send(i).unwrap();
+    }
+    assert_eq!(rx1.try_next().unwrap(), Some(0));
+    assert_eq!(rx2.try_next().unwrap(), Some(1));
+    assert_eq!(rx2.try_next().unwrap(), Some(2));
+    assert_eq!(rx1.try_next().unwrap(), Some(3));
+    assert!(rx1.try_next().is_err());
+    assert!(rx2.try_next().is_err());
+
+    drop(tx);
+    assert_eq!(rx1.try_next().unwrap(), None);
+    assert!(rx1.is_terminated());
+    assert_eq!(rx2.try_next().unwrap(), None);
+}
+
+#[test]
+fn buffer_is_a_hard_limit() {
+    let (mut tx1, mut rx) = mpmc::channel::<i32>(2);
+    let mut tx2 = tx1.clone();
+    let mut tx3 = tx1.clone();
+
+    tx1.try_send(1).unwrap();
+    tx2.try_send(2).unwrap();
+    let err = tx3.try_send(3).unwrap_err();
+    assert!(err.is_full());
+    assert_eq!(err.into_inner(), 3);
+
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    tx3.try_send(3).unwrap();
+    assert!(tx1.try_send(4).unwrap_err().is_full());
+}
+
+#[test]
+fn poll_ready_reserves_a_slot() {
+    let (mut tx1, mut rx) = mpmc::channel::<i32>(1);
+    let mut tx2 = tx1.clone();
+    let cx = &mut noop_context();
+
+    assert_eq!(tx1.poll_ready(cx), Poll::Ready(Ok(())));
+    assert!(tx2.try_send(2).unwrap_err().is_full());
+    assert_eq!(tx2.poll_ready(cx), Poll::Pending);
+    tx1.start_send(1).unwrap();
+
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(tx2.poll_ready(cx), Poll::Ready(Ok(())));
+
+    // Dropping a sender gives its reserved slot back.
+    drop(tx2);
+    tx1.try_send(3).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some(3));
+}
+
+#[test]
+fn receivers_woken_in_order() {
+    let (mut tx, mut rx1) = mpmc::channel::<i32>(16);
+    let mut rx2 = rx1.clone();
+    let (waker1, count1) = new_count_waker();
+    let (waker2, count2) = new_count_waker();
+
+    assert_eq!(rx1.poll_next_unpin(&mut Context::from_waker(&waker1)), Poll::Pending);
+    assert_eq!(rx2.poll_next_unpin(&mut Context::from_waker(&waker2)), Poll::Pending);
+    // Polling again does not lose the receiver's place in the queue.
+    assert_eq!(rx1.poll_next_unpin(&mut Context::from_waker(&waker1)), Poll::Pending);
+
+    tx.try_send(1).unwrap();
+    assert_eq!(count1, 1);
+    assert_eq!(count2, 0);
+
+    tx.try_send(2).unwrap();
+    assert_eq!(count1, 1);
+    assert_eq!(count2, 1);
+
+    assert_eq!(rx2.try_next().unwrap(), Some(1));
+    assert_eq!(rx1.try_next().unwrap(), Some(2));
+}
+
+#[test]
+fn dropped_receiver_passes_on_wakeup() {
+    let (mut tx, mut rx1) = mpmc::channel::<i32>(16);
+    let mut rx2 = rx1.clone();
+    let (waker1, count1) = new_count_waker();
+    let (waker2, count2) = new_count_waker();
+
+    assert_eq!(rx1.poll_next_unpin(&mut Context::from_waker(&waker1)), Poll::Pending);
+    assert_eq!(rx2.poll_next_unpin(&mut Context::from_waker(&waker2)), Poll::Pending);
+
+    tx.try_send(1).unwrap();
+    assert_eq!(count1, 1);
+    drop(rx1);
+    assert_eq!(count2, 1);
+    assert_eq!(rx2.try_next().unwrap(), Some(1));
+}
+
+#[test]
+fn dropped_recv_passes_on_wakeup() {
+    let (mut tx, mut rx1) = mpmc::channel::<i32>(16);
+    let mut rx2 = rx1.clone();
+    let (waker1, count1) = new_count_waker();
+    let (waker2, count2) = new_count_waker();
+
+    let mut recv1 = rx1.recv();
+    assert_eq!(recv1.poll_unpin(&mut Context::from_waker(&waker1)), Poll::Pending);
+    let mut recv2 = rx2.recv();
+    assert_eq!(recv2.poll_unpin(&mut Context::from_waker(&waker2)), Poll::Pending);
+
+    tx.try_send(1).unwrap();
+    assert_eq!(count1, 1);
+    assert_eq!(count2, 0);
+    drop(recv1);
+    assert_eq!(count2, 1);
+    assert_eq!(recv2.poll_unpin(&mut Context::from_waker(&waker2)), Poll::Ready(Some(1)));
+    assert!(recv2.is_terminated());
+    drop(recv2);
+
+    // A receiver which was not woken gives up its place in the queue.
+    let mut recv1 = rx1.recv();
+    assert_eq!(recv1.poll_unpin(&mut Context::from_waker(&waker1)), Poll::Pending);
+    drop(recv1);
+    let mut recv2 = rx2.recv();
+    assert_eq!(recv2.poll_unpin(&mut Context::from_waker(&waker2)), Poll::Pending);
+    tx.try_send(2).unwrap();
+    assert_eq!(count1, 1);
+    assert_eq!(count2, 2);
+}
+
+#[test]
+fn senders_woken_in_order() {
+    block_on(poll_fn(move |cx| {
+        let (mut tx1, mut rx) = mpmc::channel::<i32>(1);
+        let mut tx2 = tx1.clone();
+        let (waker2, count2) = new_count_waker();
+
+        tx1.try_send(1).unwrap();
+        assert_eq!(tx2.poll_ready(&mut Context::from_waker(&waker2)), Poll::Pending);
+        assert_eq!(tx1.poll_ready(cx), Poll::Pending);
+
+        assert_eq!(rx.try_next().unwrap(), Some(1));
+        assert_eq!(count2, 1);
+        assert_eq!(tx2.poll_ready(cx), Poll::Ready(Ok(())));
+        tx2.start_send(2).unwrap();
+
+        assert_eq!(rx.try_next().unwrap(), Some(2));
+        assert_eq!(tx1.poll_ready(cx), Poll::Ready(Ok(())));
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn close_from_receiver() {
+    let (mut tx, mut rx1) = mpmc::channel::<i32>(4);
+    let mut rx2 = rx1.clone();
+
+    tx.try_send(1).unwrap();
+    rx1.close();
+    assert!(tx.is_closed());
+    assert!(tx.try_send(2).unwrap_err().is_disconnected());
+
+    assert_eq!(rx2.try_next().unwrap(), Some(1));
+    assert_eq!(rx1.try_next().unwrap(), None);
+    assert_eq!(rx2.try_next().unwrap(), None);
+}
+
+#[test]
+fn dropping_all_receivers_disconnects() {
+    let (mut tx, rx1) = mpmc::channel::<i32>(4);
+    let rx2 = rx1.clone();
+    assert!(tx.is_connected_to(&rx2));
+
+    drop(rx1);
+    assert!(!tx.is_closed());
+    tx.try_send(1).unwrap();
+    drop(rx2);
+    assert!(tx.is_closed());
+    assert!(block_on(tx.send(2)).unwrap_err().is_disconnected());
+}
+
+#[test]
+fn sink_close_disconnects_sender() {
+    let (tx1, mut rx) = mpmc::channel::<i32>(4);
+    let mut tx2 = tx1.clone();
+    assert!(tx1.same_receiver(&tx2));
+
+    block_on(tx2.close()).unwrap();
+    assert!(tx2.is_closed());
+    assert!(!tx1.is_closed());
+
+    drop(tx1);
+    assert_eq!(block_on(rx.next()), None);
+}
+
+#[test]
+fn send_recv_no_buffer_pressure() {
+    block_on(poll_fn(move |cx| {
+        let (tx, rx) = mpmc::channel::<i32>(1);
+        pin_mut!(tx, rx);
+
+        assert!(tx.as_mut().poll_ready(cx).is_ready());
+        assert!(tx.as_mut().start_send(1).is_ok());
+        assert!(tx.as_mut().poll_ready(cx).is_pending());
+
+        assert_eq!(rx.as_mut().poll_next(cx), Poll::Ready(Some(1)));
+        assert!(tx.as_mut().poll_ready(cx).is_ready());
+
+        Poll::Ready(())
+    }));
+}
+
+#[test]
+fn work_queue_threads() {
+    const AMT: usize = 10000;
+    const NTHREADS: usize = 4;
+
+    let (tx, rx) = mpmc::channel::<usize>(8);
+    let total = Arc::new(AtomicUsize::new(0));
+
+    let workers: Vec<_> = (0..NTHREADS)
+        .map(|_| {
+            let rx = rx.clone();
+            let total = total.clone();
+            thread::spawn(move || {
+                for i in block_on_stream(rx) {
+                    total.fetch_add(i, Ordering::SeqCst);
+                }
+            })
+        })
+        .collect();
+    drop(rx);
+
+    let producers: Vec<_> = (0..NTHREADS)
+        .map(|n| {
+            let mut tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..AMT {
+                    if i % NTHREADS == n {
+                        block_on(tx.send(i)).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    for t in producers.into_iter().chain(workers) {
+        t.join().unwrap();
+    }
+    assert_eq!(total.load(Ordering::SeqCst), (0..AMT).sum::<usize>());
+}
@@ -0,0 +1,210 @@
+use futures::channel::mpsc;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::FutureExt;
+use futures::stream::{FusedStream, Stream, StreamExt};
+use futures::task::{Context, Poll};
+use futures_test::task::{new_count_waker, noop_context};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn highest_priority_first() {
+    let (mut tx, mut rx) = mpsc::priority_channel(3, 8);
+
+    tx.try_send_with_priority("low", 2).unwrap();
+    tx.try_send_with_priority("mid", 1).unwrap();
+    tx.try_send_with_priority("high", 0).unwrap();
+    tx.try_send_with_priority("mid 2", 1).unwrap();
+
+    assert_eq!(rx.try_next().unwrap(), Some("high"));
+    assert_eq!(rx.try_next().unwrap(), Some("mid"));
+    tx.try_send_with_priority("high 2", 0).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some("high 2"));
+    assert_eq!(rx.try_next().unwrap(), Some("mid 2"));
+    assert_eq!(rx.try_next().unwrap(), Some("low"));
+    assert!(rx.try_next().is_err());
+
+    drop(tx);
+    assert_eq!(rx.try_next().unwrap(), None);
+    assert!(rx.is_terminated());
+}
+
+#[test]
+fn fifo_within_level() {
+    let (mut tx, rx) = mpsc::priority_channel(2, 100);
+
+    for i in 0..100 {
+        tx.try_send_with_priority(i, i % 2).unwrap();
+    }
+    drop(tx);
+
+    let received: Vec<_> = block_on_stream(rx).collect();
+    let expected: Vec<_> =
+        (0..100).filter(|i| i % 2 == 0).chain((0..100).filter(|i| i % 2 == 1)).collect();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn capacity_per_level() {
+    let (mut tx, mut rx) = mpsc::priority_channel(2, 2);
+
+    tx.try_send_with_priority(1, 1).unwrap();
+    tx.try_send_with_priority(2, 1).unwrap();
+    assert!(tx.try_send_with_priority(3, 1).unwrap_err().is_full());
+
+    // A full low-priority level does not block the high-priority one.
+    tx.try_send_with_priority(10, 0).unwrap();
+    tx.try_send_with_priority(11, 0).unwrap();
+    assert!(tx.try_send_with_priority(12, 0).unwrap_err().is_full());
+
+    assert_eq!(rx.len_of(0), 2);
+    assert_eq!(rx.len_of(1), 2);
+    assert_eq!(rx.size_hint(), (4, None));
+
+    assert_eq!(rx.try_next().unwrap(), Some(10));
+    assert!(tx.try_send_with_priority(3, 1).unwrap_err().is_full());
+    tx.try_send_with_priority(12, 0).unwrap();
+}
+
+#[test]
+fn poll_ready_reserves_a_slot_in_its_level() {
+    let (mut tx1, mut rx) = mpsc::priority_channel(2, 1);
+    let mut tx2 = tx1.clone();
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    assert_eq!(tx1.poll_ready_with_priority(cx, 1), Poll::Ready(Ok(())));
+    assert_eq!(tx2.poll_ready_with_priority(cx, 1), Poll::Pending);
+    assert!(tx2.try_send_with_priority(1, 1).unwrap_err().is_full());
+    tx2.try_send_with_priority(0, 0).unwrap();
+
+    tx1.start_send_with_priority(1, 1).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some(0));
+    assert_eq!(count, 0);
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(count, 1);
+    assert_eq!(tx2.poll_ready_with_priority(cx, 1), Poll::Ready(Ok(())));
+}
+
+#[test]
+fn dropped_reservation_is_passed_on() {
+    let (mut tx1, _rx) = mpsc::priority_channel::<i32>(1, 1);
+    let mut tx2 = tx1.clone();
+    let (waker, count) = new_count_waker();
+
+    assert_eq!(tx1.poll_ready_with_priority(&mut noop_context(), 0), Poll::Ready(Ok(())));
+    assert_eq!(tx2.poll_ready_with_priority(&mut Context::from_waker(&waker), 0), Poll::Pending);
+    drop(tx1);
+    assert_eq!(count, 1);
+    assert_eq!(tx2.poll_ready_with_priority(&mut noop_context(), 0), Poll::Ready(Ok(())));
+}
+
+#[test]
+fn send_with_priority_waits_for_capacity() {
+    let (mut tx, mut rx) = mpsc::priority_channel(2, 1);
+
+    block_on(tx.send_with_priority(1, 1)).unwrap();
+    let mut send = tx.send_with_priority(2, 1);
+    assert_eq!(send.poll_unpin(&mut noop_context()), Poll::Pending);
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(send.poll_unpin(&mut noop_context()), Poll::Ready(Ok(())));
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+}
+
+#[test]
+fn dropped_send_with_priority_passes_on_wakeup() {
+    let (mut tx1, mut rx) = mpsc::priority_channel(1, 1);
+    let mut tx2 = tx1.clone();
+    let mut tx3 = tx1.clone();
+    let (waker1, count1) = new_count_waker();
+    let (waker2, count2) = new_count_waker();
+
+    tx3.try_send_with_priority(0, 0).unwrap();
+    let mut send1 = tx1.send_with_priority(1, 0);
+    assert_eq!(send1.poll_unpin(&mut Context::from_waker(&waker1)), Poll::Pending);
+    let mut send2 = tx2.send_with_priority(2, 0);
+    assert_eq!(send2.poll_unpin(&mut Context::from_waker(&waker2)), Poll::Pending);
+
+    assert_eq!(rx.try_next().unwrap(), Some(0));
+    assert_eq!(count1, 1);
+    assert_eq!(count2, 0);
+    drop(send1);
+    assert_eq!(count2, 1);
+    assert_eq!(send2.poll_unpin(&mut noop_context()), Poll::Ready(Ok(())));
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+}
+
+#[test]
+fn receiver_woken_by_send() {
+    let (mut tx, mut rx) = mpsc::priority_channel(2, 1);
+    let (waker, count) = new_count_waker();
+
+    assert_eq!(rx.poll_next_unpin(&mut Context::from_waker(&waker)), Poll::Pending);
+    tx.try_send_with_priority(1, 1).unwrap();
+    assert_eq!(count, 1);
+    drop(tx);
+    assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![1]);
+}
+
+#[test]
+fn close_drains_all_levels() {
+    let (mut tx, mut rx) = mpsc::priority_channel(2, 4);
+
+    tx.try_send_with_priority(1, 1).unwrap();
+    tx.try_send_with_priority(0, 0).unwrap();
+    rx.close();
+    assert!(tx.is_closed());
+    assert!(tx.try_send_with_priority(2, 0).unwrap_err().is_disconnected());
+    assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![0, 1]);
+}
+
+#[test]
+fn receiver_drop_drops_messages() {
+    let msg = Arc::new(());
+    let (mut tx, rx) = mpsc::priority_channel(2, 4);
+
+    tx.try_send_with_priority(msg.clone(), 0).unwrap();
+    tx.try_send_with_priority(msg.clone(), 1).unwrap();
+    drop(rx);
+
+    assert_eq!(Arc::strong_count(&msg), 1);
+    assert!(block_on(tx.send_with_priority(msg.clone(), 0)).unwrap_err().is_disconnected());
+}
+
+#[test]
+#[should_panic(expected = "priority level 2 out of range for 2 levels")]
+fn level_out_of_range() {
+    let (mut tx, _rx) = mpsc::priority_channel(2, 4);
+    let _ = tx.try_send_with_priority(1, 2);
+}
+
+#[test]
+fn stress_levels_across_threads() {
+    const AMT: usize = 1000;
+    const LEVELS: usize = 4;
+    let (tx, rx) = mpsc::priority_channel(LEVELS, 8);
+
+    let threads: Vec<_> = (0..LEVELS)
+        .map(|level| {
+            let mut tx = tx.clone();
+            thread::spawn(move || {
+                block_on(async {
+                    for i in 0..AMT {
+                        tx.send_with_priority((level, i), level).await.unwrap();
+                    }
+                })
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut next = [0; LEVELS];
+    for (level, i) in block_on_stream(rx) {
+        assert_eq!(next[level], i);
+        next[level] += 1;
+    }
+    assert_eq!(next, [AMT; LEVELS]);
+    for t in threads {
+        t.join().unwrap();
+    }
+}
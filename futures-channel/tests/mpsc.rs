@@ -1,11 +1,11 @@
 use futures::channel::{mpsc, oneshot};
 use futures::executor::{block_on, block_on_stream};
-use futures::future::{poll_fn, FutureExt};
+use futures::future::{poll_fn, FusedFuture, FutureExt};
 use futures::pin_mut;
 use futures::sink::{Sink, SinkExt};
-use futures::stream::{Stream, StreamExt};
+use futures::stream::{FusedStream, Stream, StreamExt};
 use futures::task::{Context, Poll};
-use futures_test::task::{new_count_waker, noop_context};
+use futures_test::task::{new_count_waker, noop_context, panic_context};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -102,6 +102,20 @@ fn send_recv_threads_no_capacity() {
     t.join().unwrap();
 }
 
+#[test]
+fn rendezvous_send_waits_for_receiver() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(0);
+    let cx = &mut noop_context();
+
+    tx.try_send(1).unwrap();
+    assert!(tx.try_send(2).unwrap_err().is_full());
+    assert_eq!(tx.poll_flush_unpin(cx), Poll::Pending);
+
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(tx.poll_flush_unpin(cx), Poll::Ready(Ok(())));
+    tx.try_send(2).unwrap();
+}
+
 #[test]
 fn recv_close_gets_none() {
     let (mut tx, mut rx) = mpsc::channel::<i32>(10);
@@ -497,10 +511,8 @@ fn try_send_fail() {
 fn try_send_recv() {
     let (mut tx, mut rx) = mpsc::channel(1);
     tx.try_send("hello").unwrap();
-    tx.try_send("hello").unwrap();
     tx.try_send("hello").unwrap_err(); // should be full
     rx.try_next().unwrap();
-    rx.try_next().unwrap();
     rx.try_next().unwrap_err(); // should be empty
     tx.try_send("hello").unwrap();
     rx.try_next().unwrap();
@@ -628,3 +640,841 @@ fn send_backpressure_multi_senders() {
     let item = block_on(rx.next()).unwrap();
     assert_eq!(item, 2);
 }
+
+#[test]
+fn reserve_send() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+
+    let permit = block_on(tx.reserve()).unwrap();
+    permit.send(1);
+    assert_eq!(block_on(rx.next()), Some(1));
+
+    // Dropping an unused permit leaves the capacity available.
+    drop(block_on(tx.reserve()).unwrap());
+    block_on(tx.reserve()).unwrap().send(2);
+    drop(tx);
+    assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![2]);
+}
+
+#[test]
+fn reserve_backpressure() {
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let (mut tx, mut rx) = mpsc::channel::<i32>(0);
+    block_on(tx.reserve()).unwrap().send(1);
+
+    let mut task = tx.reserve();
+    assert!(task.poll_unpin(&mut cx).is_pending());
+    assert_eq!(counter, 0);
+
+    assert_eq!(block_on(rx.next()), Some(1));
+    assert_eq!(counter, 1);
+    match task.poll_unpin(&mut cx) {
+        Poll::Ready(Ok(permit)) => permit.send(2),
+        _ => panic!("capacity should be available"),
+    }
+    assert!(task.is_terminated());
+
+    assert_eq!(block_on(rx.next()), Some(2));
+}
+
+#[test]
+fn reserve_disconnected() {
+    let (mut tx, rx) = mpsc::channel::<i32>(1);
+    drop(rx);
+    assert!(block_on(tx.reserve()).unwrap_err().is_disconnected());
+
+    let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+    let permit = block_on(tx.reserve()).unwrap();
+    rx.close();
+    // The message is dropped, since the receiver went away.
+    permit.send(1);
+    assert_eq!(block_on(rx.next()), None);
+}
+
+#[test]
+fn reserve_owned() {
+    let (tx, rx) = mpsc::channel::<i32>(0);
+
+    let permit = block_on(tx.reserve_owned()).unwrap();
+    let t = thread::spawn(move || {
+        let tx = permit.send(1);
+        let tx = block_on(tx.reserve_owned()).unwrap().release();
+        block_on(tx.reserve_owned()).unwrap().send(2);
+    });
+
+    assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![1, 2]);
+    t.join().unwrap();
+}
+
+#[test]
+fn capacity_does_not_grow_with_senders() {
+    let (tx, mut rx) = mpsc::channel::<i32>(2);
+    let mut senders: Vec<_> = (0..8).map(|_| tx.clone()).collect();
+
+    let sent = senders.iter_mut().zip(0..).filter_map(|(tx, i)| tx.try_send(i).ok());
+    assert_eq!(sent.count(), 2);
+
+    assert_eq!(rx.try_next().unwrap(), Some(0));
+    senders[7].try_send(7).unwrap();
+    assert!(senders[6].try_send(6).unwrap_err().is_full());
+}
+
+#[test]
+fn waiting_senders_get_capacity_in_order() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(0);
+    let mut tx2 = tx1.clone();
+    let mut tx3 = tx1.clone();
+    let (waker2, count2) = new_count_waker();
+    let (waker3, count3) = new_count_waker();
+
+    tx1.try_send(1).unwrap();
+    assert_eq!(tx2.poll_ready(&mut Context::from_waker(&waker2)), Poll::Pending);
+    assert_eq!(tx3.poll_ready(&mut Context::from_waker(&waker3)), Poll::Pending);
+
+    // The freed slot goes to the sender that has waited the longest, and
+    // cannot be taken by a sender that did not wait.
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(count2, 1);
+    assert_eq!(count3, 0);
+    assert!(tx1.try_send(2).unwrap_err().is_full());
+    tx2.try_send(2).unwrap();
+
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+    assert_eq!(count3, 1);
+    assert_eq!(tx3.poll_ready(&mut noop_context()), Poll::Ready(Ok(())));
+}
+
+#[test]
+fn dropped_sender_gives_capacity_back() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(0);
+    let mut tx2 = tx1.clone();
+    let mut tx3 = tx1.clone();
+    let (waker3, count3) = new_count_waker();
+    let cx = &mut noop_context();
+
+    // A sender holding reserved capacity.
+    assert_eq!(tx1.poll_ready(cx), Poll::Ready(Ok(())));
+    assert_eq!(tx2.poll_ready(cx), Poll::Pending);
+    assert_eq!(tx3.poll_ready(&mut Context::from_waker(&waker3)), Poll::Pending);
+
+    // A waiting sender does not keep its place in line once dropped.
+    drop(tx2);
+    drop(tx1);
+    assert_eq!(count3, 1);
+    tx3.try_send(3).unwrap();
+    assert_eq!(rx.try_next().unwrap(), Some(3));
+}
+
+#[test]
+fn stress_capacity_is_hard_bound() {
+    const AMT: usize = 1000;
+    const NTHREADS: usize = 8;
+    const BUFFER: usize = 4;
+
+    let (tx, rx) = mpsc::channel::<usize>(BUFFER);
+    let in_flight = Arc::new(AtomicUsize::new(0));
+
+    let threads: Vec<_> = (0..NTHREADS)
+        .map(|_| {
+            let mut tx = tx.clone();
+            let in_flight = in_flight.clone();
+            thread::spawn(move || {
+                for _ in 0..AMT {
+                    block_on(poll_fn(|cx| tx.poll_ready(cx))).unwrap();
+                    let n = in_flight.fetch_add(1, Ordering::SeqCst);
+                    tx.start_send(n).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    for _ in block_on_stream(rx) {
+        // The slot of the message just received is already free again, so
+        // it is counted on top of the channel's capacity.
+        let n = in_flight.fetch_sub(1, Ordering::SeqCst);
+        assert!(n <= BUFFER + 1, "{} messages in flight", n);
+    }
+
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+#[test]
+fn weak_sender_does_not_keep_channel_open() {
+    let (tx, mut rx) = mpsc::channel::<i32>(1);
+    let weak = tx.downgrade();
+
+    let mut upgraded = weak.upgrade().unwrap();
+    drop(tx);
+    upgraded.try_send(1).unwrap();
+    drop(upgraded);
+
+    assert_eq!(block_on(rx.next()), Some(1));
+    assert_eq!(block_on(rx.next()), None);
+    assert!(weak.upgrade().is_none());
+    assert!(weak.clone().upgrade().is_none());
+}
+
+#[test]
+fn weak_unbounded_sender_does_not_keep_channel_open() {
+    let (tx, rx) = mpsc::unbounded::<i32>();
+    let weak = tx.downgrade();
+    weak.upgrade().unwrap().unbounded_send(1).unwrap();
+    drop(tx);
+
+    assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![1]);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn weak_sender_outliving_receiver() {
+    let (mut tx, rx) = mpsc::channel::<i32>(1);
+    let weak = tx.downgrade();
+    drop(rx);
+
+    let mut upgraded = weak.upgrade().unwrap();
+    assert!(upgraded.try_send(1).unwrap_err().is_disconnected());
+
+    tx.disconnect();
+    assert!(tx.downgrade().upgrade().is_none());
+}
+
+#[test]
+fn drop_newest_policy() {
+    let (mut tx, mut rx) = mpsc::channel_with_policy::<i32>(2, mpsc::OverflowPolicy::DropNewest);
+
+    for i in 0..5 {
+        tx.try_send(i).unwrap();
+    }
+    assert_eq!(rx.dropped_count(), 3);
+    assert_eq!(rx.try_next().unwrap(), Some(0));
+    tx.try_send(5).unwrap();
+
+    drop(tx);
+    assert_eq!(block_on(rx.by_ref().collect::<Vec<_>>()), vec![1, 5]);
+    assert_eq!(rx.dropped_count(), 3);
+}
+
+#[test]
+fn drop_oldest_policy() {
+    let (mut tx, mut rx) = mpsc::channel_with_policy::<i32>(2, mpsc::OverflowPolicy::DropOldest);
+
+    for i in 0..5 {
+        tx.try_send(i).unwrap();
+    }
+    assert_eq!(rx.dropped_count(), 3);
+    assert_eq!(rx.try_next().unwrap(), Some(3));
+    tx.try_send(5).unwrap();
+
+    drop(tx);
+    assert_eq!(block_on(rx.by_ref().collect::<Vec<_>>()), vec![4, 5]);
+    assert_eq!(rx.dropped_count(), 3);
+}
+
+#[test]
+fn drop_policy_sink_never_blocks() {
+    let (mut tx, mut rx) = mpsc::channel_with_policy::<i32>(1, mpsc::OverflowPolicy::DropOldest);
+    let cx = &mut panic_context();
+
+    for i in 0..3 {
+        assert_eq!(tx.poll_ready_unpin(cx), Poll::Ready(Ok(())));
+        tx.start_send_unpin(i).unwrap();
+        assert_eq!(tx.poll_flush_unpin(cx), Poll::Ready(Ok(())));
+    }
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+    assert_eq!(rx.dropped_count(), 2);
+
+    rx.close();
+    assert!(tx.try_send(3).unwrap_err().is_disconnected());
+    assert_eq!(rx.dropped_count(), 2);
+}
+
+#[test]
+fn drop_policy_permit_reserves_nothing() {
+    let (mut tx, mut rx) = mpsc::channel_with_policy::<i32>(1, mpsc::OverflowPolicy::DropNewest);
+
+    let permit = block_on(tx.reserve()).unwrap();
+    permit.send(1);
+    let permit = block_on(tx.reserve()).unwrap();
+    permit.send(2);
+    assert_eq!(rx.dropped_count(), 1);
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+
+    let (mut tx, mut rx) = mpsc::channel_with_policy::<i32>(1, mpsc::OverflowPolicy::DropOldest);
+    let mut tx2 = tx.clone();
+    let permit = block_on(tx.reserve()).unwrap();
+    tx2.try_send(1).unwrap();
+    permit.send(2);
+    assert_eq!(rx.dropped_count(), 1);
+    assert_eq!(rx.try_next().unwrap(), Some(2));
+}
+
+#[test]
+fn drop_oldest_while_receiving() {
+    const AMT: usize = 10000;
+    const NTHREADS: usize = 2;
+
+    // Senders make room while the receiver is taking messages, which must
+    // neither lose a message's slot nor count it twice.
+    let (tx, mut rx) = mpsc::channel_with_policy::<usize>(1, mpsc::OverflowPolicy::DropOldest);
+
+    let threads: Vec<_> = (0..NTHREADS)
+        .map(|_| {
+            let mut tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..AMT {
+                    tx.try_send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut received = 0;
+    loop {
+        match rx.try_next() {
+            Ok(Some(_)) => received += 1,
+            Ok(None) => break,
+            Err(_) => thread::yield_now(),
+        }
+    }
+    assert_eq!(received + rx.dropped_count(), AMT * NTHREADS);
+
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+#[test]
+#[should_panic(expected = "needs a buffer")]
+fn drop_policy_zero_buffer() {
+    let _ = mpsc::channel_with_policy::<i32>(0, mpsc::OverflowPolicy::DropNewest);
+}
+
+#[test]
+fn stress_drop_oldest() {
+    const AMT: usize = 10000;
+    const NTHREADS: usize = 4;
+
+    let (tx, rx) = mpsc::channel_with_policy::<usize>(4, mpsc::OverflowPolicy::DropOldest);
+
+    let threads: Vec<_> = (0..NTHREADS)
+        .map(|_| {
+            let mut tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..AMT {
+                    block_on(tx.send(i)).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut rx = block_on_stream(rx);
+    let received = rx.by_ref().count();
+    assert_eq!(received + rx.dropped_count(), AMT * NTHREADS);
+
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+#[test]
+fn send_recv_blocking() {
+    const AMT: usize = 1000;
+
+    let (mut tx, mut rx) = mpsc::channel::<usize>(0);
+    let (utx, mut urx) = mpsc::unbounded::<usize>();
+
+    let t = thread::spawn(move || {
+        for i in 0..AMT {
+            tx.send_blocking(i).unwrap();
+            utx.unbounded_send(i).unwrap();
+        }
+    });
+
+    for i in 0..AMT {
+        assert_eq!(rx.recv_blocking(), Some(i));
+        assert_eq!(urx.recv_blocking(), Some(i));
+    }
+    assert_eq!(rx.recv_blocking(), None);
+    assert_eq!(urx.recv_blocking(), None);
+    t.join().unwrap();
+}
+
+#[test]
+fn send_blocking_disconnected() {
+    let (mut tx, rx) = mpsc::channel::<i32>(0);
+    drop(rx);
+    assert!(tx.send_blocking(1).unwrap_err().is_disconnected());
+}
+
+#[test]
+fn blocking_in_executor_context() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+
+    // Unlike a nested `block_on`, these do not panic.
+    block_on(async {
+        tx.send_blocking(1).unwrap();
+        assert_eq!(rx.recv_blocking(), Some(1));
+    });
+}
+
+#[test]
+fn recv_many() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(8);
+    let mut buf = Vec::new();
+
+    assert_eq!(rx.poll_recv_many(&mut noop_context(), &mut buf, 4), Poll::Pending);
+    for i in 0..6 {
+        tx.try_send(i).unwrap();
+    }
+
+    assert_eq!(block_on(rx.recv_many(&mut buf, 4)), 4);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 0)), 0);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 4)), 2);
+    assert_eq!(buf, [0, 1, 2, 3, 4, 5]);
+
+    drop(tx);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 4)), 0);
+    assert!(rx.is_terminated());
+}
+
+#[test]
+fn unbounded_recv_many() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+    let (waker, count) = new_count_waker();
+    let mut buf = Vec::new();
+
+    assert_eq!(rx.poll_recv_many(&mut Context::from_waker(&waker), &mut buf, 4), Poll::Pending);
+    for i in 0..6 {
+        tx.unbounded_send(i).unwrap();
+    }
+    assert_eq!(count, 1);
+
+    assert_eq!(block_on(rx.recv_many(&mut buf, 4)), 4);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 0)), 0);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 4)), 2);
+    assert_eq!(buf, [0, 1, 2, 3, 4, 5]);
+    assert!(rx.is_empty());
+
+    drop(tx);
+    assert_eq!(block_on(rx.recv_many(&mut buf, 4)), 0);
+    assert!(rx.is_terminated());
+}
+
+#[test]
+fn recv_many_frees_capacity() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(2);
+    let mut tx2 = tx1.clone();
+    let (waker, count) = new_count_waker();
+
+    tx1.try_send(1).unwrap();
+    tx1.try_send(2).unwrap();
+    assert_eq!(tx2.poll_ready(&mut Context::from_waker(&waker)), Poll::Pending);
+
+    let mut buf = Vec::new();
+    assert_eq!(block_on(rx.recv_many(&mut buf, 8)), 2);
+    assert_eq!(count, 1);
+    tx2.try_send(3).unwrap();
+    tx1.try_send(4).unwrap();
+    assert!(tx1.try_send(5).unwrap_err().is_full());
+}
+
+#[test]
+fn send_batch_is_not_interleaved() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(3);
+    let mut tx2 = tx1.clone();
+    let cx = &mut noop_context();
+
+    tx1.try_send(0).unwrap();
+    let mut batch = tx1.send_batch(vec![1, 2, 3]);
+    assert_eq!(batch.poll_unpin(cx), Poll::Pending);
+
+    // The batch waits for room for all of its messages, and does not let
+    // other senders in ahead of it.
+    assert_eq!(rx.try_next().unwrap(), Some(0));
+    assert!(tx2.try_send(4).unwrap_err().is_full());
+
+    assert_eq!(batch.poll_unpin(cx), Poll::Ready(Ok(())));
+    drop(batch);
+    assert!(tx2.try_send(4).unwrap_err().is_full());
+
+    let mut buf = Vec::new();
+    assert_eq!(block_on(rx.recv_many(&mut buf, 8)), 3);
+    assert_eq!(buf, [1, 2, 3]);
+    tx2.try_send(4).unwrap();
+}
+
+#[test]
+fn send_batch_larger_than_capacity() {
+    let (mut tx, rx) = mpsc::channel::<usize>(2);
+
+    let t = thread::spawn(move || block_on(rx.collect::<Vec<_>>()));
+    block_on(tx.send_batch(0..100)).unwrap();
+    drop(tx);
+
+    assert_eq!(t.join().unwrap(), (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn dropped_send_batch_keeps_prior_reservation() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(2);
+    let cx = &mut noop_context();
+
+    tx.try_send(0).unwrap();
+    assert_eq!(tx.poll_ready(cx), Poll::Ready(Ok(())));
+
+    let mut batch = tx.send_batch(vec![1, 2]);
+    assert_eq!(batch.poll_unpin(cx), Poll::Pending);
+
+    // The slot reserved before the batch stays with the sender.
+    drop(batch);
+    assert_eq!(tx.capacity(), 0);
+    assert_eq!(rx.try_next().unwrap(), Some(0));
+    assert_eq!(tx.capacity(), 1);
+
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert!(tx.try_send(3).unwrap_err().is_full());
+}
+
+#[test]
+fn send_batch_disconnected() {
+    let (mut tx, rx) = mpsc::channel::<i32>(2);
+    drop(rx);
+    assert!(block_on(tx.send_batch(vec![1, 2])).unwrap_err().is_disconnected());
+}
+
+#[test]
+fn bounded_introspection() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(3);
+    let tx2 = tx1.clone();
+    let weak = tx1.downgrade();
+    let cx = &mut noop_context();
+
+    assert_eq!(tx1.max_capacity(), 3);
+    assert_eq!(rx.max_capacity(), 3);
+    assert_eq!(rx.sender_count(), 2);
+    assert!(tx1.is_empty());
+
+    tx1.try_send(1).unwrap();
+    assert_eq!(tx2.len(), 1);
+    assert_eq!(rx.len(), 1);
+    assert_eq!(tx2.capacity(), 2);
+
+    // A reservation takes capacity without adding a message.
+    assert_eq!(tx1.poll_ready(cx), Poll::Ready(Ok(())));
+    assert_eq!(rx.capacity(), 1);
+    assert_eq!(rx.len(), 1);
+    tx1.try_send(2).unwrap();
+    tx1.try_send(3).unwrap();
+    assert!(tx2.is_full());
+    assert_eq!(rx.len(), 3);
+
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert!(!tx2.is_full());
+    assert_eq!(rx.capacity(), 1);
+
+    drop(tx2);
+    assert_eq!(rx.sender_count(), 1);
+    drop(weak);
+    assert_eq!(tx1.sender_count(), 1);
+
+    tx1.disconnect();
+    assert_eq!(tx1.len(), 0);
+    assert_eq!(tx1.max_capacity(), 0);
+    assert!(tx1.is_full());
+    assert_eq!(rx.sender_count(), 0);
+    assert_eq!(rx.len(), 2);
+}
+
+#[test]
+fn drop_policy_max_capacity() {
+    let (tx, _rx) = mpsc::channel_with_policy::<i32>(2, mpsc::OverflowPolicy::DropOldest);
+    assert_eq!(tx.max_capacity(), 2);
+    assert_eq!(tx.capacity(), 2);
+}
+
+#[test]
+fn rendezvous_max_capacity() {
+    let (mut tx, _rx) = mpsc::channel::<i32>(0);
+    assert_eq!(tx.max_capacity(), 0);
+    assert_eq!(tx.capacity(), 1);
+
+    tx.try_send(1).unwrap();
+    assert!(tx.is_full());
+}
+
+#[test]
+fn unbounded_introspection() {
+    let (tx1, mut rx) = mpsc::unbounded::<i32>();
+    let tx2 = tx1.clone();
+
+    assert!(rx.is_empty());
+    assert_eq!(rx.sender_count(), 2);
+    let max = rx.max_capacity();
+    assert_eq!(tx1.capacity(), max);
+
+    tx1.unbounded_send(1).unwrap();
+    tx2.unbounded_send(2).unwrap();
+    assert_eq!(tx1.len(), 2);
+    assert_eq!(rx.capacity(), max - 2);
+
+    drop(tx2);
+    assert_eq!(tx1.sender_count(), 1);
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(rx.len(), 1);
+}
+
+#[test]
+fn poll_closed_when_senders_gone() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(2);
+    let tx2 = tx1.clone();
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    assert_eq!(rx.poll_closed(cx), Poll::Pending);
+    tx1.try_send(1).unwrap();
+    drop(tx1);
+    assert_eq!(rx.poll_closed(cx), Poll::Pending);
+
+    let count_before = count.get();
+    drop(tx2);
+    assert_eq!(count.get(), count_before + 1);
+    assert_eq!(rx.poll_closed(cx), Poll::Ready(()));
+
+    // Buffered messages are still received.
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(rx.try_next().unwrap(), None);
+    assert_eq!(rx.poll_closed(cx), Poll::Ready(()));
+}
+
+#[test]
+fn poll_closed_ignores_weak_senders() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+    let weak = tx.downgrade();
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    assert_eq!(rx.poll_closed(cx), Poll::Pending);
+    drop(tx);
+    assert_eq!(count, 1);
+    assert_eq!(rx.poll_closed(cx), Poll::Ready(()));
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn poll_closed_across_threads() {
+    let (tx, mut rx) = mpsc::channel::<i32>(1);
+
+    let t = thread::spawn(move || drop(tx));
+    block_on(poll_fn(|cx| rx.poll_closed(cx)));
+    t.join().unwrap();
+    assert_eq!(rx.sender_count(), 0);
+}
+
+#[test]
+fn poll_closed_has_its_own_waker() {
+    let (tx, mut rx) = mpsc::channel::<i32>(1);
+    let (closed_waker, closed_count) = new_count_waker();
+    let (next_waker, next_count) = new_count_waker();
+
+    assert_eq!(rx.poll_closed(&mut Context::from_waker(&closed_waker)), Poll::Pending);
+    assert_eq!(rx.poll_next_unpin(&mut Context::from_waker(&next_waker)), Poll::Pending);
+
+    drop(tx);
+    assert_eq!(closed_count, 1);
+    assert_eq!(next_count, 1);
+}
+
+#[test]
+fn closed_future() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    let mut closed = rx.closed();
+    assert_eq!(closed.poll_unpin(cx), Poll::Pending);
+    tx.unbounded_send(1).unwrap();
+    assert_eq!(count, 0);
+
+    drop(tx);
+    assert_eq!(count, 1);
+    assert_eq!(closed.poll_unpin(cx), Poll::Ready(()));
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+}
+
+#[test]
+fn close_with_from_sender() {
+    let (mut tx1, mut rx) = mpsc::channel::<i32>(2);
+    let mut tx2 = tx1.clone();
+
+    tx1.try_send(1).unwrap();
+    tx1.close_with("peer crashed");
+
+    let err = tx2.try_send(2).unwrap_err();
+    assert!(err.is_disconnected());
+    assert_eq!(err.reason().unwrap().to_string(), "peer crashed");
+    assert_eq!(err.to_string(), "send failed because channel was closed: peer crashed");
+
+    assert_eq!(block_on(rx.next()), Some(1));
+    assert_eq!(block_on(rx.next()), None);
+    assert!(rx.is_terminated());
+    assert_eq!(rx.close_reason().unwrap().to_string(), "peer crashed");
+}
+
+#[test]
+fn sender_close_with_wakes_parked_senders() {
+    let (mut tx1, _rx) = mpsc::channel::<i32>(0);
+    let mut tx2 = tx1.clone();
+    let mut tx3 = tx1.clone();
+    let (waker1, count1) = new_count_waker();
+    let (waker2, count2) = new_count_waker();
+    let cx1 = &mut Context::from_waker(&waker1);
+    let cx2 = &mut Context::from_waker(&waker2);
+
+    // One sender waits for its message to be received, the other for a slot.
+    let mut send1 = tx1.send(1);
+    assert_eq!(send1.poll_unpin(cx1), Poll::Pending);
+    let mut send2 = tx2.send(2);
+    assert_eq!(send2.poll_unpin(cx2), Poll::Pending);
+
+    tx3.close_with("shutting down");
+    assert_eq!(count1, 1);
+    assert_eq!(count2, 1);
+
+    // A message sent before the channel was closed counts as sent.
+    assert_eq!(send1.poll_unpin(cx1), Poll::Ready(Ok(())));
+    let err = match send2.poll_unpin(cx2) {
+        Poll::Ready(Err(err)) => err,
+        other => panic!("unexpected poll result: {:?}", other),
+    };
+    assert_eq!(err.reason().unwrap().to_string(), "shutting down");
+}
+
+#[test]
+fn close_with_fails_pending_send() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(0);
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    tx.try_send(1).unwrap();
+    let mut tx2 = tx.clone();
+    {
+        let mut send = tx2.send(2);
+        assert_eq!(send.poll_unpin(cx), Poll::Pending);
+
+        let reason = std::io::Error::new(std::io::ErrorKind::Other, "shutting down");
+        rx.close_with(reason);
+        assert_eq!(count, 1);
+
+        let err = match send.poll_unpin(cx) {
+            Poll::Ready(Err(err)) => err,
+            other => panic!("unexpected poll result: {:?}", other),
+        };
+        assert!(err.is_disconnected());
+        let reason = err.reason().unwrap().downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(reason.to_string(), "shutting down");
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    // Buffered messages can still be received.
+    assert_eq!(rx.try_next().unwrap(), Some(1));
+    assert_eq!(rx.try_next().unwrap(), None);
+    assert_eq!(rx.close_reason().unwrap().to_string(), "shutting down");
+}
+
+#[test]
+fn close_without_reason() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+    drop(tx.clone());
+    tx.close_channel();
+
+    // The channel is closed already, so the reason is dropped.
+    rx.close_with("too late");
+    let err = tx.try_send(1).unwrap_err();
+    assert!(err.reason().is_none());
+    assert!(std::error::Error::source(&err).is_none());
+
+    assert_eq!(block_on(rx.next()), None);
+    assert!(rx.close_reason().is_none());
+}
+
+#[test]
+fn close_with_first_reason_wins() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+
+    tx.close_with("first");
+    rx.close_with("second");
+    let err = tx.unbounded_send(1).unwrap_err();
+    assert_eq!(err.reason().unwrap().to_string(), "first");
+
+    assert_eq!(block_on(rx.next()), None);
+    assert_eq!(rx.close_reason().unwrap().to_string(), "first");
+}
+
+#[test]
+fn close_with_from_unbounded_receiver() {
+    let (tx, mut rx) = mpsc::unbounded::<i32>();
+
+    tx.unbounded_send(1).unwrap();
+    rx.close_with(String::from("done"));
+    let err = tx.unbounded_send(2).unwrap_err();
+    assert!(err.is_disconnected());
+    assert_eq!(err.reason().unwrap().to_string(), "done");
+    assert_eq!(block_on(tx.clone().send(3)).unwrap_err().reason().unwrap().to_string(), "done");
+
+    assert_eq!(block_on(rx.next()), Some(1));
+    assert_eq!(block_on(rx.next()), None);
+    assert_eq!(rx.close_reason().unwrap().to_string(), "done");
+}
+
+#[test]
+fn unread_messages_are_dropped() {
+    let msg = Arc::new(());
+    let (tx, rx) = mpsc::unbounded();
+
+    // Enough messages to span several blocks of the queue.
+    for _ in 0..100 {
+        tx.unbounded_send(msg.clone()).unwrap();
+    }
+    drop(rx);
+    drop(tx);
+    assert_eq!(Arc::strong_count(&msg), 1);
+}
+
+#[test]
+fn stress_order_per_sender() {
+    const AMT: usize = 10000;
+    const NTHREADS: usize = 4;
+
+    let (tx, rx) = mpsc::unbounded::<(usize, usize)>();
+
+    let threads: Vec<_> = (0..NTHREADS)
+        .map(|n| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..AMT {
+                    tx.unbounded_send((n, i)).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut next = vec![0; NTHREADS];
+    for (n, i) in block_on_stream(rx) {
+        assert_eq!(next[n], i);
+        next[n] += 1;
+    }
+    assert_eq!(next, vec![AMT; NTHREADS]);
+
+    for t in threads {
+        t.join().unwrap();
+    }
+}
@@ -250,3 +250,25 @@ fn cancel_sends() {
 //         },
 //     }
 // }
+
+#[test]
+fn recv_blocking() {
+    let (tx, rx) = oneshot::channel::<i32>();
+    let t = thread::spawn(move || tx.send(1).unwrap());
+    assert_eq!(rx.recv_blocking(), Ok(1));
+    t.join().unwrap();
+
+    let (tx, rx) = oneshot::channel::<i32>();
+    let t = thread::spawn(move || drop(tx));
+    assert_eq!(rx.recv_blocking(), Err(oneshot::Canceled));
+    t.join().unwrap();
+}
+
+#[test]
+fn recv_blocking_in_executor_context() {
+    let (tx, rx) = oneshot::channel::<i32>();
+    tx.send(1).unwrap();
+
+    // Unlike a nested `block_on`, this does not panic.
+    assert_eq!(block_on(async { rx.recv_blocking() }), Ok(1));
+}
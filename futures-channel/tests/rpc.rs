@@ -0,0 +1,134 @@
+use futures::channel::rpc::{self, Canceled};
+use futures::executor::block_on;
+use futures::future::{self, FusedFuture, FutureExt};
+use futures::stream::{FusedStream, StreamExt};
+use futures::task::{Context, Poll};
+use futures_test::task::{new_count_waker, noop_context};
+use std::thread;
+
+#[test]
+fn call_and_respond() {
+    let (mut client, mut server) = rpc::channel::<i32, String>(1);
+
+    let mut call = client.call(1);
+    assert_eq!(call.poll_unpin(&mut noop_context()), Poll::Pending);
+
+    let (req, responder) = server.try_next().unwrap().unwrap();
+    assert_eq!(req, 1);
+    responder.send(req.to_string()).unwrap();
+
+    assert_eq!(call.poll_unpin(&mut noop_context()), Poll::Ready(Ok("1".to_string())));
+    assert!(call.is_terminated());
+}
+
+#[test]
+fn dropped_responder_cancels_call() {
+    let (mut client, mut server) = rpc::channel::<i32, i32>(1);
+    let (waker, count) = new_count_waker();
+
+    let mut call = client.call(1);
+    assert_eq!(call.poll_unpin(&mut Context::from_waker(&waker)), Poll::Pending);
+
+    let (_, responder) = server.try_next().unwrap().unwrap();
+    drop(responder);
+    assert_eq!(count, 1);
+    assert_eq!(call.poll_unpin(&mut noop_context()), Poll::Ready(Err(Canceled)));
+}
+
+#[test]
+fn dropped_server_cancels_calls() {
+    let (mut client, server) = rpc::channel::<i32, i32>(1);
+
+    let mut call = client.call(1);
+    assert_eq!(call.poll_unpin(&mut noop_context()), Poll::Pending);
+    drop(server);
+    assert_eq!(call.poll_unpin(&mut noop_context()), Poll::Ready(Err(Canceled)));
+    drop(call);
+
+    assert!(client.is_closed());
+    assert_eq!(block_on(client.call(2)), Err(Canceled));
+}
+
+#[test]
+fn abandoned_call_is_visible_to_responder() {
+    let (mut client, mut server) = rpc::channel::<i32, i32>(1);
+    let (waker, count) = new_count_waker();
+
+    let mut call = client.call(1);
+    assert_eq!(call.poll_unpin(&mut noop_context()), Poll::Pending);
+    let (_, mut responder) = server.try_next().unwrap().unwrap();
+
+    assert_eq!(responder.poll_canceled(&mut Context::from_waker(&waker)), Poll::Pending);
+    drop(call);
+    assert_eq!(count, 1);
+    assert!(responder.is_canceled());
+    assert_eq!(responder.send(2), Err(2));
+}
+
+#[test]
+fn call_waits_for_capacity() {
+    let (mut client, mut server) = rpc::channel::<i32, i32>(0);
+    let mut client2 = client.clone();
+
+    let mut call1 = client.call(1);
+    let mut call2 = client2.call(2);
+    assert_eq!(call1.poll_unpin(&mut noop_context()), Poll::Pending);
+    assert_eq!(call2.poll_unpin(&mut noop_context()), Poll::Pending);
+
+    // The second request only gets into the channel once the first is read.
+    let (req, responder1) = server.try_next().unwrap().unwrap();
+    assert_eq!(req, 1);
+    assert!(server.try_next().is_err());
+    assert_eq!(call2.poll_unpin(&mut noop_context()), Poll::Pending);
+    let (req, responder2) = server.try_next().unwrap().unwrap();
+    assert_eq!(req, 2);
+
+    responder2.send(20).unwrap();
+    responder1.send(10).unwrap();
+    assert_eq!(call2.poll_unpin(&mut noop_context()), Poll::Ready(Ok(20)));
+    assert_eq!(call1.poll_unpin(&mut noop_context()), Poll::Ready(Ok(10)));
+}
+
+#[test]
+fn server_terminates_when_clients_gone() {
+    let (client, mut server) = rpc::channel::<i32, i32>(1);
+    let client2 = client.clone();
+    assert!(client.same_server(&client2));
+
+    drop(client);
+    drop(client2);
+    assert!(block_on(server.next()).is_none());
+    assert!(server.is_terminated());
+}
+
+#[test]
+fn many_clients_across_threads() {
+    const CLIENTS: i32 = 4;
+    const CALLS: i32 = 100;
+    let (client, server) = rpc::channel::<i32, i32>(4);
+
+    let server = thread::spawn(move || {
+        block_on(server.for_each(|(req, responder)| {
+            responder.send(req + 1).unwrap();
+            future::ready(())
+        }))
+    });
+
+    let clients: Vec<_> = (0..CLIENTS)
+        .map(|c| {
+            let mut client = client.clone();
+            thread::spawn(move || {
+                for i in 0..CALLS {
+                    let req = c * CALLS + i;
+                    assert_eq!(block_on(client.call(req)), Ok(req + 1));
+                }
+            })
+        })
+        .collect();
+    drop(client);
+
+    for t in clients {
+        t.join().unwrap();
+    }
+    server.join().unwrap();
+}
@@ -0,0 +1,179 @@
+use futures::channel::watch;
+use futures::executor::{block_on, block_on_stream};
+use futures::future::FutureExt;
+use futures::stream::{FusedStream, StreamExt};
+use futures::task::{Context, Poll};
+use futures_test::task::{new_count_waker, noop_context};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn send_recv() {
+    let (tx, mut rx) = watch::channel(0);
+    assert_eq!(*rx.borrow(), 0);
+    assert!(!rx.has_changed());
+
+    tx.send(1).unwrap();
+    assert!(rx.has_changed());
+    assert_eq!(*rx.borrow(), 1);
+    block_on(rx.changed()).unwrap();
+    assert!(!rx.has_changed());
+
+    drop(tx);
+    assert!(block_on(rx.changed()).is_err());
+    assert_eq!(*rx.borrow(), 1);
+}
+
+#[test]
+fn only_latest_value_is_observed() {
+    let (tx, rx) = watch::channel(0);
+
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    tx.send(3).unwrap();
+    drop(tx);
+
+    let v: Vec<_> = block_on(rx.collect());
+    assert_eq!(v, vec![3]);
+}
+
+#[test]
+fn send_modify() {
+    let (tx, mut rx) = watch::channel(vec![1]);
+
+    tx.send_modify(|v| v.push(2));
+    assert_eq!(*tx.borrow(), vec![1, 2]);
+    assert!(rx.has_changed());
+    assert_eq!(*rx.borrow_and_update(), vec![1, 2]);
+    assert!(!rx.has_changed());
+
+    drop(rx);
+    assert!(tx.is_closed());
+    tx.send_modify(|v| v.push(3));
+    assert_eq!(*tx.borrow(), vec![1, 2, 3]);
+}
+
+#[test]
+fn send_fails_without_receivers() {
+    let (tx, rx) = watch::channel(0);
+    let rx2 = rx.clone();
+    assert_eq!(tx.receiver_count(), 2);
+    assert!(tx.is_connected_to(&rx2));
+
+    drop(rx);
+    tx.send(1).unwrap();
+    drop(rx2);
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(2).unwrap_err().into_inner(), 2);
+    assert_eq!(*tx.borrow(), 1);
+
+    let rx = tx.subscribe();
+    assert!(!rx.has_changed());
+    tx.send(3).unwrap();
+    assert!(rx.has_changed());
+}
+
+#[test]
+fn clone_keeps_seen_version() {
+    let (tx, mut rx1) = watch::channel(0);
+
+    tx.send(1).unwrap();
+    let mut rx2 = rx1.clone();
+    assert!(rx2.has_changed());
+    block_on(rx1.changed()).unwrap();
+    let rx3 = rx1.clone();
+    assert!(!rx3.has_changed());
+    block_on(rx2.changed()).unwrap();
+    assert!(rx3.same_channel(&rx2));
+}
+
+#[test]
+fn changed_wakes_every_receiver() {
+    let (tx, mut rx1) = watch::channel(0);
+    let mut rx2 = rx1.clone();
+    let (waker, count) = new_count_waker();
+    let cx = &mut Context::from_waker(&waker);
+
+    assert_eq!(rx1.changed().poll_unpin(cx), Poll::Pending);
+    assert_eq!(rx2.poll_next_unpin(cx), Poll::Pending);
+    assert_eq!(count, 0);
+
+    tx.send(1).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(rx1.changed().poll_unpin(cx), Poll::Ready(Ok(())));
+    assert_eq!(rx2.poll_next_unpin(cx), Poll::Ready(Some(1)));
+
+    assert_eq!(rx1.poll_next_unpin(cx), Poll::Pending);
+    drop(tx);
+    assert_eq!(count, 3);
+    assert_eq!(rx1.poll_next_unpin(cx), Poll::Ready(None));
+    assert!(rx1.is_terminated());
+    assert_eq!(rx2.poll_next_unpin(cx), Poll::Ready(None));
+}
+
+#[test]
+fn unchanged_receiver_stays_pending() {
+    let (tx, mut rx) = watch::channel(0);
+    let cx = &mut noop_context();
+
+    tx.send(1).unwrap();
+    assert_eq!(rx.poll_changed(cx), Poll::Ready(Ok(())));
+    assert_eq!(rx.poll_changed(cx), Poll::Pending);
+    assert_eq!(rx.poll_changed(cx), Poll::Pending);
+}
+
+#[test]
+fn stream_yields_each_value_once() {
+    let (tx, mut rx) = watch::channel(0);
+    let cx = &mut noop_context();
+
+    tx.send(1).unwrap();
+    assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(1)));
+    assert_eq!(rx.poll_next_unpin(cx), Poll::Pending);
+}
+
+#[test]
+fn stream_yields_value_sent_while_polling_once() {
+    let (tx, mut rx) = watch::channel(0);
+    let cx = &mut noop_context();
+    tx.send(1).unwrap();
+
+    // Hold the lock on the value while the stream is polled, so that the
+    // receiver sees the version of the first value but gets the second one.
+    let (modifying_tx, modifying_rx) = std::sync::mpsc::channel();
+    let t = thread::spawn(move || {
+        tx.send_modify(|value| {
+            *value = 2;
+            modifying_tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+        });
+        tx
+    });
+    modifying_rx.recv().unwrap();
+
+    assert_eq!(rx.poll_next_unpin(cx), Poll::Ready(Some(2)));
+    assert_eq!(rx.poll_next_unpin(cx), Poll::Pending);
+    drop(t.join().unwrap());
+}
+
+#[test]
+fn stress_latest_value() {
+    const AMT: usize = 10000;
+
+    let (tx, rx) = watch::channel(0);
+
+    let t = thread::spawn(move || {
+        for i in 1..=AMT {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let mut last = 0;
+    for i in block_on_stream(rx) {
+        assert!(i > last);
+        last = i;
+    }
+    assert_eq!(last, AMT);
+
+    t.join().unwrap();
+}
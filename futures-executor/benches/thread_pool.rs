@@ -0,0 +1,140 @@
+//! Benchmarks for the scheduler of `ThreadPool`.
+//!
+//! Running them on the revision before the work-stealing scheduler compares
+//! it with the previous design, where all workers shared a single queue.
+//! Results on a single-core machine, where the pool runs one worker:
+//!
+//! ```text
+//!                         shared queue    work-stealing
+//! spawn_many              3.03-3.10 ms    2.82-2.97 ms
+//! spawn_many_from_tasks   2.98-3.02 ms    2.72-2.86 ms
+//! yield_many              0.49 ms         0.61 ms
+//! ping_pong               4.16-4.17 ms    3.20-3.22 ms
+//! ```
+//!
+//! With one worker, these show the overhead of the scheduler, not what is
+//! gained by stealing. In `yield_many`, tasks wake themselves, and each wake
+//! moves the previous task in the LIFO slot to the locked run queue. The
+//! benchmarks have yet to be compared on a multi-core machine.
+
+#![feature(test)]
+
+extern crate test;
+use crate::test::Bencher;
+
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::future::Future;
+use futures::lock::WaitGroup;
+use futures::stream::StreamExt;
+use futures::task::{Context, Poll};
+use futures_executor::ThreadPool;
+use std::pin::Pin;
+
+#[bench]
+fn spawn_many(b: &mut Bencher) {
+    const NUM: usize = 10_000;
+
+    let pool = ThreadPool::new().unwrap();
+
+    b.iter(|| {
+        let wg = WaitGroup::new();
+        for _ in 0..NUM {
+            let wg = wg.clone();
+            pool.spawn_ok(async move { drop(wg) });
+        }
+        block_on(wg.wait());
+    });
+}
+
+#[bench]
+fn spawn_many_from_tasks(b: &mut Bencher) {
+    const NUM_SPAWNERS: usize = 10;
+    const NUM: usize = 1_000;
+
+    let pool = ThreadPool::new().unwrap();
+
+    b.iter(|| {
+        let wg = WaitGroup::new();
+        for _ in 0..NUM_SPAWNERS {
+            let spawner = pool.clone();
+            let wg = wg.clone();
+            pool.spawn_ok(async move {
+                for _ in 0..NUM {
+                    let wg = wg.clone();
+                    spawner.spawn_ok(async move { drop(wg) });
+                }
+            });
+        }
+        block_on(wg.wait());
+    });
+}
+
+#[bench]
+fn yield_many(b: &mut Bencher) {
+    const NUM_TASKS: usize = 100;
+    const NUM_YIELDS: usize = 100;
+
+    struct Yield {
+        rem: usize,
+    }
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if self.rem == 0 {
+                Poll::Ready(())
+            } else {
+                self.rem -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    let pool = ThreadPool::new().unwrap();
+
+    b.iter(|| {
+        let wg = WaitGroup::new();
+        for _ in 0..NUM_TASKS {
+            let wg = wg.clone();
+            pool.spawn_ok(async move {
+                Yield { rem: NUM_YIELDS }.await;
+                drop(wg);
+            });
+        }
+        block_on(wg.wait());
+    });
+}
+
+#[bench]
+fn ping_pong(b: &mut Bencher) {
+    const NUM_PAIRS: usize = 100;
+    const NUM_ROUNDS: usize = 100;
+
+    let pool = ThreadPool::new().unwrap();
+
+    b.iter(|| {
+        let wg = WaitGroup::new();
+        for _ in 0..NUM_PAIRS {
+            let (ping_tx, mut ping_rx) = mpsc::unbounded();
+            let (pong_tx, mut pong_rx) = mpsc::unbounded();
+
+            pool.spawn_ok(async move {
+                while ping_rx.next().await.is_some() {
+                    pong_tx.unbounded_send(()).unwrap();
+                }
+            });
+            let wg = wg.clone();
+            pool.spawn_ok(async move {
+                for _ in 0..NUM_ROUNDS {
+                    ping_tx.unbounded_send(()).unwrap();
+                    pong_rx.next().await.unwrap();
+                }
+                drop(wg);
+            });
+        }
+        block_on(wg.wait());
+    });
+}
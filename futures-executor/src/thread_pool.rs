@@ -5,12 +5,14 @@ use futures_core::task::{Context, Poll};
 use futures_task::{waker_ref, ArcWake};
 use futures_task::{FutureObj, Spawn, SpawnError};
 use futures_util::future::FutureExt;
+use std::cell::Cell;
 use std::cmp;
+use std::collections::VecDeque;
 use std::fmt;
 use std::io;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::ptr;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 
 /// A general-purpose thread pool for scheduling tasks that poll futures to
@@ -22,6 +24,19 @@ use std::thread;
 /// This type is a clonable handle to the threadpool itself.
 /// Cloning it will only create a new reference, not a new threadpool.
 ///
+/// # Scheduling
+///
+/// Each worker thread has its own queue of tasks to run. Tasks spawned or
+/// woken from a worker thread go to the queue of that worker, and other
+/// threads push their tasks to a queue shared by all the workers. A worker
+/// which runs out of tasks steals half of the tasks of another worker, picked
+/// at random.
+///
+/// A task woken by the task a worker is running is put in a slot of that
+/// worker, and runs as soon as the current task yields, while the data they
+/// share is still in the CPU caches. To keep other tasks from being starved,
+/// only a few tasks in a row are run from that slot.
+///
 /// This type is only available when the `thread-pool` feature of this
 /// library is activated.
 #[cfg_attr(docsrs, doc(cfg(feature = "thread-pool")))]
@@ -46,12 +61,29 @@ trait AssertSendSync: Send + Sync {}
 impl AssertSendSync for ThreadPool {}
 
 struct PoolState {
-    tx: Mutex<mpsc::Sender<Message>>,
-    rx: Mutex<mpsc::Receiver<Message>>,
+    // Tasks scheduled from outside the worker threads.
+    injector: Mutex<VecDeque<Task>>,
+    // The run queue of each worker.
+    queues: Box<[Mutex<VecDeque<Task>>]>,
+    // Number of tasks in the injector and run queues, so that a worker
+    // about to park need not lock every queue to find out whether there are
+    // tasks left.
+    num_queued: AtomicUsize,
+    sleep: Mutex<Sleep>,
+    sleep_condvar: Condvar,
+    // Number of workers parked, or about to park, in `PoolState::park`.
+    // Only modified with `sleep` locked.
+    num_idle: AtomicUsize,
     cnt: AtomicUsize,
     size: usize,
 }
 
+struct Sleep {
+    // Number of parked workers which were notified and did not wake up yet.
+    wakeups: usize,
+    is_shutdown: bool,
+}
+
 impl fmt::Debug for ThreadPool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ThreadPool").field("size", &self.state.size).finish()
@@ -67,11 +99,6 @@ impl fmt::Debug for ThreadPoolBuilder {
     }
 }
 
-enum Message {
-    Run(Task),
-    Close,
-}
-
 impl ThreadPool {
     /// Creates a new thread pool with the default configuration.
     ///
@@ -101,7 +128,7 @@ impl ThreadPool {
             wake_handle: Arc::new(WakeHandle { exec: self.clone(), mutex: UnparkMutex::new() }),
             exec: self.clone(),
         };
-        self.state.send(Message::Run(task));
+        self.state.schedule(task, false);
     }
 
     /// Spawns a task that polls the given future with output `()` to
@@ -133,9 +160,193 @@ impl Spawn for ThreadPool {
     }
 }
 
+// Number of tasks a worker runs in a row from its LIFO slot before
+// looking at its run queue.
+const MAX_LIFO_POLLS: usize = 3;
+
+// A worker looks at the injector queue first once every that many ticks, so
+// that tasks queued there are not starved by tasks spawned by workers.
+const INJECTOR_INTERVAL: u32 = 61;
+
+// Maximum number of tasks a worker moves at once from the injector queue to
+// its own run queue.
+const MAX_INJECTOR_BATCH: usize = 32;
+
+// Number of times a worker which ran out of tasks yields its thread and looks
+// for tasks again before parking, as parking and unparking a thread is much
+// more expensive when tasks keep coming.
+const SEARCH_ROUNDS: usize = 4;
+
+thread_local! {
+    // The worker running on this thread, if any.
+    static CURRENT_WORKER: Cell<*const Worker> = Cell::new(ptr::null());
+}
+
+struct Worker {
+    state: *const PoolState,
+    idx: usize,
+    // The task last woken by the task this worker is running.
+    lifo_slot: Cell<Option<Task>>,
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // The worker only stops before the pool is shut down if a task
+        // panics. Hand its tasks over to the remaining workers.
+        // Safety: the pool state outlives the worker, see `PoolState::work`.
+        let state = unsafe { &*self.state };
+        let mut tasks: Vec<_> = self.lifo_slot.take().into_iter().collect();
+        // Unlike the tasks of the run queue, the one of the LIFO slot is not
+        // counted in `num_queued` yet.
+        let num_new = tasks.len();
+        tasks.extend(state.queues[self.idx].lock().unwrap().drain(..));
+        if !tasks.is_empty() {
+            state.injector.lock().unwrap().extend(tasks);
+            state.num_queued.fetch_add(num_new, Ordering::SeqCst);
+            state.notify_one();
+        }
+    }
+}
+
+// Unregisters the worker of this thread when dropped, even if a task panics.
+struct CurrentWorkerGuard;
+
+impl Drop for CurrentWorkerGuard {
+    fn drop(&mut self) {
+        CURRENT_WORKER.with(|current| current.set(ptr::null()));
+    }
+}
+
 impl PoolState {
-    fn send(&self, msg: Message) {
-        self.tx.lock().unwrap().send(msg).unwrap();
+    // Schedules a task to run. `is_wake` is true if the task was woken,
+    // rather than spawned.
+    fn schedule(&self, task: Task, is_wake: bool) {
+        let worker = CURRENT_WORKER.try_with(Cell::get).unwrap_or(ptr::null());
+        // Safety: `CURRENT_WORKER` is only set while the worker it points to
+        // is running on this thread.
+        match unsafe { worker.as_ref() } {
+            Some(worker) if ptr::eq(worker.state, self) => {
+                let task = if is_wake {
+                    match worker.lifo_slot.replace(Some(task)) {
+                        Some(task) => task,
+                        None => return,
+                    }
+                } else {
+                    task
+                };
+                self.queues[worker.idx].lock().unwrap().push_back(task);
+            }
+            _ => self.injector.lock().unwrap().push_back(task),
+        }
+        self.num_queued.fetch_add(1, Ordering::SeqCst);
+        self.notify_one();
+    }
+
+    // Wakes up a parked worker, if any, to run a newly queued task.
+    fn notify_one(&self) {
+        // Pairs with the fence in `park`: either the worker sees the new task
+        // before parking, or we see it is idle.
+        atomic::fence(Ordering::SeqCst);
+        if self.num_idle.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let mut sleep = self.sleep.lock().unwrap();
+        if sleep.wakeups < self.num_idle.load(Ordering::SeqCst) {
+            sleep.wakeups += 1;
+            self.sleep_condvar.notify_one();
+        }
+    }
+
+    // Parks the current worker until there may be tasks to run. Returns
+    // `false` if the pool is shut down.
+    fn park(&self) -> bool {
+        let mut sleep = self.sleep.lock().unwrap();
+        self.num_idle.fetch_add(1, Ordering::SeqCst);
+        atomic::fence(Ordering::SeqCst);
+        if !self.has_tasks() {
+            while !sleep.is_shutdown {
+                sleep = self.sleep_condvar.wait(sleep).unwrap();
+                if sleep.wakeups > 0 {
+                    sleep.wakeups -= 1;
+                    break;
+                }
+            }
+        }
+        self.num_idle.fetch_sub(1, Ordering::SeqCst);
+        !sleep.is_shutdown
+    }
+
+    fn has_tasks(&self) -> bool {
+        self.num_queued.load(Ordering::SeqCst) != 0
+    }
+
+    fn shutdown(&self) {
+        self.sleep.lock().unwrap().is_shutdown = true;
+        self.sleep_condvar.notify_all();
+    }
+
+    // Finds the next task for worker `idx` to run, other than the one in its
+    // LIFO slot.
+    fn next_task(&self, idx: usize, tick: u32, rng: &mut FastRand) -> Option<Task> {
+        let task = if tick % INJECTOR_INTERVAL == 0 { self.pop_injector(idx) } else { None };
+        let task = task
+            .or_else(|| self.queues[idx].lock().unwrap().pop_front())
+            .or_else(|| self.pop_injector(idx))
+            .or_else(|| self.steal(idx, rng))?;
+        self.num_queued.fetch_sub(1, Ordering::SeqCst);
+        Some(task)
+    }
+
+    // Takes a task from the injector queue, moving a fair share of the
+    // remaining ones to the run queue of worker `idx`.
+    fn pop_injector(&self, idx: usize) -> Option<Task> {
+        let (task, batch) = {
+            let mut injector = self.injector.lock().unwrap();
+            let task = injector.pop_front()?;
+            let n = cmp::min(injector.len() / self.size, MAX_INJECTOR_BATCH);
+            (task, injector.drain(..n).collect::<Vec<_>>())
+        };
+        if !batch.is_empty() {
+            self.queues[idx].lock().unwrap().extend(batch);
+        }
+        Some(task)
+    }
+
+    // Steals half of the tasks of another worker, picked at random, for
+    // worker `idx`.
+    fn steal(&self, idx: usize, rng: &mut FastRand) -> Option<Task> {
+        let start = rng.next() as usize % self.size;
+        for i in 0..self.size {
+            let victim = (start + i) % self.size;
+            if victim == idx {
+                continue;
+            }
+            let mut stolen = {
+                let mut queue = self.queues[victim].lock().unwrap();
+                let n = (queue.len() + 1) / 2;
+                queue.drain(..n).collect::<VecDeque<_>>()
+            };
+            if let Some(task) = stolen.pop_front() {
+                if !stolen.is_empty() {
+                    self.queues[idx].lock().unwrap().extend(stolen);
+                    // Let other idle workers steal from us in turn.
+                    self.notify_one();
+                }
+                return Some(task);
+            }
+        }
+        None
+    }
+
+    // Keeps looking for a task for a little while before worker `idx` parks.
+    fn search(&self, idx: usize, tick: u32, rng: &mut FastRand) -> Option<Task> {
+        for _ in 0..SEARCH_ROUNDS {
+            thread::yield_now();
+            if let Some(task) = self.next_task(idx, tick, rng) {
+                return Some(task);
+            }
+        }
+        None
     }
 
     fn work(
@@ -148,11 +359,37 @@ impl PoolState {
         if let Some(after_start) = after_start {
             after_start(idx);
         }
-        loop {
-            let msg = self.rx.lock().unwrap().recv().unwrap();
-            match msg {
-                Message::Run(task) => task.run(),
-                Message::Close => break,
+        {
+            let worker = Worker { state: self, idx, lifo_slot: Cell::new(None) };
+            CURRENT_WORKER.with(|current| current.set(&worker));
+            let _guard = CurrentWorkerGuard;
+
+            let mut rng = FastRand::new(idx);
+            let mut tick = 0u32;
+            let mut lifo_polls = 0;
+            loop {
+                tick = tick.wrapping_add(1);
+                let task = if lifo_polls < MAX_LIFO_POLLS { worker.lifo_slot.take() } else { None };
+                let task = match task {
+                    Some(task) => {
+                        lifo_polls += 1;
+                        Some(task)
+                    }
+                    None => {
+                        lifo_polls = 0;
+                        self.next_task(idx, tick, &mut rng)
+                            .or_else(|| worker.lifo_slot.take())
+                            .or_else(|| self.search(idx, tick, &mut rng))
+                    }
+                };
+                match task {
+                    Some(task) => task.run(),
+                    None => {
+                        if !self.park() {
+                            break;
+                        }
+                    }
+                }
             }
         }
         if let Some(before_stop) = before_stop {
@@ -161,6 +398,25 @@ impl PoolState {
     }
 }
 
+// A xorshift random number generator, to pick the workers to steal from.
+struct FastRand(u32);
+
+impl FastRand {
+    fn new(seed: usize) -> Self {
+        // The state must not be zero.
+        Self((seed as u32).wrapping_mul(0x9e37_79b9) | 1)
+    }
+
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
 impl Clone for ThreadPool {
     fn clone(&self) -> Self {
         self.state.cnt.fetch_add(1, Ordering::Relaxed);
@@ -170,10 +426,10 @@ impl Clone for ThreadPool {
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
+        // Tasks hold a handle to the pool, so none is left once the last
+        // handle is dropped.
         if self.state.cnt.fetch_sub(1, Ordering::Relaxed) == 1 {
-            for _ in 0..self.state.size {
-                self.state.send(Message::Close);
-            }
+            self.state.shutdown();
         }
     }
 }
@@ -260,11 +516,14 @@ impl ThreadPoolBuilder {
 
     /// Create a [`ThreadPool`](ThreadPool) with the given configuration.
     pub fn create(&mut self) -> Result<ThreadPool, io::Error> {
-        let (tx, rx) = mpsc::channel();
         let pool = ThreadPool {
             state: Arc::new(PoolState {
-                tx: Mutex::new(tx),
-                rx: Mutex::new(rx),
+                injector: Mutex::new(VecDeque::new()),
+                queues: (0..self.pool_size).map(|_| Mutex::new(VecDeque::new())).collect(),
+                num_queued: AtomicUsize::new(0),
+                sleep: Mutex::new(Sleep { wakeups: 0, is_shutdown: false }),
+                sleep_condvar: Condvar::new(),
+                num_idle: AtomicUsize::new(0),
                 cnt: AtomicUsize::new(1),
                 size: self.pool_size,
             }),
@@ -347,7 +606,7 @@ impl fmt::Debug for Task {
 impl ArcWake for WakeHandle {
     fn wake_by_ref(arc_self: &Arc<Self>) {
         match arc_self.mutex.notify() {
-            Ok(task) => arc_self.exec.state.send(Message::Run(task)),
+            Ok(task) => arc_self.exec.state.schedule(task, true),
             Err(()) => {}
         }
     }
@@ -372,4 +631,102 @@ mod tests {
         let count = rx.into_iter().count();
         assert_eq!(count, 2);
     }
+
+    #[test]
+    fn test_before_stop_after_drop() {
+        let (tx, rx) = mpsc::sync_channel(2);
+        let pool = ThreadPoolBuilder::new()
+            .pool_size(2)
+            .before_stop(move |_| tx.send(1).unwrap())
+            .create()
+            .unwrap();
+        pool.spawn_ok(async {});
+        drop(pool);
+
+        let count = rx.into_iter().count();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_spawn_from_tasks() {
+        let pool = ThreadPoolBuilder::new().pool_size(4).create().unwrap();
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..10 {
+            let spawner = pool.clone();
+            let tx = tx.clone();
+            pool.spawn_ok(async move {
+                for _ in 0..100 {
+                    let tx = tx.clone();
+                    spawner.spawn_ok(async move { tx.send(()).unwrap() });
+                }
+            });
+        }
+        drop(tx);
+        assert_eq!(rx.iter().count(), 1000);
+    }
+
+    #[test]
+    fn test_woken_tasks_do_not_starve_others() {
+        use futures::channel::mpsc as channel;
+        use futures::stream::StreamExt;
+        use std::sync::atomic::AtomicBool;
+
+        // With a single worker, the two tasks below keep waking each other
+        // through the LIFO slot until a task spawned from outside runs.
+        let pool = ThreadPoolBuilder::new().pool_size(1).create().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx_a, mut rx_a) = channel::unbounded();
+        let (tx_b, mut rx_b) = channel::unbounded();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        pool.spawn_ok(async move {
+            while rx_b.next().await.is_some() {
+                tx_a.unbounded_send(()).unwrap();
+            }
+        });
+        let stop2 = stop.clone();
+        pool.spawn_ok(async move {
+            while !stop2.load(Ordering::SeqCst) {
+                tx_b.unbounded_send(()).unwrap();
+                rx_a.next().await;
+            }
+            done_tx.send(()).unwrap();
+        });
+        pool.spawn_ok(async move { stop.store(true, Ordering::SeqCst) });
+
+        done_rx.recv().unwrap();
+    }
+
+    #[test]
+    fn test_task_woken_by_panicking_task_runs() {
+        use futures::channel::oneshot;
+        use futures::future;
+
+        let pool = ThreadPoolBuilder::new().pool_size(2).create().unwrap();
+        let (tx, rx) = oneshot::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let (pending_tx, pending_rx) = mpsc::channel();
+        pool.spawn_ok(async move {
+            let mut rx = rx;
+            let res = future::poll_fn(|cx| {
+                let poll = rx.poll_unpin(cx);
+                if poll.is_pending() {
+                    pending_tx.send(()).unwrap();
+                }
+                poll
+            })
+            .await;
+            done_tx.send(res).unwrap();
+        });
+        pending_rx.recv().unwrap();
+        // Sending puts the receiving task in the LIFO slot of the worker
+        // running the sending task, which then panics.
+        pool.spawn_ok(async move {
+            tx.send(()).unwrap();
+            panic!("task panicked after waking another");
+        });
+
+        assert_eq!(done_rx.recv().unwrap(), Ok(()));
+    }
 }
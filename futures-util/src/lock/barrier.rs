@@ -0,0 +1,178 @@
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+use slab::Slab;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Mutex as StdMutex;
+
+/// A futures-aware barrier, letting a group of tasks wait until they have
+/// all reached the same point.
+///
+/// This is the asynchronous counterpart of [`std::sync::Barrier`]: tasks
+/// wait on the barrier with [`wait`](Barrier::wait), and are all released
+/// once `n` of them are waiting. The barrier can then be reused for the next
+/// group of `n` tasks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::future::join_all;
+/// use futures::lock::Barrier;
+///
+/// let barrier = Barrier::new(3);
+///
+/// let results = join_all((0..3).map(|_| barrier.wait())).await;
+/// // Exactly one of the tasks is the leader.
+/// assert_eq!(results.iter().filter(|r| r.is_leader()).count(), 1);
+/// # });
+/// ```
+pub struct Barrier {
+    n: usize,
+    state: StdMutex<BarrierState>,
+}
+
+struct BarrierState {
+    // Number of tasks waiting in the current generation.
+    count: usize,
+
+    // Incremented every time the barrier releases its waiting tasks.
+    generation: usize,
+
+    waiters: Slab<Waker>,
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Barrier").field("n", &self.n).field("waiting", &state.count).finish()
+    }
+}
+
+impl Barrier {
+    /// Creates a new barrier which releases tasks in groups of `n`.
+    ///
+    /// A barrier created with `n` set to `0` behaves like one with `n` set
+    /// to `1`, releasing every task right away.
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            state: StdMutex::new(BarrierState { count: 0, generation: 0, waiters: Slab::new() }),
+        }
+    }
+
+    /// Waits until all the tasks of the group have reached this point.
+    ///
+    /// The returned future is counted as waiting on the barrier once it is
+    /// first polled, and stops being counted if it is dropped before the
+    /// barrier releases it.
+    ///
+    /// The last task to arrive is the leader, as reported by
+    /// [`BarrierWaitResult::is_leader`].
+    pub fn wait(&self) -> BarrierWait<'_> {
+        BarrierWait { barrier: Some(self), generation: 0, wait_key: WAIT_KEY_NONE }
+    }
+}
+
+// Sentinel for when no slot in the `Slab` has been dedicated to this object.
+const WAIT_KEY_NONE: usize = usize::max_value();
+
+/// A future which resolves once a [`Barrier`](Barrier) releases the tasks
+/// waiting on it.
+///
+/// This is created by the [`wait`](Barrier::wait) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BarrierWait<'a> {
+    // `None` indicates that the future has completed.
+    barrier: Option<&'a Barrier>,
+    generation: usize,
+    wait_key: usize,
+}
+
+impl fmt::Debug for BarrierWait<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BarrierWait")
+            .field("barrier", &self.barrier)
+            .field(
+                "wait_key",
+                &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+            )
+            .finish()
+    }
+}
+
+impl FusedFuture for BarrierWait<'_> {
+    fn is_terminated(&self) -> bool {
+        self.barrier.is_none()
+    }
+}
+
+impl Future for BarrierWait<'_> {
+    type Output = BarrierWaitResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let barrier = self.barrier.expect("polled BarrierWait after completion");
+        let mut state = barrier.state.lock().unwrap();
+
+        if self.wait_key == WAIT_KEY_NONE {
+            state.count += 1;
+            if state.count >= barrier.n {
+                state.count = 0;
+                state.generation = state.generation.wrapping_add(1);
+                for waker in state.waiters.drain() {
+                    waker.wake();
+                }
+                drop(state);
+                self.barrier = None;
+                return Poll::Ready(BarrierWaitResult { is_leader: true });
+            }
+            self.generation = state.generation;
+            self.wait_key = state.waiters.insert(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        if state.generation != self.generation {
+            // The barrier was released, and the waker already removed.
+            drop(state);
+            self.wait_key = WAIT_KEY_NONE;
+            self.barrier = None;
+            return Poll::Ready(BarrierWaitResult { is_leader: false });
+        }
+
+        let waker = &mut state.waiters[self.wait_key];
+        if !waker.will_wake(cx.waker()) {
+            *waker = cx.waker().clone();
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for BarrierWait<'_> {
+    fn drop(&mut self) {
+        if self.wait_key == WAIT_KEY_NONE {
+            return;
+        }
+        if let Some(barrier) = self.barrier {
+            let mut state = barrier.state.lock().unwrap();
+            // Only leave the group if it has not been released yet.
+            if state.generation == self.generation {
+                state.waiters.remove(self.wait_key);
+                state.count -= 1;
+            }
+        }
+    }
+}
+
+/// The result of waiting on a [`Barrier`](Barrier).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// Returns `true` for exactly one of the tasks released together by the
+    /// barrier: the last one to arrive.
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
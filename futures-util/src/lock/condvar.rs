@@ -0,0 +1,335 @@
+use super::mutex::{Mutex, MutexGuard, MutexLockFuture};
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+use slab::Slab;
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::sync::Mutex as StdMutex;
+
+/// A futures-aware condition variable, to be used with a futures-aware
+/// [`Mutex`](super::Mutex).
+///
+/// A task holding a [`MutexGuard`](super::MutexGuard) can give it up and
+/// wait with [`wait`](Condvar::wait) until another task calls
+/// [`notify_one`](Condvar::notify_one) or
+/// [`notify_all`](Condvar::notify_all), at which point the mutex is locked
+/// again. Releasing the guard and starting to wait happen atomically: a
+/// notification sent by a task which locked the mutex after the guard was
+/// released cannot be missed.
+///
+/// Another task may lock the mutex between the notification and the moment
+/// the waiting task gets the lock back, and change the protected data. The
+/// condition being waited for should thus be checked again once the wait
+/// completes, for which [`wait_while`](Condvar::wait_while) is provided.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::future::join;
+/// use futures::lock::{Condvar, Mutex};
+///
+/// let ready = Mutex::new(false);
+/// let condvar = Condvar::new();
+///
+/// join(
+///     async {
+///         let guard = condvar.wait_while(ready.lock().await, |ready| !*ready).await;
+///         assert!(*guard);
+///     },
+///     async {
+///         *ready.lock().await = true;
+///         condvar.notify_one();
+///     },
+/// )
+/// .await;
+/// # });
+/// ```
+pub struct Condvar {
+    state: StdMutex<State>,
+}
+
+struct State {
+    waiters: Slab<Waiter>,
+
+    // Keys of the waiters in `waiters` which have not been notified yet, in
+    // the order they started waiting.
+    queue: VecDeque<usize>,
+}
+
+struct Waiter {
+    notified: bool,
+    waker: Option<Waker>,
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Condvar").field("waiters", &state.queue.len()).finish()
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Condvar {
+    /// Creates a new condition variable, with no task waiting on it.
+    pub fn new() -> Self {
+        Self { state: StdMutex::new(State { waiters: Slab::new(), queue: VecDeque::new() }) }
+    }
+
+    /// Releases `guard` and waits until this condition variable is notified,
+    /// then locks the mutex again.
+    ///
+    /// The task starts waiting when this method is called, not when the
+    /// returned future is first polled, so that no notification is missed
+    /// in between. The future resolves with the re-acquired guard.
+    ///
+    /// If the future is dropped after being picked by
+    /// [`notify_one`](Condvar::notify_one), but before the notification was
+    /// observed, the notification is passed on to the next waiting task.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> CondvarWait<'_, 'a, T> {
+        let mutex = MutexGuard::mutex(&guard);
+        let wait_key = {
+            let mut state = self.state.lock().unwrap();
+            let wait_key = state.waiters.insert(Waiter { notified: false, waker: None });
+            state.queue.push_back(wait_key);
+            wait_key
+        };
+        drop(guard);
+        CondvarWait { condvar: self, mutex, state: WaitState::Waiting(wait_key) }
+    }
+
+    /// Waits on this condition variable as long as `condition` returns
+    /// `true`, then resolves with the guard.
+    ///
+    /// The condition is checked with the mutex locked, first right away, and
+    /// then every time the task is notified and got the lock back. Waking up
+    /// while the condition still holds is not an error: the task simply goes
+    /// back to waiting.
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        condition: F,
+    ) -> CondvarWaitWhile<'_, 'a, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        CondvarWaitWhile { condvar: self, state: WaitWhileState::Check(guard), condition }
+    }
+
+    /// Wakes the task which has been waiting the longest on this condition
+    /// variable, if any.
+    ///
+    /// Unlike [`Notify`](super::Notify), no permit is stored if no task is
+    /// waiting.
+    pub fn notify_one(&self) {
+        self.state.lock().unwrap().notify_one();
+    }
+
+    /// Wakes all the tasks waiting on this condition variable.
+    pub fn notify_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        while state.notify_one() {}
+    }
+
+    fn poll_notified(&self, wait_key: usize, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        let waiter = &mut state.waiters[wait_key];
+        if waiter.notified {
+            state.waiters.remove(wait_key);
+            Poll::Ready(())
+        } else {
+            match &waiter.waker {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => waiter.waker = Some(cx.waker().clone()),
+            }
+            Poll::Pending
+        }
+    }
+
+    // Called when a future still waiting for a notification is dropped.
+    fn cancel_wait(&self, wait_key: usize) {
+        let mut state = self.state.lock().unwrap();
+        if state.waiters.remove(wait_key).notified {
+            // We were notified, but are gone before we could observe it.
+            // Pass the notification on.
+            state.notify_one();
+        } else if let Some(pos) = state.queue.iter().position(|&key| key == wait_key) {
+            state.queue.remove(pos);
+        }
+    }
+}
+
+impl State {
+    // Notifies the oldest waiter, returning `false` if there is none.
+    fn notify_one(&mut self) -> bool {
+        match self.queue.pop_front() {
+            Some(key) => {
+                let waiter = &mut self.waiters[key];
+                waiter.notified = true;
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+enum WaitState<'a, T: ?Sized> {
+    Waiting(usize),
+    Locking(MutexLockFuture<'a, T>),
+    Done,
+}
+
+/// A future which resolves once a [`Condvar`](Condvar) has been notified
+/// and the mutex locked again.
+///
+/// This is created by the [`wait`](Condvar::wait) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CondvarWait<'c, 'a, T: ?Sized> {
+    condvar: &'c Condvar,
+    mutex: &'a Mutex<T>,
+    state: WaitState<'a, T>,
+}
+
+impl<T: ?Sized> fmt::Debug for CondvarWait<'_, '_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            WaitState::Waiting(_) => "Waiting",
+            WaitState::Locking(_) => "Locking",
+            WaitState::Done => "Done",
+        };
+        f.debug_struct("CondvarWait")
+            .field("condvar", &self.condvar)
+            .field("mutex", &self.mutex)
+            .field("state", &state)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> FusedFuture for CondvarWait<'_, '_, T> {
+    fn is_terminated(&self) -> bool {
+        match self.state {
+            WaitState::Done => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Future for CondvarWait<'_, 'a, T> {
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match &mut self.state {
+                WaitState::Waiting(wait_key) => {
+                    let wait_key = *wait_key;
+                    if self.condvar.poll_notified(wait_key, cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.state = WaitState::Locking(self.mutex.lock());
+                }
+                WaitState::Locking(lock) => {
+                    let guard = match Pin::new(lock).poll(cx) {
+                        Poll::Ready(guard) => guard,
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    self.state = WaitState::Done;
+                    return Poll::Ready(guard);
+                }
+                WaitState::Done => panic!("polled CondvarWait after completion"),
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for CondvarWait<'_, '_, T> {
+    fn drop(&mut self) {
+        if let WaitState::Waiting(wait_key) = self.state {
+            self.condvar.cancel_wait(wait_key);
+        }
+    }
+}
+
+enum WaitWhileState<'c, 'a, T: ?Sized> {
+    Check(MutexGuard<'a, T>),
+    Wait(CondvarWait<'c, 'a, T>),
+    Done,
+}
+
+/// A future which resolves once a condition checked after each notification
+/// of a [`Condvar`](Condvar) no longer holds.
+///
+/// This is created by the [`wait_while`](Condvar::wait_while) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct CondvarWaitWhile<'c, 'a, T: ?Sized, F> {
+    condvar: &'c Condvar,
+    state: WaitWhileState<'c, 'a, T>,
+    condition: F,
+}
+
+// The condition is never pinned.
+impl<T: ?Sized, F> Unpin for CondvarWaitWhile<'_, '_, T, F> {}
+
+impl<T: ?Sized, F> fmt::Debug for CondvarWaitWhile<'_, '_, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            WaitWhileState::Check(_) => "Check",
+            WaitWhileState::Wait(_) => "Wait",
+            WaitWhileState::Done => "Done",
+        };
+        f.debug_struct("CondvarWaitWhile")
+            .field("condvar", &self.condvar)
+            .field("state", &state)
+            .finish()
+    }
+}
+
+impl<T: ?Sized, F> FusedFuture for CondvarWaitWhile<'_, '_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    fn is_terminated(&self) -> bool {
+        match self.state {
+            WaitWhileState::Done => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a, T: ?Sized, F> Future for CondvarWaitWhile<'_, 'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            match mem::replace(&mut this.state, WaitWhileState::Done) {
+                WaitWhileState::Check(mut guard) => {
+                    if !(this.condition)(&mut guard) {
+                        return Poll::Ready(guard);
+                    }
+                    this.state = WaitWhileState::Wait(this.condvar.wait(guard));
+                }
+                WaitWhileState::Wait(mut wait) => match Pin::new(&mut wait).poll(cx) {
+                    Poll::Ready(guard) => this.state = WaitWhileState::Check(guard),
+                    Poll::Pending => {
+                        this.state = WaitWhileState::Wait(wait);
+                        return Poll::Pending;
+                    }
+                },
+                WaitWhileState::Done => panic!("polled CondvarWaitWhile after completion"),
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Deadlock and lock-order detection for `Mutex`, enabled by the `lock-debug`
+//! feature.
+//!
+//! Every lock acquisition records where it was made from and, when made by
+//! polling a lock future, the task making it, identified by its waker. When a
+//! task starts waiting for a lock, it is checked against the locks that task
+//! already holds:
+//!
+//! - if the task holds that very lock, it waits forever unless the lock is
+//!   held by another future polled by the same task, as with `join`;
+//! - otherwise, an edge from each held lock to the new one is added to a
+//!   global lock-order graph, and the graph already having a path the other
+//!   way around is a lock order inversion, as two tasks taking the locks in
+//!   opposite orders can deadlock. Futures polled by the same task add edges
+//!   between locks they take independently, so this too may be a false
+//!   alarm.
+//!
+//! Both are reported to the hook set by `set_deadlock_hook`, if any.
+//!
+//! When the feature is disabled, the types in this module are empty and their
+//! methods do nothing.
+
+#[cfg(feature = "lock-debug")]
+mod imp {
+    use futures_core::task::Context;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::panic;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex as StdMutex, MutexGuard as StdMutexGuard, Once};
+    use std::task::Waker;
+
+    /// Where a lock was taken from.
+    #[derive(Clone, Copy)]
+    pub(in crate::lock) struct Location(&'static panic::Location<'static>);
+
+    impl Location {
+        // The `lock-debug` feature requires Rust 1.46.
+        #[allow(clippy::incompatible_msrv)]
+        #[track_caller]
+        pub(in crate::lock) fn caller() -> Self {
+            Self(panic::Location::caller())
+        }
+    }
+
+    impl fmt::Display for Location {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(self.0, f)
+        }
+    }
+
+    type Hook = Arc<dyn Fn(&str) + Send + Sync>;
+
+    struct Held {
+        // The task holding the lock, if it was taken by polling a lock future.
+        holder: Option<Waker>,
+        location: Location,
+    }
+
+    #[derive(Default)]
+    struct Registry {
+        // Locks currently held, by id.
+        held: HashMap<usize, Held>,
+
+        // `edges[a][b]` records that lock `b` was taken while holding lock
+        // `a`, with the locations the first time it happened.
+        edges: HashMap<usize, HashMap<usize, (Location, Location)>>,
+
+        // Called with the reports, see `set_deadlock_hook`.
+        hook: Option<Hook>,
+    }
+
+    impl Registry {
+        // Finds a path from lock `from` to lock `to` in the lock-order graph.
+        fn find_path(&self, from: usize, to: usize) -> Option<Vec<(Location, Location)>> {
+            let mut visited = vec![from];
+            let mut stack = vec![(from, Vec::new())];
+            while let Some((lock, path)) = stack.pop() {
+                for (&next, &locations) in self.edges.get(&lock).into_iter().flatten() {
+                    let mut path: Vec<(Location, Location)> = path.clone();
+                    path.push(locations);
+                    if next == to {
+                        return Some(path);
+                    }
+                    if !visited.contains(&next) {
+                        visited.push(next);
+                        stack.push((next, path));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    fn registry() -> StdMutexGuard<'static, Registry> {
+        static INIT: Once = Once::new();
+        static mut REGISTRY: *const StdMutex<Registry> = ptr::null();
+        // `REGISTRY` is only written once, before anyone reads it.
+        let registry = unsafe {
+            INIT.call_once(|| {
+                REGISTRY = Box::into_raw(Box::new(StdMutex::new(Registry::default())));
+            });
+            &*REGISTRY
+        };
+        // A panic reporting a deadlock must not disable the detection for the
+        // rest of the program.
+        registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets the function called with a description of each possible deadlock
+    /// found by the `lock-debug` feature, replacing the previous one.
+    ///
+    /// Possible deadlocks are not reported until a hook is set. The hook may
+    /// log them, or panic to make a test fail, but it must not take a
+    /// [`Mutex`](crate::lock::Mutex) itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// futures::lock::set_deadlock_hook(Box::new(|report| {
+    ///     eprintln!("{}", report);
+    /// }));
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "lock-debug")))]
+    pub fn set_deadlock_hook(hook: Box<dyn Fn(&str) + Send + Sync>) {
+        registry().hook = Some(Arc::from(hook));
+    }
+
+    fn is_held_by(held: &Held, cx: &Context<'_>) -> bool {
+        held.holder.as_ref().map_or(false, |waker| waker.will_wake(cx.waker()))
+    }
+
+    /// The debugging state of a lock.
+    pub(in crate::lock) struct LockDebug {
+        // `None` if the lock is not tracked.
+        id: Option<usize>,
+    }
+
+    impl LockDebug {
+        pub(in crate::lock) fn new() -> Self {
+            static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+            Self { id: Some(NEXT_ID.fetch_add(1, Ordering::Relaxed)) }
+        }
+
+        pub(in crate::lock) fn untracked() -> Self {
+            Self { id: None }
+        }
+
+        // Called when the task of `cx` starts trying to take the lock.
+        pub(in crate::lock) fn before_acquire(&self, location: Location, cx: &Context<'_>) {
+            let lock = match self.id {
+                Some(id) => id,
+                None => return,
+            };
+            let mut registry = registry();
+            let mut reports = Vec::new();
+
+            if let Some(held) = registry.held.get(&lock) {
+                if is_held_by(held, cx) {
+                    reports.push(format!(
+                        "possible deadlock: a task waits for a lock at {}, \
+                         which it already holds since {}, unless it is held \
+                         by another future of the same task",
+                        location, held.location
+                    ));
+                }
+            }
+
+            let held_by_task: Vec<(usize, Location)> = registry
+                .held
+                .iter()
+                .filter(|&(&id, held)| id != lock && is_held_by(held, cx))
+                .map(|(&id, held)| (id, held.location))
+                .collect();
+            for (id, held_location) in held_by_task {
+                if let Some(path) = registry.find_path(lock, id) {
+                    let mut message = format!(
+                        "lock order inversion detected: a lock is taken at {} \
+                         while holding a lock taken at {}, but they were \
+                         previously taken in the opposite order:",
+                        location, held_location
+                    );
+                    for (first, then) in path {
+                        message.push_str(&format!("\n  lock taken at {}, then at {}", first, then));
+                    }
+                    reports.push(message);
+                    continue;
+                }
+                registry
+                    .edges
+                    .entry(id)
+                    .or_default()
+                    .entry(lock)
+                    .or_insert((held_location, location));
+            }
+
+            // The hook may panic, so it is called without the registry locked.
+            let hook = registry.hook.clone();
+            drop(registry);
+            if let Some(hook) = hook {
+                for report in reports {
+                    hook(&report);
+                }
+            }
+        }
+
+        // Called once the lock is taken, by the task of `cx` if any.
+        pub(in crate::lock) fn acquired(&self, location: Location, cx: Option<&Context<'_>>) {
+            if let Some(id) = self.id {
+                let holder = cx.map(|cx| cx.waker().clone());
+                registry().held.insert(id, Held { holder, location });
+            }
+        }
+
+        // Called before the lock is released.
+        pub(in crate::lock) fn released(&self) {
+            if let Some(id) = self.id {
+                registry().held.remove(&id);
+            }
+        }
+    }
+
+    impl Drop for LockDebug {
+        fn drop(&mut self) {
+            let id = match self.id {
+                Some(id) => id,
+                None => return,
+            };
+            let mut registry = registry();
+            registry.held.remove(&id);
+            registry.edges.remove(&id);
+            for edges in registry.edges.values_mut() {
+                edges.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "lock-debug"))]
+mod imp {
+    use futures_core::task::Context;
+
+    #[derive(Clone, Copy)]
+    pub(in crate::lock) struct Location;
+
+    impl Location {
+        #[inline]
+        pub(in crate::lock) fn caller() -> Self {
+            Self
+        }
+    }
+
+    pub(in crate::lock) struct LockDebug;
+
+    impl LockDebug {
+        #[inline]
+        pub(in crate::lock) fn new() -> Self {
+            Self
+        }
+
+        #[inline]
+        pub(in crate::lock) fn untracked() -> Self {
+            Self
+        }
+
+        #[inline]
+        pub(in crate::lock) fn before_acquire(&self, _location: Location, _cx: &Context<'_>) {}
+
+        #[inline]
+        pub(in crate::lock) fn acquired(&self, _location: Location, _cx: Option<&Context<'_>>) {}
+
+        #[inline]
+        pub(in crate::lock) fn released(&self) {}
+    }
+}
+
+pub(super) use self::imp::{Location, LockDebug};
+
+#[cfg(feature = "lock-debug")]
+pub use self::imp::set_deadlock_hook;
@@ -0,0 +1,159 @@
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+use slab::Slab;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Mutex as StdMutex;
+
+/// A futures-aware countdown latch, letting tasks wait until a number of
+/// events have happened.
+///
+/// The latch starts with a count, which is decremented by
+/// [`count_down`](CountdownLatch::count_down). Tasks waiting with
+/// [`wait`](CountdownLatch::wait) are released once it reaches zero, and the
+/// latch stays open from then on.
+///
+/// # Examples
+///
+/// ```
+/// use futures::executor::block_on;
+/// use futures::lock::CountdownLatch;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let latch = Arc::new(CountdownLatch::new(4));
+///
+/// for _ in 0..4 {
+///     let latch = latch.clone();
+///     thread::spawn(move || latch.count_down());
+/// }
+///
+/// block_on(latch.wait());
+/// assert_eq!(latch.count(), 0);
+/// ```
+pub struct CountdownLatch {
+    state: StdMutex<LatchState>,
+}
+
+struct LatchState {
+    count: usize,
+    waiters: Slab<Waker>,
+}
+
+impl fmt::Debug for CountdownLatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountdownLatch").field("count", &self.count()).finish()
+    }
+}
+
+impl CountdownLatch {
+    /// Creates a new latch which opens after `count` calls to
+    /// [`count_down`](CountdownLatch::count_down).
+    pub fn new(count: usize) -> Self {
+        Self { state: StdMutex::new(LatchState { count, waiters: Slab::new() }) }
+    }
+
+    /// Returns the number of calls to
+    /// [`count_down`](CountdownLatch::count_down) left before the latch
+    /// opens.
+    pub fn count(&self) -> usize {
+        self.state.lock().unwrap().count
+    }
+
+    /// Decrements the count of the latch, releasing the waiting tasks if it
+    /// reaches zero.
+    ///
+    /// This does nothing if the latch is already open.
+    pub fn count_down(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.count == 0 {
+            return;
+        }
+        state.count -= 1;
+        if state.count == 0 {
+            for waker in state.waiters.drain() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Waits until the latch opens.
+    ///
+    /// The returned future resolves right away if the count already reached
+    /// zero.
+    pub fn wait(&self) -> LatchWait<'_> {
+        LatchWait { latch: Some(self), wait_key: WAIT_KEY_NONE }
+    }
+}
+
+// Sentinel for when no slot in the `Slab` has been dedicated to this object.
+const WAIT_KEY_NONE: usize = usize::max_value();
+
+/// A future which resolves once a [`CountdownLatch`](CountdownLatch) opens.
+///
+/// This is created by the [`wait`](CountdownLatch::wait) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LatchWait<'a> {
+    // `None` indicates that the future has completed.
+    latch: Option<&'a CountdownLatch>,
+    wait_key: usize,
+}
+
+impl fmt::Debug for LatchWait<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LatchWait")
+            .field("latch", &self.latch)
+            .field(
+                "wait_key",
+                &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+            )
+            .finish()
+    }
+}
+
+impl FusedFuture for LatchWait<'_> {
+    fn is_terminated(&self) -> bool {
+        self.latch.is_none()
+    }
+}
+
+impl Future for LatchWait<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let latch = self.latch.expect("polled LatchWait after completion");
+        let mut state = latch.state.lock().unwrap();
+
+        if state.count == 0 {
+            // Once open, the latch removed all the wakers.
+            drop(state);
+            self.wait_key = WAIT_KEY_NONE;
+            self.latch = None;
+            return Poll::Ready(());
+        }
+
+        if self.wait_key == WAIT_KEY_NONE {
+            self.wait_key = state.waiters.insert(cx.waker().clone());
+        } else {
+            let waker = &mut state.waiters[self.wait_key];
+            if !waker.will_wake(cx.waker()) {
+                *waker = cx.waker().clone();
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for LatchWait<'_> {
+    fn drop(&mut self) {
+        if self.wait_key == WAIT_KEY_NONE {
+            return;
+        }
+        if let Some(latch) = self.latch {
+            let mut state = latch.state.lock().unwrap();
+            if state.count != 0 {
+                state.waiters.remove(self.wait_key);
+            }
+        }
+    }
+}
@@ -2,13 +2,102 @@
 //!
 //! This module is only available when the `std` or `alloc` feature of this
 //! library is activated, and it is activated by default.
+//!
+//! # Debugging deadlocks
+//!
+//! With the `lock-debug` feature, [`Mutex`] keeps track of the locks held by
+//! each task, and of where they were taken from. A task taking two locks in
+//! the opposite order from the one they were previously taken in, which could
+//! deadlock with another task, is reported to the hook set by
+//! `set_deadlock_hook`, along with the locations the locks were taken from.
+//! So is a task waiting for a lock it already holds.
+//!
+//! Tasks are told apart by their wakers, so futures polled by the same task
+//! with the same waker, for instance by `join!`, count as a single task. The
+//! reports may therefore be false alarms: a task hangs if it awaits a lock
+//! while holding its guard, but not if the guard is held by another future
+//! joined with it, and locks taken by two joined futures do not need to be
+//! taken in a consistent order.
+//!
+//! This feature requires Rust 1.46 or later, and has no cost when disabled.
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod debug;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "lock-debug")]
+pub use self::debug::set_deadlock_hook;
 
 #[cfg(not(futures_no_atomic_cas))]
 #[cfg(feature = "std")]
 mod mutex;
 #[cfg(not(futures_no_atomic_cas))]
 #[cfg(feature = "std")]
-pub use self::mutex::{MappedMutexGuard, Mutex, MutexGuard, MutexLockFuture};
+pub use self::mutex::{
+    MappedMutexGuard, Mutex, MutexGuard, MutexLockFuture, OwnedMappedMutexGuard, OwnedMutexGuard,
+    OwnedMutexLockFuture,
+};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod condvar;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::condvar::{Condvar, CondvarWait, CondvarWaitWhile};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod once_cell;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::once_cell::{AsyncLazy, GetOrInit, GetOrTryInit, OnceCell};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod rwlock;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::rwlock::{
+    MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadFuture, RwLockReadGuard,
+    RwLockUpgradableReadFuture, RwLockUpgradableReadGuard, RwLockUpgradeFuture, RwLockWriteFuture,
+    RwLockWriteGuard,
+};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod semaphore;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::semaphore::{
+    Acquire, AcquireError, AcquireOwned, OwnedSemaphorePermit, Semaphore, SemaphorePermit,
+    TryAcquireError,
+};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod barrier;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::barrier::{Barrier, BarrierWait, BarrierWaitResult};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod latch;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::latch::{CountdownLatch, LatchWait};
+
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+mod wait_group;
+#[cfg(not(futures_no_atomic_cas))]
+#[cfg(feature = "std")]
+pub use self::wait_group::{WaitGroup, WaitGroupWait};
+
+#[cfg(not(futures_no_atomic_cas))]
+mod notify;
+#[cfg(not(futures_no_atomic_cas))]
+pub use self::notify::{Notified, Notify};
 
 #[cfg(not(futures_no_atomic_cas))]
 #[cfg(any(feature = "bilock", feature = "sink", feature = "io"))]
@@ -1,34 +1,51 @@
+use super::debug::{Location, LockDebug};
 use futures_core::future::{FusedFuture, Future};
 use futures_core::task::{Context, Poll, Waker};
 use slab::Slab;
 use std::cell::UnsafeCell;
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex as StdMutex;
-use std::{fmt, mem};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::{fmt, mem, ptr};
 
 /// A futures-aware mutex.
 ///
 /// # Fairness
 ///
-/// This mutex provides no fairness guarantees. Tasks may not acquire the mutex
-/// in the order that they requested the lock, and it's possible for a single task
-/// which repeatedly takes the lock to starve other tasks, which may be left waiting
-/// indefinitely.
+/// A mutex created with [`new`](Mutex::new) provides no fairness guarantees.
+/// Tasks may not acquire the mutex in the order that they requested the lock,
+/// and it's possible for a single task which repeatedly takes the lock to
+/// starve other tasks, which may be left waiting indefinitely.
+///
+/// A mutex created with [`new_fair`](Mutex::new_fair) instead hands the lock
+/// directly to the task which has been waiting the longest when it is
+/// released, so that every waiting task eventually gets it.
 pub struct Mutex<T: ?Sized> {
     state: AtomicUsize,
-    waiters: StdMutex<Slab<Waiter>>,
+    waiters: StdMutex<Waiters>,
+    is_fair: bool,
+    debug: LockDebug,
     value: UnsafeCell<T>,
 }
 
+struct Waiters {
+    slab: Slab<Waiter>,
+
+    // Keys of the waiters in `slab` which have not been handed the lock yet,
+    // in the order they started waiting. Only used by fair mutexes.
+    queue: VecDeque<usize>,
+}
+
 impl<T: ?Sized> fmt::Debug for Mutex<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let state = self.state.load(Ordering::SeqCst);
         f.debug_struct("Mutex")
             .field("is_locked", &((state & IS_LOCKED) != 0))
             .field("has_waiters", &((state & HAS_WAITERS) != 0))
+            .field("is_fair", &self.is_fair)
             .finish()
     }
 }
@@ -48,6 +65,8 @@ impl<T: Default> Default for Mutex<T> {
 enum Waiter {
     Waiting(Waker),
     Woken,
+    // The lock was handed to the waiter by a fair mutex.
+    Acquired,
 }
 
 impl Waiter {
@@ -61,7 +80,7 @@ impl Waiter {
     fn wake(&mut self) {
         match mem::replace(self, Self::Woken) {
             Self::Waiting(waker) => waker.wake(),
-            Self::Woken => {}
+            Self::Woken | Self::Acquired => {}
         }
     }
 }
@@ -72,9 +91,50 @@ const HAS_WAITERS: usize = 1 << 1;
 impl<T> Mutex<T> {
     /// Creates a new futures-aware mutex.
     pub fn new(t: T) -> Self {
+        Self::with_fairness(t, false, LockDebug::new())
+    }
+
+    /// Creates a new futures-aware mutex which hands the lock to the tasks
+    /// waiting for it in the order they started waiting.
+    ///
+    /// When the lock is released while tasks are waiting, ownership goes
+    /// directly to the one which has been waiting the longest, and other tasks
+    /// cannot take the lock in between, even by calling
+    /// [`try_lock`](Mutex::try_lock). If that task's lock future is dropped
+    /// before it completes, the lock goes to the next waiting task, or is
+    /// released if there is none.
+    ///
+    /// This prevents waiting tasks from being starved, at the cost of some
+    /// throughput under contention.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::Mutex;
+    ///
+    /// let mutex = Mutex::new_fair(0);
+    /// *mutex.lock().await += 1;
+    /// assert_eq!(*mutex.lock().await, 1);
+    /// # });
+    /// ```
+    pub fn new_fair(t: T) -> Self {
+        Self::with_fairness(t, true, LockDebug::new())
+    }
+
+    // Creates a fair mutex ignored by the `lock-debug` checks, for locks used
+    // internally by other primitives, which futures of a single task may
+    // legitimately wait for one after the other.
+    pub(super) fn new_fair_untracked(t: T) -> Self {
+        Self::with_fairness(t, true, LockDebug::untracked())
+    }
+
+    fn with_fairness(t: T, is_fair: bool, debug: LockDebug) -> Self {
         Self {
             state: AtomicUsize::new(0),
-            waiters: StdMutex::new(Slab::new()),
+            waiters: StdMutex::new(Waiters { slab: Slab::new(), queue: VecDeque::new() }),
+            is_fair,
+            debug,
             value: UnsafeCell::new(t),
         }
     }
@@ -98,21 +158,65 @@ impl<T: ?Sized> Mutex<T> {
     /// Attempt to acquire the lock immediately.
     ///
     /// If the lock is currently held, this will return `None`.
+    #[cfg_attr(feature = "lock-debug", track_caller)]
     pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
-        let old_state = self.state.fetch_or(IS_LOCKED, Ordering::Acquire);
-        if (old_state & IS_LOCKED) == 0 {
+        if self.try_acquire() {
+            self.debug.acquired(Location::caller(), None);
             Some(MutexGuard { mutex: self })
         } else {
             None
         }
     }
 
+    /// Attempt to acquire the lock immediately, returning a guard which holds
+    /// a reference to the mutex instead of borrowing it.
+    ///
+    /// If the lock is currently held, this will return `None`.
+    #[cfg_attr(feature = "lock-debug", track_caller)]
+    pub fn try_lock_owned(self: Arc<Self>) -> Option<OwnedMutexGuard<T>> {
+        if self.try_acquire() {
+            self.debug.acquired(Location::caller(), None);
+            Some(OwnedMutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
     /// Acquire the lock asynchronously.
     ///
     /// This method returns a future that will resolve once the lock has been
     /// successfully acquired.
+    #[cfg_attr(feature = "lock-debug", track_caller)]
     pub fn lock(&self) -> MutexLockFuture<'_, T> {
-        MutexLockFuture { mutex: Some(self), wait_key: WAIT_KEY_NONE }
+        MutexLockFuture { mutex: Some(self), wait_key: WAIT_KEY_NONE, location: Location::caller() }
+    }
+
+    /// Acquire the lock asynchronously, returning a guard which holds a
+    /// reference to the mutex instead of borrowing it.
+    ///
+    /// The guard can be stored in a struct, or moved into a spawned task.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::Mutex;
+    /// use std::sync::Arc;
+    ///
+    /// let mutex = Arc::new(Mutex::new(0));
+    ///
+    /// let mut guard = mutex.clone().lock_owned().await;
+    /// std::thread::spawn(move || *guard += 1).join().unwrap();
+    /// assert_eq!(*mutex.lock().await, 1);
+    /// # });
+    /// ```
+    #[cfg_attr(feature = "lock-debug", track_caller)]
+    pub fn lock_owned(self: Arc<Self>) -> OwnedMutexLockFuture<T> {
+        OwnedMutexLockFuture {
+            mutex: Some(self),
+            wait_key: WAIT_KEY_NONE,
+            location: Location::caller(),
+        }
     }
 
     /// Returns a mutable reference to the underlying data.
@@ -137,11 +241,112 @@ impl<T: ?Sized> Mutex<T> {
         unsafe { &mut *self.value.get() }
     }
 
+    // A fair mutex is never unlocked while tasks are queued for it, as
+    // unlocking hands the lock to the first of them instead, so taking the lock
+    // when it is free cannot jump the queue.
+    fn try_acquire(&self) -> bool {
+        let old_state = self.state.fetch_or(IS_LOCKED, Ordering::Acquire);
+        (old_state & IS_LOCKED) == 0
+    }
+
+    // Polls for the lock on behalf of a lock future, registering it as a
+    // waiter if the lock is held.
+    fn poll_acquire(
+        &self,
+        wait_key: &mut usize,
+        location: Location,
+        cx: &mut Context<'_>,
+    ) -> Poll<()> {
+        if *wait_key == WAIT_KEY_NONE {
+            self.debug.before_acquire(location, cx);
+        }
+
+        let poll = if self.is_fair {
+            self.poll_acquire_fair(wait_key, cx)
+        } else {
+            self.poll_acquire_unfair(wait_key, cx)
+        };
+        if poll.is_ready() {
+            self.debug.acquired(location, Some(cx));
+        }
+        poll
+    }
+
+    fn poll_acquire_unfair(&self, wait_key: &mut usize, cx: &mut Context<'_>) -> Poll<()> {
+        if self.try_acquire() {
+            self.remove_waker(*wait_key, false);
+            return Poll::Ready(());
+        }
+
+        {
+            let mut waiters = self.waiters.lock().unwrap();
+            let waiters = &mut waiters.slab;
+            if *wait_key == WAIT_KEY_NONE {
+                *wait_key = waiters.insert(Waiter::Waiting(cx.waker().clone()));
+                if waiters.len() == 1 {
+                    self.state.fetch_or(HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
+                }
+            } else {
+                waiters[*wait_key].register(cx.waker());
+            }
+        }
+
+        // Ensure that we haven't raced `MutexGuard::drop`'s unlock path by
+        // attempting to acquire the lock again.
+        if self.try_acquire() {
+            self.remove_waker(*wait_key, false);
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+
+    fn poll_acquire_fair(&self, wait_key: &mut usize, cx: &mut Context<'_>) -> Poll<()> {
+        if *wait_key == WAIT_KEY_NONE && self.try_acquire() {
+            return Poll::Ready(());
+        }
+
+        // Unlocking a fair mutex goes through `waiters`, so the lock cannot be
+        // released between the checks below.
+        let mut waiters = self.waiters.lock().unwrap();
+        if *wait_key == WAIT_KEY_NONE {
+            if self.try_acquire() {
+                return Poll::Ready(());
+            }
+            *wait_key = waiters.slab.insert(Waiter::Waiting(cx.waker().clone()));
+            waiters.queue.push_back(*wait_key);
+            self.state.fetch_or(HAS_WAITERS, Ordering::Relaxed);
+            return Poll::Pending;
+        }
+
+        match &mut waiters.slab[*wait_key] {
+            Waiter::Acquired => {
+                waiters.slab.remove(*wait_key);
+                *wait_key = WAIT_KEY_NONE;
+                Poll::Ready(())
+            }
+            waiter => {
+                waiter.register(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+
     fn remove_waker(&self, wait_key: usize, wake_another: bool) {
-        if wait_key != WAIT_KEY_NONE {
+        if wait_key != WAIT_KEY_NONE && self.is_fair {
+            let mut waiters = self.waiters.lock().unwrap();
+            if let Waiter::Acquired = waiters.slab.remove(wait_key) {
+                // The lock was handed to us, but we are gone before we could
+                // take it. Hand it to the next waiter instead.
+                self.unlock_fair(&mut waiters);
+            } else if let Some(pos) = waiters.queue.iter().position(|&key| key == wait_key) {
+                waiters.queue.remove(pos);
+            }
+        } else if wait_key != WAIT_KEY_NONE {
             let mut waiters = self.waiters.lock().unwrap();
+            let waiters = &mut waiters.slab;
             match waiters.remove(wait_key) {
-                Waiter::Waiting(_) => {}
+                Waiter::Waiting(_) | Waiter::Acquired => {}
                 Waiter::Woken => {
                     // We were awoken, but then dropped before we could
                     // wake up to acquire the lock. Wake up another
@@ -162,14 +367,38 @@ impl<T: ?Sized> Mutex<T> {
     // Unlocks the mutex. Called by MutexGuard and MappedMutexGuard when they are
     // dropped.
     fn unlock(&self) {
+        self.debug.released();
+
+        if self.is_fair {
+            self.unlock_fair(&mut self.waiters.lock().unwrap());
+            return;
+        }
+
         let old_state = self.state.fetch_and(!IS_LOCKED, Ordering::AcqRel);
         if (old_state & HAS_WAITERS) != 0 {
             let mut waiters = self.waiters.lock().unwrap();
-            if let Some((_i, waiter)) = waiters.iter_mut().next() {
+            if let Some((_i, waiter)) = waiters.slab.iter_mut().next() {
                 waiter.wake();
             }
         }
     }
+
+    // Hands the lock to the oldest waiter of a fair mutex, keeping it locked,
+    // or unlocks it if no task is waiting.
+    fn unlock_fair(&self, waiters: &mut Waiters) {
+        match waiters.queue.pop_front() {
+            Some(key) => {
+                if let Waiter::Waiting(waker) =
+                    mem::replace(&mut waiters.slab[key], Waiter::Acquired)
+                {
+                    waker.wake();
+                }
+            }
+            None => {
+                self.state.fetch_and(!(IS_LOCKED | HAS_WAITERS), Ordering::Release);
+            }
+        }
+    }
 }
 
 // Sentinel for when no slot in the `Slab` has been dedicated to this object.
@@ -180,6 +409,7 @@ pub struct MutexLockFuture<'a, T: ?Sized> {
     // `None` indicates that the mutex was successfully acquired.
     mutex: Option<&'a Mutex<T>>,
     wait_key: usize,
+    location: Location,
 }
 
 impl<T: ?Sized> fmt::Debug for MutexLockFuture<'_, T> {
@@ -206,40 +436,73 @@ impl<'a, T: ?Sized> Future for MutexLockFuture<'a, T> {
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mutex = self.mutex.expect("polled MutexLockFuture after completion");
+        let location = self.location;
 
-        if let Some(lock) = mutex.try_lock() {
-            mutex.remove_waker(self.wait_key, false);
-            self.mutex = None;
-            return Poll::Ready(lock);
+        if mutex.poll_acquire(&mut self.wait_key, location, cx).is_pending() {
+            return Poll::Pending;
         }
+        self.mutex = None;
+        Poll::Ready(MutexGuard { mutex })
+    }
+}
 
-        {
-            let mut waiters = mutex.waiters.lock().unwrap();
-            if self.wait_key == WAIT_KEY_NONE {
-                self.wait_key = waiters.insert(Waiter::Waiting(cx.waker().clone()));
-                if waiters.len() == 1 {
-                    mutex.state.fetch_or(HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
-                }
-            } else {
-                waiters[self.wait_key].register(cx.waker());
-            }
+impl<T: ?Sized> Drop for MutexLockFuture<'_, T> {
+    fn drop(&mut self) {
+        if let Some(mutex) = self.mutex {
+            // This future was dropped before it acquired the mutex.
+            //
+            // Remove ourselves from the map, waking up another waiter if we
+            // had been awoken to acquire the lock.
+            mutex.remove_waker(self.wait_key, true);
         }
+    }
+}
 
-        // Ensure that we haven't raced `MutexGuard::drop`'s unlock path by
-        // attempting to acquire the lock again.
-        if let Some(lock) = mutex.try_lock() {
-            mutex.remove_waker(self.wait_key, false);
-            self.mutex = None;
-            return Poll::Ready(lock);
-        }
+/// A future which resolves when the target mutex has been successfully
+/// acquired, owned version.
+pub struct OwnedMutexLockFuture<T: ?Sized> {
+    // `None` indicates that the mutex was successfully acquired.
+    mutex: Option<Arc<Mutex<T>>>,
+    wait_key: usize,
+    location: Location,
+}
 
-        Poll::Pending
+impl<T: ?Sized> fmt::Debug for OwnedMutexLockFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedMutexLockFuture")
+            .field("was_acquired", &self.mutex.is_none())
+            .field("mutex", &self.mutex)
+            .field(
+                "wait_key",
+                &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+            )
+            .finish()
     }
 }
 
-impl<T: ?Sized> Drop for MutexLockFuture<'_, T> {
+impl<T: ?Sized> FusedFuture for OwnedMutexLockFuture<T> {
+    fn is_terminated(&self) -> bool {
+        self.mutex.is_none()
+    }
+}
+
+impl<T: ?Sized> Future for OwnedMutexLockFuture<T> {
+    type Output = OwnedMutexGuard<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mutex = this.mutex.as_ref().expect("polled OwnedMutexLockFuture after completion");
+
+        if mutex.poll_acquire(&mut this.wait_key, this.location, cx).is_pending() {
+            return Poll::Pending;
+        }
+        Poll::Ready(OwnedMutexGuard { mutex: this.mutex.take().unwrap() })
+    }
+}
+
+impl<T: ?Sized> Drop for OwnedMutexLockFuture<T> {
     fn drop(&mut self) {
-        if let Some(mutex) = self.mutex {
+        if let Some(mutex) = &self.mutex {
             // This future was dropped before it acquired the mutex.
             //
             // Remove ourselves from the map, waking up another waiter if we
@@ -284,6 +547,12 @@ impl<'a, T: ?Sized> MutexGuard<'a, T> {
         mem::forget(this);
         MappedMutexGuard { mutex, value, _marker: PhantomData }
     }
+
+    // Returns the mutex locked by the guard, for `Condvar` to lock it again
+    // after releasing the guard.
+    pub(super) fn mutex(this: &Self) -> &'a Mutex<T> {
+        this.mutex
+    }
 }
 
 impl<T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
@@ -378,6 +647,140 @@ impl<T: ?Sized, U: ?Sized> DerefMut for MappedMutexGuard<'_, T, U> {
     }
 }
 
+/// An RAII guard returned by the `lock_owned` and `try_lock_owned` methods.
+/// When this structure is dropped (falls out of scope), the lock will be
+/// unlocked.
+///
+/// Unlike [`MutexGuard`], this guard holds an `Arc` to the mutex rather than
+/// borrowing it, so it can be stored or moved into a `'static` task.
+pub struct OwnedMutexGuard<T: ?Sized> {
+    mutex: Arc<Mutex<T>>,
+}
+
+impl<T: ?Sized> OwnedMutexGuard<T> {
+    /// Returns a locked view over a portion of the locked data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::{Mutex, OwnedMutexGuard};
+    /// use std::sync::Arc;
+    ///
+    /// let data = Arc::new(Mutex::new(Some("value".to_string())));
+    /// {
+    ///     let guard = data.clone().lock_owned().await;
+    ///     let locked_str = OwnedMutexGuard::map(guard, |opt| opt.as_mut().unwrap());
+    ///     assert_eq!(&*locked_str, "value");
+    /// }
+    /// # });
+    /// ```
+    #[inline]
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> OwnedMappedMutexGuard<T, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let value = f(unsafe { &mut *this.mutex.value.get() }) as *mut U;
+        // Don't run the `drop` method for OwnedMutexGuard. The ownership of the
+        // underlying locked state is being moved to the returned
+        // OwnedMappedMutexGuard.
+        let mutex = unsafe { ptr::read(&this.mutex) };
+        mem::forget(this);
+        OwnedMappedMutexGuard { mutex, value }
+    }
+
+    /// Returns the mutex locked by this guard.
+    pub fn mutex(this: &Self) -> &Arc<Mutex<T>> {
+        &this.mutex
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for OwnedMutexGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedMutexGuard")
+            .field("value", &&**self)
+            .field("mutex", &self.mutex)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Drop for OwnedMutexGuard<T> {
+    fn drop(&mut self) {
+        self.mutex.unlock()
+    }
+}
+
+impl<T: ?Sized> Deref for OwnedMutexGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for OwnedMutexGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+/// An RAII guard returned by the `OwnedMutexGuard::map` and
+/// `OwnedMappedMutexGuard::map` methods. When this structure is dropped
+/// (falls out of scope), the lock will be unlocked.
+pub struct OwnedMappedMutexGuard<T: ?Sized, U: ?Sized> {
+    mutex: Arc<Mutex<T>>,
+    value: *mut U,
+}
+
+impl<T: ?Sized, U: ?Sized> OwnedMappedMutexGuard<T, U> {
+    /// Returns a locked view over a portion of the locked data.
+    #[inline]
+    pub fn map<V: ?Sized, F>(this: Self, f: F) -> OwnedMappedMutexGuard<T, V>
+    where
+        F: FnOnce(&mut U) -> &mut V,
+    {
+        let value = f(unsafe { &mut *this.value }) as *mut V;
+        // Don't run the `drop` method for OwnedMappedMutexGuard. The ownership
+        // of the underlying locked state is being moved to the returned
+        // OwnedMappedMutexGuard.
+        let mutex = unsafe { ptr::read(&this.mutex) };
+        mem::forget(this);
+        OwnedMappedMutexGuard { mutex, value }
+    }
+
+    /// Returns the mutex locked by this guard.
+    pub fn mutex(this: &Self) -> &Arc<Mutex<T>> {
+        &this.mutex
+    }
+}
+
+impl<T: ?Sized, U: ?Sized + fmt::Debug> fmt::Debug for OwnedMappedMutexGuard<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedMappedMutexGuard")
+            .field("value", &&**self)
+            .field("mutex", &self.mutex)
+            .finish()
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Drop for OwnedMappedMutexGuard<T, U> {
+    fn drop(&mut self) {
+        self.mutex.unlock()
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Deref for OwnedMappedMutexGuard<T, U> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        unsafe { &*self.value }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> DerefMut for OwnedMappedMutexGuard<T, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        unsafe { &mut *self.value }
+    }
+}
+
 // Mutexes can be moved freely between threads and acquired on any thread so long
 // as the inner value can be safely sent between threads.
 unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
@@ -396,6 +799,13 @@ unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
 unsafe impl<T: ?Sized + Send, U: ?Sized + Send> Send for MappedMutexGuard<'_, T, U> {}
 unsafe impl<T: ?Sized + Sync, U: ?Sized + Sync> Sync for MappedMutexGuard<'_, T, U> {}
 
+// Owned guards also hand out their `Arc` to the mutex through `&self`, which
+// can be used to send the mutex to another thread.
+unsafe impl<T: ?Sized + Send> Send for OwnedMutexGuard<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for OwnedMutexGuard<T> {}
+unsafe impl<T: ?Sized + Send, U: ?Sized + Send> Send for OwnedMappedMutexGuard<T, U> {}
+unsafe impl<T: ?Sized + Send + Sync, U: ?Sized + Sync> Sync for OwnedMappedMutexGuard<T, U> {}
+
 #[test]
 fn test_mutex_guard_debug_not_recurse() {
     let mutex = Mutex::new(42);
@@ -403,4 +813,10 @@ fn test_mutex_guard_debug_not_recurse() {
     let _ = format!("{:?}", guard);
     let guard = MutexGuard::map(guard, |n| n);
     let _ = format!("{:?}", guard);
+
+    let mutex = Arc::new(Mutex::new(42));
+    let guard = mutex.try_lock_owned().unwrap();
+    let _ = format!("{:?}", guard);
+    let guard = OwnedMutexGuard::map(guard, |n| n);
+    let _ = format!("{:?}", guard);
 }
@@ -0,0 +1,326 @@
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+
+/// Notifies tasks waiting for an event.
+///
+/// A `Notify` has no data of its own: tasks wait on it with
+/// [`notified`](Notify::notified), and get woken by
+/// [`notify_one`](Notify::notify_one) or
+/// [`notify_waiters`](Notify::notify_waiters).
+///
+/// When `notify_one` is called while no task is waiting, a permit is stored
+/// and the next call to `notified` completes right away, consuming it. At most
+/// one permit is stored at a time, so several calls to `notify_one` without a
+/// waiter in between only let one `notified` future through.
+///
+/// Unlike the other primitives in this module, `Notify` does not need the
+/// `std` feature, and can be used with only `alloc`.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::future::join;
+/// use futures::lock::Notify;
+///
+/// let notify = Notify::new();
+///
+/// join(
+///     async { notify.notified().await },
+///     async { notify.notify_one() },
+/// )
+/// .await;
+/// # });
+/// ```
+pub struct Notify {
+    // Incremented on every call to `notify_waiters`. `Notified` futures
+    // remember the value they were created with, and complete once it
+    // changes.
+    generation: AtomicUsize,
+    state: Spinlock<State>,
+}
+
+struct State {
+    // `true` if `notify_one` was called with no waiter to wake.
+    permit: bool,
+
+    // Tasks waiting on the `Notify`, in the order they started waiting.
+    waiters: Vec<Waiter>,
+
+    next_key: usize,
+}
+
+struct Waiter {
+    key: usize,
+    notified: Option<NotifiedBy>,
+    waker: Option<Waker>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NotifiedBy {
+    One,
+    All,
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock();
+        f.debug_struct("Notify")
+            .field("permit", &state.permit)
+            .field("waiters", &state.waiters.len())
+            .finish()
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notify {
+    /// Creates a new `Notify` with no stored permit.
+    pub fn new() -> Self {
+        Self {
+            generation: AtomicUsize::new(0),
+            state: Spinlock::new(State { permit: false, waiters: Vec::new(), next_key: 0 }),
+        }
+    }
+
+    /// Returns a future which completes once this `Notify` is notified.
+    ///
+    /// The future completes right away if a permit was stored by
+    /// [`notify_one`](Notify::notify_one), consuming it. Calls to
+    /// [`notify_waiters`](Notify::notify_waiters) wake every future created
+    /// before the call, whether it was already polled or not.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: Some(self),
+            generation: self.generation.load(Ordering::SeqCst),
+            wait_key: WAIT_KEY_NONE,
+        }
+    }
+
+    /// Wakes the task which has been waiting the longest.
+    ///
+    /// If no task is waiting, a permit is stored instead, for the next
+    /// [`notified`](Notify::notified) future to consume.
+    pub fn notify_one(&self) {
+        let waker = self.state.lock().notify_one();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wakes all the tasks waiting on this `Notify`.
+    ///
+    /// Only the [`notified`](Notify::notified) futures which already exist
+    /// are woken, and no permit is stored for futures created later.
+    pub fn notify_waiters(&self) {
+        let wakers: Vec<Waker> = {
+            let mut state = self.state.lock();
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            state
+                .waiters
+                .iter_mut()
+                .filter(|waiter| waiter.notified.is_none())
+                .filter_map(|waiter| {
+                    waiter.notified = Some(NotifiedBy::All);
+                    waiter.waker.take()
+                })
+                .collect()
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl State {
+    // Hands a notification to the oldest waiter not notified yet, returning
+    // its waker, or stores a permit if there is none.
+    fn notify_one(&mut self) -> Option<Waker> {
+        match self.waiters.iter_mut().find(|waiter| waiter.notified.is_none()) {
+            Some(waiter) => {
+                waiter.notified = Some(NotifiedBy::One);
+                waiter.waker.take()
+            }
+            None => {
+                self.permit = true;
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, key: usize) -> Option<Waiter> {
+        let index = self.waiters.iter().position(|waiter| waiter.key == key)?;
+        Some(self.waiters.remove(index))
+    }
+}
+
+// Sentinel for when no slot in the waiter list is used.
+const WAIT_KEY_NONE: usize = usize::max_value();
+
+/// A future which completes once a [`Notify`] is notified.
+///
+/// Created by [`Notify::notified`]. If the future is dropped after being
+/// picked by [`Notify::notify_one`], but before completing, the notification
+/// is passed on to the next waiting task, or stored as a permit.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'a> {
+    // `None` once the future completed.
+    notify: Option<&'a Notify>,
+    generation: usize,
+    wait_key: usize,
+}
+
+impl fmt::Debug for Notified<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notified")
+            .field("notify", &self.notify)
+            .field(
+                "wait_key",
+                &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+            )
+            .finish()
+    }
+}
+
+impl FusedFuture for Notified<'_> {
+    fn is_terminated(&self) -> bool {
+        self.notify.is_none()
+    }
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let notify = self.notify.expect("polled Notified after completion");
+        let mut state = notify.state.lock();
+
+        if self.wait_key == WAIT_KEY_NONE {
+            if notify.generation.load(Ordering::SeqCst) != self.generation {
+                drop(state);
+                self.notify = None;
+                return Poll::Ready(());
+            }
+            if state.permit {
+                state.permit = false;
+                drop(state);
+                self.notify = None;
+                return Poll::Ready(());
+            }
+            let key = state.next_key;
+            state.next_key = state.next_key.wrapping_add(1);
+            state.waiters.push(Waiter { key, notified: None, waker: Some(cx.waker().clone()) });
+            self.wait_key = key;
+            return Poll::Pending;
+        }
+
+        let wait_key = self.wait_key;
+        let waiter = state
+            .waiters
+            .iter_mut()
+            .find(|waiter| waiter.key == wait_key)
+            .expect("waiter missing from Notify");
+        if waiter.notified.is_none() {
+            match &waiter.waker {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => waiter.waker = Some(cx.waker().clone()),
+            }
+            return Poll::Pending;
+        }
+
+        state.remove(wait_key);
+        drop(state);
+        self.wait_key = WAIT_KEY_NONE;
+        self.notify = None;
+        Poll::Ready(())
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        if self.wait_key == WAIT_KEY_NONE {
+            return;
+        }
+        if let Some(notify) = self.notify {
+            let waker = {
+                let mut state = notify.state.lock();
+                match state.remove(self.wait_key) {
+                    // This future was picked by `notify_one`, but it will
+                    // never complete, so the notification goes to someone
+                    // else.
+                    Some(Waiter { notified: Some(NotifiedBy::One), .. }) => state.notify_one(),
+                    _ => None,
+                }
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// A minimal lock, as `std::sync::Mutex` is not available without `std`. It is
+// only held for short operations on the waiter list, and never while waking
+// tasks.
+struct Spinlock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// `Spinlock` only hands out access to `value` to one thread at a time.
+unsafe impl<T: Send> Send for Spinlock<T> {}
+unsafe impl<T: Send> Sync for Spinlock<T> {}
+
+impl<T> Spinlock<T> {
+    fn new(value: T) -> Self {
+        Self { locked: AtomicBool::new(false), value: UnsafeCell::new(value) }
+    }
+
+    fn lock(&self) -> SpinlockGuard<'_, T> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                // `core::hint::spin_loop` needs a newer compiler than we support.
+                #[allow(deprecated)]
+                core::sync::atomic::spin_loop_hint();
+            }
+        }
+        SpinlockGuard { lock: self }
+    }
+}
+
+struct SpinlockGuard<'a, T> {
+    lock: &'a Spinlock<T>,
+}
+
+impl<T> Deref for SpinlockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for SpinlockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for SpinlockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
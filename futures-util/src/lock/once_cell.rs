@@ -0,0 +1,430 @@
+use super::mutex::{Mutex, MutexGuard, MutexLockFuture};
+use core::convert::Infallible;
+use futures_core::future::{FusedFuture, Future, TryFuture};
+use futures_core::ready;
+use futures_core::task::{Context, Poll};
+use pin_project_lite::pin_project;
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A futures-aware cell which can be written to only once, by an
+/// asynchronous initializer.
+///
+/// When several tasks call [`get_or_init`](OnceCell::get_or_init) or
+/// [`get_or_try_init`](OnceCell::get_or_try_init) on an empty cell, only one
+/// initializer runs at a time, and the other tasks wait for it. If it fails,
+/// or its future is dropped before completing, the next waiting task runs
+/// its own initializer instead.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::lock::OnceCell;
+///
+/// let cell = OnceCell::new();
+/// assert_eq!(cell.get(), None);
+///
+/// let value = cell.get_or_init(|| async { 92 }).await;
+/// assert_eq!(*value, 92);
+///
+/// // The cell is already initialized, so this initializer is not run.
+/// let value = cell.get_or_init(|| async { unreachable!() }).await;
+/// assert_eq!(*value, 92);
+/// # });
+/// ```
+pub struct OnceCell<T> {
+    // Set, with the value written, by the initializer holding `init_lock`.
+    is_initialized: AtomicBool,
+    value: UnsafeCell<Option<T>>,
+    init_lock: Mutex<()>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnceCell").field("value", &self.get()).finish()
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for OnceCell<T> {
+    fn from(value: T) -> Self {
+        Self::with_value(Some(value))
+    }
+}
+
+impl<T> OnceCell<T> {
+    /// Creates a new, empty cell.
+    pub fn new() -> Self {
+        Self::with_value(None)
+    }
+
+    fn with_value(value: Option<T>) -> Self {
+        Self {
+            is_initialized: AtomicBool::new(value.is_some()),
+            value: UnsafeCell::new(value),
+            // Tasks waiting to initialize the cell retry in the order they
+            // started waiting.
+            init_lock: Mutex::new_fair_untracked(()),
+        }
+    }
+
+    /// Returns whether the cell has been initialized.
+    pub fn is_initialized(&self) -> bool {
+        self.is_initialized.load(Ordering::Acquire)
+    }
+
+    /// Returns a reference to the value of the cell, or `None` if it has not
+    /// been initialized yet.
+    pub fn get(&self) -> Option<&T> {
+        if self.is_initialized() {
+            // The value is never written again once initialized.
+            unsafe { (*self.value.get()).as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the value of the cell, or `None` if it
+    /// has not been initialized yet.
+    ///
+    /// Since this call borrows the cell mutably, no initializer can be
+    /// running.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        unsafe { (*self.value.get()).as_mut() }
+    }
+
+    /// Initializes the cell with `value`.
+    ///
+    /// This fails, giving `value` back, if the cell is already initialized
+    /// or an initializer is running.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        match self.init_lock.try_lock() {
+            Some(_guard) if !self.is_initialized() => {
+                unsafe { self.store(value) };
+                Ok(())
+            }
+            _ => Err(value),
+        }
+    }
+
+    /// Returns the value of the cell, initializing it with the future
+    /// returned by `init` if it is empty.
+    ///
+    /// The returned future waits for any initializer already running. If that
+    /// initializer does not complete, `init` is called, and its future
+    /// awaited by the returned future. `init` is not called if the cell gets
+    /// initialized in the meantime.
+    pub fn get_or_init<F, Fut>(&self, init: F) -> GetOrInit<'_, T, F, Fut>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        GetOrInit {
+            cell: self,
+            init: Some(init),
+            state: InitState::Locking { lock: self.init_lock.lock() },
+        }
+    }
+
+    /// Returns the value of the cell, initializing it with the fallible
+    /// future returned by `init` if it is empty.
+    ///
+    /// This works like [`get_or_init`](OnceCell::get_or_init), except that
+    /// if the future returned by `init` fails, the cell stays empty, the next
+    /// waiting task gets to run its initializer, and the error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::OnceCell;
+    ///
+    /// let cell = OnceCell::new();
+    ///
+    /// let result = cell.get_or_try_init(|| async { Err(()) }).await;
+    /// assert_eq!(result, Err(()));
+    /// assert!(!cell.is_initialized());
+    ///
+    /// let result = cell.get_or_try_init(|| async { Ok::<_, ()>(1) }).await;
+    /// assert_eq!(result, Ok(&1));
+    /// # });
+    /// ```
+    pub fn get_or_try_init<F, Fut>(&self, init: F) -> GetOrTryInit<'_, T, F, Fut>
+    where
+        F: FnOnce() -> Fut,
+        Fut: TryFuture<Ok = T>,
+    {
+        GetOrTryInit {
+            cell: self,
+            init: Some(init),
+            state: InitState::Locking { lock: self.init_lock.lock() },
+        }
+    }
+
+    /// Takes the value out of the cell, leaving it empty.
+    pub fn take(&mut self) -> Option<T> {
+        *self.is_initialized.get_mut() = false;
+        unsafe { (*self.value.get()).take() }
+    }
+
+    /// Consumes the cell, returning its value if it was initialized.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    // Must be called with `init_lock` held and the cell empty.
+    unsafe fn store(&self, value: T) {
+        *self.value.get() = Some(value);
+        self.is_initialized.store(true, Ordering::Release);
+    }
+
+    fn poll_init<'a, F, Fut, P, E>(
+        &'a self,
+        mut state: Pin<&mut InitState<'a, Fut>>,
+        init: &mut Option<F>,
+        poll: P,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<&'a T, E>>
+    where
+        F: FnOnce() -> Fut,
+        P: Fn(Pin<&mut Fut>, &mut Context<'_>) -> Poll<Result<T, E>>,
+    {
+        loop {
+            match state.as_mut().project() {
+                InitStateProj::Locking { lock } => {
+                    if let Some(value) = self.get() {
+                        state.set(InitState::Done);
+                        return Poll::Ready(Ok(value));
+                    }
+                    let guard = ready!(Pin::new(lock).poll(cx));
+                    // Another initializer may have succeeded while we waited.
+                    if let Some(value) = self.get() {
+                        state.set(InitState::Done);
+                        return Poll::Ready(Ok(value));
+                    }
+                    let init = init.take().expect("polled OnceCell future after completion");
+                    state.set(InitState::Running { guard, future: init() });
+                }
+                InitStateProj::Running { future, .. } => {
+                    let result = ready!(poll(future, cx));
+                    // Setting the state releases the lock, letting the next
+                    // waiter in.
+                    return Poll::Ready(match result {
+                        Ok(value) => {
+                            unsafe { self.store(value) };
+                            state.set(InitState::Done);
+                            Ok(self.get().unwrap())
+                        }
+                        Err(e) => {
+                            state.set(InitState::Done);
+                            Err(e)
+                        }
+                    });
+                }
+                InitStateProj::Done => panic!("polled OnceCell future after completion"),
+            }
+        }
+    }
+}
+
+// The value is only written once, with `init_lock` held, before any `&T` is
+// handed out. Sharing the cell lets any thread initialize it, and then
+// access the value.
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+pin_project! {
+    #[project = InitStateProj]
+    enum InitState<'a, Fut> {
+        Locking { lock: MutexLockFuture<'a, ()> },
+        Running { guard: MutexGuard<'a, ()>, #[pin] future: Fut },
+        Done,
+    }
+}
+
+impl<Fut> InitState<'_, Fut> {
+    fn name(&self) -> &'static str {
+        match self {
+            InitState::Locking { .. } => "Locking",
+            InitState::Running { .. } => "Running",
+            InitState::Done => "Done",
+        }
+    }
+}
+
+pin_project! {
+    /// A future which resolves to the value of a [`OnceCell`](OnceCell),
+    /// initializing it if needed.
+    ///
+    /// This is created by the [`get_or_init`](OnceCell::get_or_init) method.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct GetOrInit<'a, T, F, Fut> {
+        cell: &'a OnceCell<T>,
+        init: Option<F>,
+        #[pin]
+        state: InitState<'a, Fut>,
+    }
+}
+
+impl<T: fmt::Debug, F, Fut> fmt::Debug for GetOrInit<'_, T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GetOrInit")
+            .field("cell", &self.cell)
+            .field("state", &self.state.name())
+            .finish()
+    }
+}
+
+impl<T, F, Fut> FusedFuture for GetOrInit<'_, T, F, Fut>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    fn is_terminated(&self) -> bool {
+        match self.state {
+            InitState::Done => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a, T, F, Fut> Future for GetOrInit<'a, T, F, Fut>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    type Output = &'a T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let cell: &'a OnceCell<T> = this.cell;
+        cell.poll_init(
+            this.state,
+            this.init,
+            |future, cx| future.poll(cx).map(Ok::<T, Infallible>),
+            cx,
+        )
+        .map(|result| match result {
+            Ok(value) => value,
+            Err(never) => match never {},
+        })
+    }
+}
+
+pin_project! {
+    /// A future which resolves to the value of a [`OnceCell`](OnceCell),
+    /// trying to initialize it if needed.
+    ///
+    /// This is created by the [`get_or_try_init`](OnceCell::get_or_try_init)
+    /// method.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct GetOrTryInit<'a, T, F, Fut> {
+        cell: &'a OnceCell<T>,
+        init: Option<F>,
+        #[pin]
+        state: InitState<'a, Fut>,
+    }
+}
+
+impl<T: fmt::Debug, F, Fut> fmt::Debug for GetOrTryInit<'_, T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GetOrTryInit")
+            .field("cell", &self.cell)
+            .field("state", &self.state.name())
+            .finish()
+    }
+}
+
+impl<T, F, Fut> FusedFuture for GetOrTryInit<'_, T, F, Fut>
+where
+    F: FnOnce() -> Fut,
+    Fut: TryFuture<Ok = T>,
+{
+    fn is_terminated(&self) -> bool {
+        match self.state {
+            InitState::Done => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a, T, F, Fut> Future for GetOrTryInit<'a, T, F, Fut>
+where
+    F: FnOnce() -> Fut,
+    Fut: TryFuture<Ok = T>,
+{
+    type Output = Result<&'a T, Fut::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let cell: &'a OnceCell<T> = this.cell;
+        cell.poll_init(this.state, this.init, Fut::try_poll, cx)
+    }
+}
+
+/// A value which is initialized asynchronously on first access.
+///
+/// This is a [`OnceCell`](OnceCell) bundled with its initializer. The value
+/// is computed by the first task calling [`force`](AsyncLazy::force), while
+/// other tasks doing so wait for it. If that task's future is dropped before
+/// the value is ready, the initializer is called again by the next one, so it
+/// is an `Fn` rather than an `FnOnce`.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::lock::AsyncLazy;
+///
+/// let config = AsyncLazy::new(|| async { String::from("parsed config") });
+/// assert_eq!(config.get(), None);
+/// assert_eq!(config.force().await, "parsed config");
+/// assert_eq!(config.get().map(String::as_str), Some("parsed config"));
+/// # });
+/// ```
+pub struct AsyncLazy<T, F> {
+    cell: OnceCell<T>,
+    init: F,
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for AsyncLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncLazy").field("cell", &self.cell).finish()
+    }
+}
+
+impl<T, F> AsyncLazy<T, F> {
+    /// Creates a new lazy value, computed by the future returned by `init`.
+    pub fn new(init: F) -> Self {
+        Self { cell: OnceCell::new(), init }
+    }
+
+    /// Returns a reference to the value, or `None` if it has not been
+    /// computed yet.
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+
+    /// Consumes the lazy value, returning it if it was computed.
+    pub fn into_value(self) -> Option<T> {
+        self.cell.into_inner()
+    }
+}
+
+impl<T, F, Fut> AsyncLazy<T, F>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = T>,
+{
+    /// Returns a reference to the value, computing it if needed.
+    pub fn force(&self) -> GetOrInit<'_, T, &F, Fut> {
+        self.cell.get_or_init(&self.init)
+    }
+}
@@ -0,0 +1,941 @@
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+use slab::Slab;
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex as StdMutex;
+use std::{fmt, mem};
+
+/// A futures-aware read-write lock.
+///
+/// This lock allows any number of readers or at most one writer to access
+/// the data at any point in time. In addition, one reader at a time can hold
+/// an [upgradable read lock](RwLock::upgradable_read), which can later be
+/// upgraded to a write lock without letting another writer in.
+///
+/// # Fairness
+///
+/// This lock prefers writers: once a task waits for the write lock, or to
+/// upgrade a read lock, new readers wait until it is done. Writers can starve
+/// readers this way if they keep the lock busy. Otherwise there are no
+/// guarantees on the order in which tasks acquire the lock.
+pub struct RwLock<T: ?Sized> {
+    state: AtomicUsize,
+    waiters: StdMutex<Waiters>,
+    value: UnsafeCell<T>,
+}
+
+impl<T: ?Sized> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.load(Ordering::SeqCst);
+        f.debug_struct("RwLock")
+            .field("readers", &(state / ONE_READER))
+            .field("is_write_locked", &((state & WRITE_LOCKED) != 0))
+            .field("is_upgradable_locked", &((state & UPGRADABLE_LOCKED) != 0))
+            .field("has_waiters", &((state & HAS_WAITERS) != 0))
+            .finish()
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+struct Waiters {
+    slab: Slab<Waiter>,
+
+    // Number of waiters of kind `Write` or `Upgrade` in `slab`.
+    num_writers: usize,
+}
+
+struct Waiter {
+    kind: Kind,
+
+    // `None` once the waiter has been woken up.
+    waker: Option<Waker>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Read,
+    UpgradableRead,
+    Write,
+    Upgrade,
+}
+
+impl Kind {
+    fn is_writer(self) -> bool {
+        self == Self::Write || self == Self::Upgrade
+    }
+}
+
+impl Waiter {
+    fn register(&mut self, waker: &Waker) {
+        match &self.waker {
+            Some(w) if waker.will_wake(w) => {}
+            _ => self.waker = Some(waker.clone()),
+        }
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+const WRITE_LOCKED: usize = 1 << 0;
+const UPGRADABLE_LOCKED: usize = 1 << 1;
+// Set while a task waits for the write lock, or to upgrade its read lock.
+// New readers wait while it is set.
+const WRITER_WAITING: usize = 1 << 2;
+const HAS_WAITERS: usize = 1 << 3;
+// The number of readers is stored in the remaining bits.
+const ONE_READER: usize = 1 << 4;
+const READERS: usize = !(ONE_READER - 1);
+
+impl<T> RwLock<T> {
+    /// Creates a new futures-aware read-write lock.
+    pub fn new(t: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            waiters: StdMutex::new(Waiters { slab: Slab::new(), num_writers: 0 }),
+            value: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consumes this lock, returning the underlying data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::lock::RwLock;
+    ///
+    /// let lock = RwLock::new(0);
+    /// assert_eq!(lock.into_inner(), 0);
+    /// ```
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Attempt to acquire a read lock immediately.
+    ///
+    /// If the lock is held for writing, or a task waits to write, this will
+    /// return `None`.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & (WRITE_LOCKED | WRITER_WAITING) != 0 {
+                return None;
+            }
+            assert!(state & READERS != READERS, "too many readers of an RwLock");
+            match self.state.compare_exchange_weak(
+                state,
+                state + ONE_READER,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(RwLockReadGuard { lock: self }),
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Attempt to acquire an upgradable read lock immediately.
+    ///
+    /// If the lock is held for writing or by another upgradable reader, or a
+    /// task waits to write, this will return `None`.
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & (WRITE_LOCKED | UPGRADABLE_LOCKED | WRITER_WAITING) != 0 {
+                return None;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state | UPGRADABLE_LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(RwLockUpgradableReadGuard { lock: self }),
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Attempt to acquire the write lock immediately.
+    ///
+    /// If the lock is currently held, this will return `None`.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & (WRITE_LOCKED | UPGRADABLE_LOCKED | READERS) != 0 {
+                return None;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state | WRITE_LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(RwLockWriteGuard { lock: self }),
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Acquire a read lock asynchronously.
+    ///
+    /// This method returns a future that will resolve once a read lock has
+    /// been successfully acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::RwLock;
+    ///
+    /// let lock = RwLock::new(5);
+    /// let r1 = lock.read().await;
+    /// let r2 = lock.read().await;
+    /// assert_eq!(*r1 + *r2, 10);
+    /// # });
+    /// ```
+    pub fn read(&self) -> RwLockReadFuture<'_, T> {
+        RwLockReadFuture { lock: Some(self), wait_key: WAIT_KEY_NONE }
+    }
+
+    /// Acquire an upgradable read lock asynchronously.
+    ///
+    /// An upgradable read lock can be held along with other read locks, but
+    /// not with another upgradable read lock or the write lock. It can be
+    /// [upgraded](RwLockUpgradableReadGuard::upgrade) to the write lock
+    /// without releasing it first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::{RwLock, RwLockUpgradableReadGuard};
+    ///
+    /// let lock = RwLock::new(1);
+    /// let reader = lock.upgradable_read().await;
+    /// if *reader == 1 {
+    ///     let mut writer = RwLockUpgradableReadGuard::upgrade(reader).await;
+    ///     *writer = 2;
+    /// }
+    /// assert_eq!(*lock.read().await, 2);
+    /// # });
+    /// ```
+    pub fn upgradable_read(&self) -> RwLockUpgradableReadFuture<'_, T> {
+        RwLockUpgradableReadFuture { lock: Some(self), wait_key: WAIT_KEY_NONE }
+    }
+
+    /// Acquire the write lock asynchronously.
+    ///
+    /// This method returns a future that will resolve once the write lock has
+    /// been successfully acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::RwLock;
+    ///
+    /// let lock = RwLock::new(5);
+    /// *lock.write().await += 1;
+    /// assert_eq!(*lock.read().await, 6);
+    /// # });
+    /// ```
+    pub fn write(&self) -> RwLockWriteFuture<'_, T> {
+        RwLockWriteFuture { lock: Some(self), wait_key: WAIT_KEY_NONE }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `RwLock` mutably, no actual locking needs
+    /// to take place -- the mutable borrow statically guarantees no locks
+    /// exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::RwLock;
+    ///
+    /// let mut lock = RwLock::new(0);
+    /// *lock.get_mut() = 10;
+    /// assert_eq!(*lock.read().await, 10);
+    /// # });
+    /// ```
+    pub fn get_mut(&mut self) -> &mut T {
+        // We know statically that there are no other references to `self`, so
+        // there's no need to lock.
+        unsafe { &mut *self.value.get() }
+    }
+
+    // Attempts to acquire the lock for `kind`, returning whether it did.
+    fn try_acquire(&self, kind: Kind) -> bool {
+        match kind {
+            Kind::Read => self.try_read().map(mem::forget).is_some(),
+            Kind::UpgradableRead => self.try_upgradable_read().map(mem::forget).is_some(),
+            Kind::Write => self.try_write().map(mem::forget).is_some(),
+            Kind::Upgrade => self.try_upgrade(),
+        }
+    }
+
+    // Turns the upgradable read lock held by the caller into the write lock
+    // if there are no readers left.
+    fn try_upgrade(&self) -> bool {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & READERS != 0 {
+                return false;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                (state & !UPGRADABLE_LOCKED) | WRITE_LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    // Polls for the lock for `kind`, registering `cx` in the waiters until
+    // it is acquired.
+    fn poll_acquire(&self, kind: Kind, wait_key: &mut usize, cx: &mut Context<'_>) -> Poll<()> {
+        if self.try_acquire(kind) {
+            self.remove_waker(kind, *wait_key, false);
+            *wait_key = WAIT_KEY_NONE;
+            return Poll::Ready(());
+        }
+
+        {
+            let mut waiters = self.waiters.lock().unwrap();
+            if *wait_key == WAIT_KEY_NONE {
+                *wait_key = waiters.slab.insert(Waiter { kind, waker: Some(cx.waker().clone()) });
+                if waiters.slab.len() == 1 {
+                    self.state.fetch_or(HAS_WAITERS, Ordering::SeqCst);
+                }
+                if kind.is_writer() {
+                    waiters.num_writers += 1;
+                    if waiters.num_writers == 1 {
+                        self.state.fetch_or(WRITER_WAITING, Ordering::SeqCst);
+                    }
+                }
+            } else {
+                waiters.slab[*wait_key].register(cx.waker());
+            }
+        }
+
+        // Ensure that we haven't raced an unlock by attempting to acquire the
+        // lock again.
+        if self.try_acquire(kind) {
+            self.remove_waker(kind, *wait_key, false);
+            *wait_key = WAIT_KEY_NONE;
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+
+    fn remove_waker(&self, kind: Kind, wait_key: usize, wake_another: bool) {
+        if wait_key != WAIT_KEY_NONE {
+            let mut waiters = self.waiters.lock().unwrap();
+            waiters.slab.remove(wait_key);
+            if kind.is_writer() {
+                waiters.num_writers -= 1;
+                if waiters.num_writers == 0 {
+                    self.state.fetch_and(!WRITER_WAITING, Ordering::SeqCst);
+                }
+            }
+            if waiters.slab.is_empty() {
+                self.state.fetch_and(!HAS_WAITERS, Ordering::SeqCst);
+            } else if wake_another {
+                // We may have been awoken, but then dropped before we could
+                // acquire the lock, or been the writer holding back the
+                // readers. Wake up whoever can go now.
+                self.wake_waiters(&mut waiters);
+            }
+        }
+    }
+
+    // Wakes up the waiters which may be able to acquire the lock in its
+    // current state.
+    fn wake_waiters(&self, waiters: &mut Waiters) {
+        let state = self.state.load(Ordering::SeqCst);
+        if state & WRITE_LOCKED != 0 {
+            return;
+        }
+
+        if state & READERS == 0 {
+            // The upgradable reader already holds part of the lock, so it
+            // goes before any other writer.
+            let upgrade = waiters.slab.iter_mut().find(|(_, w)| w.kind == Kind::Upgrade);
+            if let Some((_, waiter)) = upgrade {
+                waiter.wake();
+                return;
+            }
+        }
+
+        if waiters.num_writers != 0 {
+            // New readers are held back for the writers.
+            if state & (READERS | UPGRADABLE_LOCKED) == 0 {
+                let writer = waiters.slab.iter_mut().find(|(_, w)| w.kind == Kind::Write);
+                if let Some((_, waiter)) = writer {
+                    waiter.wake();
+                }
+            }
+            return;
+        }
+
+        let mut wake_upgradable = state & UPGRADABLE_LOCKED == 0;
+        for (_, waiter) in waiters.slab.iter_mut() {
+            match waiter.kind {
+                Kind::Read => waiter.wake(),
+                Kind::UpgradableRead if wake_upgradable => {
+                    waiter.wake();
+                    wake_upgradable = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Called by the guards when they are dropped, after they have changed
+    // `state`.
+    fn unlocked(&self, old_state: usize) {
+        if (old_state & HAS_WAITERS) != 0 {
+            let mut waiters = self.waiters.lock().unwrap();
+            self.wake_waiters(&mut waiters);
+        }
+    }
+
+    fn unlock_read(&self) {
+        let old_state = self.state.fetch_sub(ONE_READER, Ordering::Release);
+        // Other readers are never waiting for this one.
+        if old_state & READERS == ONE_READER {
+            self.unlocked(old_state);
+        }
+    }
+
+    fn unlock_upgradable_read(&self) {
+        let old_state = self.state.fetch_and(!UPGRADABLE_LOCKED, Ordering::Release);
+        self.unlocked(old_state);
+    }
+
+    fn unlock_write(&self) {
+        let old_state = self.state.fetch_and(!WRITE_LOCKED, Ordering::Release);
+        self.unlocked(old_state);
+    }
+}
+
+// Sentinel for when no slot in the `Slab` has been dedicated to this object.
+const WAIT_KEY_NONE: usize = usize::max_value();
+
+macro_rules! lock_future {
+    ($(#[$attr:meta])* $name:ident, $kind:expr, $guard:ident) => {
+        $(#[$attr])*
+        pub struct $name<'a, T: ?Sized> {
+            // `None` indicates that the lock was successfully acquired.
+            lock: Option<&'a RwLock<T>>,
+            wait_key: usize,
+        }
+
+        impl<T: ?Sized> fmt::Debug for $name<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("was_acquired", &self.lock.is_none())
+                    .field("lock", &self.lock)
+                    .field(
+                        "wait_key",
+                        &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+                    )
+                    .finish()
+            }
+        }
+
+        impl<T: ?Sized> FusedFuture for $name<'_, T> {
+            fn is_terminated(&self) -> bool {
+                self.lock.is_none()
+            }
+        }
+
+        impl<'a, T: ?Sized> Future for $name<'a, T> {
+            type Output = $guard<'a, T>;
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                let lock = self.lock.expect(concat!("polled ", stringify!($name), " after completion"));
+
+                match lock.poll_acquire($kind, &mut self.wait_key, cx) {
+                    Poll::Ready(()) => {
+                        self.lock = None;
+                        Poll::Ready($guard { lock })
+                    }
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+        }
+
+        impl<T: ?Sized> Drop for $name<'_, T> {
+            fn drop(&mut self) {
+                if let Some(lock) = self.lock {
+                    // This future was dropped before it acquired the lock.
+                    //
+                    // Remove ourselves from the map, waking up other waiters
+                    // if we had been awoken to acquire the lock.
+                    lock.remove_waker($kind, self.wait_key, true);
+                }
+            }
+        }
+
+        // It's safe to switch which thread the acquire is being attempted on
+        // so long as `T` can be accessed on that thread.
+        unsafe impl<T: ?Sized + Send + Sync> Send for $name<'_, T> {}
+        // doesn't have any interesting `&self` methods (only Debug)
+        unsafe impl<T: ?Sized> Sync for $name<'_, T> {}
+    };
+}
+
+lock_future! {
+    /// A future which resolves when a read lock has been successfully
+    /// acquired.
+    RwLockReadFuture, Kind::Read, RwLockReadGuard
+}
+
+lock_future! {
+    /// A future which resolves when an upgradable read lock has been
+    /// successfully acquired.
+    RwLockUpgradableReadFuture, Kind::UpgradableRead, RwLockUpgradableReadGuard
+}
+
+lock_future! {
+    /// A future which resolves when the write lock has been successfully
+    /// acquired.
+    RwLockWriteFuture, Kind::Write, RwLockWriteGuard
+}
+
+/// A future which resolves when an upgradable read lock has been upgraded to
+/// the write lock.
+///
+/// This is created by the [`upgrade`](RwLockUpgradableReadGuard::upgrade)
+/// method.
+pub struct RwLockUpgradeFuture<'a, T: ?Sized> {
+    // `None` indicates that the lock was successfully upgraded.
+    guard: Option<RwLockUpgradableReadGuard<'a, T>>,
+    wait_key: usize,
+}
+
+impl<T: ?Sized> fmt::Debug for RwLockUpgradeFuture<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RwLockUpgradeFuture")
+            .field("was_upgraded", &self.guard.is_none())
+            .field("lock", &self.guard.as_ref().map(|guard| guard.lock))
+            .field(
+                "wait_key",
+                &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+            )
+            .finish()
+    }
+}
+
+impl<T: ?Sized> FusedFuture for RwLockUpgradeFuture<'_, T> {
+    fn is_terminated(&self) -> bool {
+        self.guard.is_none()
+    }
+}
+
+impl<'a, T: ?Sized> Future for RwLockUpgradeFuture<'a, T> {
+    type Output = RwLockWriteGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lock = self.guard.as_ref().expect("polled RwLockUpgradeFuture after completion").lock;
+
+        match lock.poll_acquire(Kind::Upgrade, &mut self.wait_key, cx) {
+            Poll::Ready(()) => {
+                // The upgradable read lock has become the write lock.
+                mem::forget(self.guard.take());
+                Poll::Ready(RwLockWriteGuard { lock })
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockUpgradeFuture<'_, T> {
+    fn drop(&mut self) {
+        if let Some(guard) = &self.guard {
+            // This future was dropped before it upgraded the lock. The
+            // upgradable read lock is released along with `guard`.
+            guard.lock.remove_waker(Kind::Upgrade, self.wait_key, true);
+        }
+    }
+}
+
+/// An RAII guard returned by the `read` and `try_read` methods.
+/// When this structure is dropped (falls out of scope), the read lock will be
+/// released.
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T: ?Sized> RwLockReadGuard<'a, T> {
+    /// Returns a read-locked view over a portion of the locked data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::{RwLock, RwLockReadGuard};
+    ///
+    /// let data = RwLock::new(Some("value".to_string()));
+    /// {
+    ///     let locked_str = RwLockReadGuard::map(data.read().await, |opt| opt.as_ref().unwrap());
+    ///     assert_eq!(&*locked_str, "value");
+    /// }
+    /// # });
+    /// ```
+    #[inline]
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> MappedRwLockReadGuard<'a, T, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        let lock = this.lock;
+        let value = f(unsafe { &*this.lock.value.get() });
+        // Don't run the `drop` method for RwLockReadGuard. The ownership of the
+        // underlying locked state is being moved to the returned
+        // MappedRwLockReadGuard.
+        mem::forget(this);
+        MappedRwLockReadGuard { lock, value, _marker: PhantomData }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RwLockReadGuard")
+            .field("value", &&**self)
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_read()
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+/// An RAII guard returned by the `upgradable_read` and `try_upgradable_read`
+/// methods. When this structure is dropped (falls out of scope), the
+/// upgradable read lock will be released.
+pub struct RwLockUpgradableReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T: ?Sized> RwLockUpgradableReadGuard<'a, T> {
+    /// Upgrades to the write lock asynchronously.
+    ///
+    /// This method returns a future that will resolve once the readers still
+    /// holding the lock have released it. New readers are held back in the
+    /// meantime.
+    pub fn upgrade(this: Self) -> RwLockUpgradeFuture<'a, T> {
+        RwLockUpgradeFuture { guard: Some(this), wait_key: WAIT_KEY_NONE }
+    }
+
+    /// Attempt to upgrade to the write lock immediately.
+    ///
+    /// If other readers still hold the lock, the guard is returned back.
+    pub fn try_upgrade(this: Self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        if this.lock.try_upgrade() {
+            let lock = this.lock;
+            mem::forget(this);
+            Ok(RwLockWriteGuard { lock })
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Turns this guard into a plain read lock, allowing another task to
+    /// acquire the upgradable read lock.
+    pub fn downgrade(this: Self) -> RwLockReadGuard<'a, T> {
+        let lock = this.lock;
+        mem::forget(this);
+        // Clears `UPGRADABLE_LOCKED` and adds a reader at once.
+        let old_state =
+            lock.state.fetch_add(ONE_READER.wrapping_sub(UPGRADABLE_LOCKED), Ordering::Release);
+        lock.unlocked(old_state);
+        RwLockReadGuard { lock }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RwLockUpgradableReadGuard")
+            .field("value", &&**self)
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_upgradable_read()
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+/// An RAII guard returned by the `write` and `try_write` methods.
+/// When this structure is dropped (falls out of scope), the write lock will be
+/// released.
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T: ?Sized> RwLockWriteGuard<'a, T> {
+    /// Returns a write-locked view over a portion of the locked data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use futures::lock::{RwLock, RwLockWriteGuard};
+    ///
+    /// let data = RwLock::new(Some("value".to_string()));
+    /// {
+    ///     let mut locked_str = RwLockWriteGuard::map(data.write().await, |opt| opt.as_mut().unwrap());
+    ///     locked_str.push_str("s");
+    /// }
+    /// assert_eq!(*data.read().await, Some("values".to_string()));
+    /// # });
+    /// ```
+    #[inline]
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> MappedRwLockWriteGuard<'a, T, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let lock = this.lock;
+        let value = f(unsafe { &mut *this.lock.value.get() });
+        // Don't run the `drop` method for RwLockWriteGuard. The ownership of
+        // the underlying locked state is being moved to the returned
+        // MappedRwLockWriteGuard.
+        mem::forget(this);
+        MappedRwLockWriteGuard { lock, value, _marker: PhantomData }
+    }
+
+    /// Turns the write lock into a read lock, without letting another writer
+    /// in first.
+    ///
+    /// Other readers are let in too, unless a writer is waiting for the lock:
+    /// as always, writers go first, so readers keep waiting until the writer
+    /// has had the lock, which it gets once the returned guard is dropped.
+    pub fn downgrade(this: Self) -> RwLockReadGuard<'a, T> {
+        let lock = this.lock;
+        mem::forget(this);
+        // Clears `WRITE_LOCKED` and adds a reader at once.
+        let old_state =
+            lock.state.fetch_add(ONE_READER.wrapping_sub(WRITE_LOCKED), Ordering::Release);
+        lock.unlocked(old_state);
+        RwLockReadGuard { lock }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RwLockWriteGuard")
+            .field("value", &&**self)
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_write()
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+/// An RAII guard returned by the `RwLockReadGuard::map` and
+/// `MappedRwLockReadGuard::map` methods. When this structure is dropped
+/// (falls out of scope), the read lock will be released.
+pub struct MappedRwLockReadGuard<'a, T: ?Sized, U: ?Sized> {
+    lock: &'a RwLock<T>,
+    value: *const U,
+    _marker: PhantomData<&'a U>,
+}
+
+impl<'a, T: ?Sized, U: ?Sized> MappedRwLockReadGuard<'a, T, U> {
+    /// Returns a read-locked view over a portion of the locked data.
+    #[inline]
+    pub fn map<V: ?Sized, F>(this: Self, f: F) -> MappedRwLockReadGuard<'a, T, V>
+    where
+        F: FnOnce(&U) -> &V,
+    {
+        let lock = this.lock;
+        let value = f(unsafe { &*this.value });
+        // Don't run the `drop` method for MappedRwLockReadGuard. The ownership
+        // of the underlying locked state is being moved to the returned
+        // MappedRwLockReadGuard.
+        mem::forget(this);
+        MappedRwLockReadGuard { lock, value, _marker: PhantomData }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized + fmt::Debug> fmt::Debug for MappedRwLockReadGuard<'_, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedRwLockReadGuard")
+            .field("value", &&**self)
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Drop for MappedRwLockReadGuard<'_, T, U> {
+    fn drop(&mut self) {
+        self.lock.unlock_read()
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Deref for MappedRwLockReadGuard<'_, T, U> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        unsafe { &*self.value }
+    }
+}
+
+/// An RAII guard returned by the `RwLockWriteGuard::map` and
+/// `MappedRwLockWriteGuard::map` methods. When this structure is dropped
+/// (falls out of scope), the write lock will be released.
+pub struct MappedRwLockWriteGuard<'a, T: ?Sized, U: ?Sized> {
+    lock: &'a RwLock<T>,
+    value: *mut U,
+    _marker: PhantomData<&'a mut U>,
+}
+
+impl<'a, T: ?Sized, U: ?Sized> MappedRwLockWriteGuard<'a, T, U> {
+    /// Returns a write-locked view over a portion of the locked data.
+    #[inline]
+    pub fn map<V: ?Sized, F>(this: Self, f: F) -> MappedRwLockWriteGuard<'a, T, V>
+    where
+        F: FnOnce(&mut U) -> &mut V,
+    {
+        let lock = this.lock;
+        let value = f(unsafe { &mut *this.value });
+        // Don't run the `drop` method for MappedRwLockWriteGuard. The
+        // ownership of the underlying locked state is being moved to the
+        // returned MappedRwLockWriteGuard.
+        mem::forget(this);
+        MappedRwLockWriteGuard { lock, value, _marker: PhantomData }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized + fmt::Debug> fmt::Debug for MappedRwLockWriteGuard<'_, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedRwLockWriteGuard")
+            .field("value", &&**self)
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Drop for MappedRwLockWriteGuard<'_, T, U> {
+    fn drop(&mut self) {
+        self.lock.unlock_write()
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Deref for MappedRwLockWriteGuard<'_, T, U> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        unsafe { &*self.value }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> DerefMut for MappedRwLockWriteGuard<'_, T, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        unsafe { &mut *self.value }
+    }
+}
+
+// Readers on different threads access the value at the same time, so it
+// needs to be `Sync` as well for the lock to be shared between threads.
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+// It's safe to switch which thread the upgrade is being attempted on so long
+// as `T` can be accessed on that thread.
+unsafe impl<T: ?Sized + Send + Sync> Send for RwLockUpgradeFuture<'_, T> {}
+// doesn't have any interesting `&self` methods (only Debug)
+unsafe impl<T: ?Sized> Sync for RwLockUpgradeFuture<'_, T> {}
+
+// Read guards only give shared access to the value, while write guards also
+// allow moving it out to another thread. So do upgradable read guards, as
+// they can be upgraded on the thread they are sent to.
+unsafe impl<T: ?Sized + Sync> Send for RwLockReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Send + Sync> Send for RwLockUpgradableReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockUpgradableReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Send + Sync> Send for RwLockWriteGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync, U: ?Sized + Sync> Send for MappedRwLockReadGuard<'_, T, U> {}
+unsafe impl<T: ?Sized + Sync, U: ?Sized + Sync> Sync for MappedRwLockReadGuard<'_, T, U> {}
+unsafe impl<T: ?Sized + Send + Sync, U: ?Sized + Send + Sync> Send
+    for MappedRwLockWriteGuard<'_, T, U>
+{
+}
+unsafe impl<T: ?Sized + Sync, U: ?Sized + Sync> Sync for MappedRwLockWriteGuard<'_, T, U> {}
+
+#[test]
+fn test_rwlock_guard_debug_not_recurse() {
+    let lock = RwLock::new(42);
+    let guard = lock.try_read().unwrap();
+    let _ = format!("{:?}", guard);
+    let guard = RwLockReadGuard::map(guard, |n| n);
+    let _ = format!("{:?}", guard);
+    drop(guard);
+    let guard = lock.try_write().unwrap();
+    let _ = format!("{:?}", guard);
+    let guard = RwLockWriteGuard::map(guard, |n| n);
+    let _ = format!("{:?}", guard);
+}
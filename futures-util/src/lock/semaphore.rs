@@ -0,0 +1,561 @@
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+use slab::Slab;
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+
+/// A futures-aware counting semaphore.
+///
+/// A semaphore holds a number of permits, which tasks acquire to do some
+/// work and give back once done, limiting how many of them do it at the same
+/// time.
+///
+/// # Fairness
+///
+/// Tasks waiting for permits are served in the order they started waiting,
+/// and a task waiting for many permits holds back the tasks which come after
+/// it, even those asking for fewer permits than are available. This way,
+/// requests for many permits cannot be starved by requests for a few.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::lock::Semaphore;
+///
+/// let semaphore = Semaphore::new(3);
+///
+/// let a = semaphore.acquire().await.unwrap();
+/// let b = semaphore.acquire_many(2).await.unwrap();
+/// assert_eq!(semaphore.available_permits(), 0);
+/// assert!(semaphore.try_acquire().is_err());
+///
+/// drop(a);
+/// assert_eq!(semaphore.available_permits(), 1);
+/// drop(b);
+/// assert_eq!(semaphore.available_permits(), 3);
+/// # });
+/// ```
+pub struct Semaphore {
+    state: StdMutex<State>,
+}
+
+struct State {
+    // Number of permits not held by anyone.
+    permits: usize,
+
+    is_closed: bool,
+
+    waiters: Slab<Waiter>,
+
+    // Keys of the waiters in `waiters` which have not acquired their permits
+    // yet, in the order they started waiting.
+    queue: VecDeque<usize>,
+}
+
+struct Waiter {
+    // Number of permits the waiter asks for.
+    permits: usize,
+
+    // `true` once the permits have been handed to the waiter.
+    acquired: bool,
+
+    waker: Option<Waker>,
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Semaphore")
+            .field("permits", &state.permits)
+            .field("is_closed", &state.is_closed)
+            .field("waiters", &state.queue.len())
+            .finish()
+    }
+}
+
+/// The error returned by the futures of [`Semaphore`](Semaphore) when it has
+/// been closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcquireError {
+    _priv: (),
+}
+
+/// The error returned by the `try_acquire` methods of
+/// [`Semaphore`](Semaphore).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryAcquireError {
+    kind: TryAcquireErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TryAcquireErrorKind {
+    Closed,
+    NoPermits,
+}
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "semaphore closed")
+    }
+}
+
+impl std::error::Error for AcquireError {}
+
+impl TryAcquireError {
+    /// Returns `true` if the permits could not be acquired because the
+    /// semaphore was closed.
+    pub fn is_closed(&self) -> bool {
+        self.kind == TryAcquireErrorKind::Closed
+    }
+
+    /// Returns `true` if the permits could not be acquired because there were
+    /// not enough of them available.
+    pub fn is_no_permits(&self) -> bool {
+        self.kind == TryAcquireErrorKind::NoPermits
+    }
+}
+
+impl fmt::Display for TryAcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TryAcquireErrorKind::Closed => write!(f, "semaphore closed"),
+            TryAcquireErrorKind::NoPermits => write!(f, "no permits available"),
+        }
+    }
+}
+
+impl std::error::Error for TryAcquireError {}
+
+impl Semaphore {
+    /// Creates a new semaphore holding `permits` permits.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: StdMutex::new(State {
+                permits,
+                is_closed: false,
+                waiters: Slab::new(),
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().permits
+    }
+
+    /// Adds `n` permits to the semaphore, handing them to the waiting tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of available permits overflows.
+    pub fn add_permits(&self, n: usize) {
+        self.state.lock().unwrap().release(n);
+    }
+
+    /// Closes the semaphore.
+    ///
+    /// Tasks waiting for permits, and all later attempts to acquire permits,
+    /// fail. Permits held when the semaphore is closed can still be used, and
+    /// are given back as usual.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.is_closed = true;
+        let queue = mem::replace(&mut state.queue, VecDeque::new());
+        for key in queue {
+            state.waiters[key].wake();
+        }
+    }
+
+    /// Returns whether the semaphore has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().is_closed
+    }
+
+    /// Acquires a permit asynchronously.
+    ///
+    /// This method returns a future that will resolve once a permit has been
+    /// acquired, or fail if the semaphore is closed.
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    /// Acquires `n` permits asynchronously.
+    ///
+    /// This method returns a future that will resolve once all the permits
+    /// have been acquired at once, or fail if the semaphore is closed.
+    pub fn acquire_many(&self, n: usize) -> Acquire<'_> {
+        Acquire { semaphore: Some(self), permits: n, wait_key: WAIT_KEY_NONE }
+    }
+
+    /// Attempts to acquire a permit immediately.
+    ///
+    /// This fails if the semaphore is closed, has no permit available, or
+    /// tasks are already waiting for permits.
+    pub fn try_acquire(&self) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.try_acquire_many(1)
+    }
+
+    /// Attempts to acquire `n` permits immediately.
+    ///
+    /// This fails if the semaphore is closed, has not enough permits
+    /// available, or tasks are already waiting for permits.
+    pub fn try_acquire_many(&self, n: usize) -> Result<SemaphorePermit<'_>, TryAcquireError> {
+        self.state.lock().unwrap().try_acquire(n)?;
+        Ok(SemaphorePermit { semaphore: self, permits: n })
+    }
+
+    /// Acquires a permit asynchronously, returning a permit which holds a
+    /// reference to the semaphore instead of borrowing it.
+    ///
+    /// The permit can be moved into a spawned task.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::executor::block_on;
+    /// use futures::lock::Semaphore;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let semaphore = Arc::new(Semaphore::new(2));
+    ///
+    /// let threads: Vec<_> = (0..8)
+    ///     .map(|i| {
+    ///         // At most two threads run at a time.
+    ///         let permit = block_on(semaphore.clone().acquire_owned()).unwrap();
+    ///         thread::spawn(move || {
+    ///             let _permit = permit;
+    ///             i * 2
+    ///         })
+    ///     })
+    ///     .collect();
+    /// for (i, t) in threads.into_iter().enumerate() {
+    ///     assert_eq!(t.join().unwrap(), i * 2);
+    /// }
+    /// ```
+    pub fn acquire_owned(self: Arc<Self>) -> AcquireOwned {
+        self.acquire_many_owned(1)
+    }
+
+    /// Acquires `n` permits asynchronously, returning a permit which holds a
+    /// reference to the semaphore instead of borrowing it.
+    pub fn acquire_many_owned(self: Arc<Self>, n: usize) -> AcquireOwned {
+        AcquireOwned { semaphore: Some(self), permits: n, wait_key: WAIT_KEY_NONE }
+    }
+
+    /// Attempts to acquire a permit immediately, returning a permit which
+    /// holds a reference to the semaphore instead of borrowing it.
+    pub fn try_acquire_owned(self: Arc<Self>) -> Result<OwnedSemaphorePermit, TryAcquireError> {
+        self.try_acquire_many_owned(1)
+    }
+
+    /// Attempts to acquire `n` permits immediately, returning a permit which
+    /// holds a reference to the semaphore instead of borrowing it.
+    pub fn try_acquire_many_owned(
+        self: Arc<Self>,
+        n: usize,
+    ) -> Result<OwnedSemaphorePermit, TryAcquireError> {
+        self.state.lock().unwrap().try_acquire(n)?;
+        Ok(OwnedSemaphorePermit { semaphore: self, permits: n })
+    }
+
+    fn poll_acquire(
+        &self,
+        n: usize,
+        wait_key: &mut usize,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), AcquireError>> {
+        let mut state = self.state.lock().unwrap();
+
+        if *wait_key == WAIT_KEY_NONE {
+            match state.try_acquire(n) {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(e) if e.is_closed() => return Poll::Ready(Err(AcquireError { _priv: () })),
+                Err(_) => {}
+            }
+            *wait_key = state.waiters.insert(Waiter {
+                permits: n,
+                acquired: false,
+                waker: Some(cx.waker().clone()),
+            });
+            state.queue.push_back(*wait_key);
+            return Poll::Pending;
+        }
+
+        if state.waiters[*wait_key].acquired {
+            state.waiters.remove(*wait_key);
+            *wait_key = WAIT_KEY_NONE;
+            Poll::Ready(Ok(()))
+        } else if state.is_closed {
+            state.waiters.remove(*wait_key);
+            *wait_key = WAIT_KEY_NONE;
+            Poll::Ready(Err(AcquireError { _priv: () }))
+        } else {
+            state.waiters[*wait_key].register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    // Called when a future waiting for permits is dropped.
+    fn cancel_acquire(&self, wait_key: usize) {
+        if wait_key == WAIT_KEY_NONE {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let waiter = state.waiters.remove(wait_key);
+        if waiter.acquired {
+            // The permits were handed to us, but we are gone before we could
+            // take them. Give them back.
+            state.release(waiter.permits);
+        } else if let Some(pos) = state.queue.iter().position(|&key| key == wait_key) {
+            state.queue.remove(pos);
+            // If we were first in line, the next waiters may fit in the
+            // available permits.
+            if pos == 0 {
+                state.release(0);
+            }
+        }
+    }
+
+    fn release(&self, n: usize) {
+        self.state.lock().unwrap().release(n);
+    }
+}
+
+impl State {
+    fn try_acquire(&mut self, n: usize) -> Result<(), TryAcquireError> {
+        if self.is_closed {
+            Err(TryAcquireError { kind: TryAcquireErrorKind::Closed })
+        } else if !self.queue.is_empty() || self.permits < n {
+            // Tasks which are already waiting go first.
+            Err(TryAcquireError { kind: TryAcquireErrorKind::NoPermits })
+        } else {
+            self.permits -= n;
+            Ok(())
+        }
+    }
+
+    // Gives `n` permits back, and hands them to the waiters in the order they
+    // started waiting, as long as they fit.
+    fn release(&mut self, n: usize) {
+        self.permits = self.permits.checked_add(n).expect("semaphore permits overflow");
+
+        while let Some(&key) = self.queue.front() {
+            let waiter = &mut self.waiters[key];
+            if waiter.permits > self.permits {
+                break;
+            }
+            self.permits -= waiter.permits;
+            waiter.acquired = true;
+            waiter.wake();
+            self.queue.pop_front();
+        }
+    }
+}
+
+impl Waiter {
+    fn register(&mut self, waker: &Waker) {
+        match &self.waker {
+            Some(w) if waker.will_wake(w) => {}
+            _ => self.waker = Some(waker.clone()),
+        }
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+// Sentinel for when no slot in the `Slab` has been dedicated to this object.
+const WAIT_KEY_NONE: usize = usize::max_value();
+
+/// A future which resolves when permits have been acquired from a
+/// [`Semaphore`](Semaphore).
+///
+/// This is created by the [`acquire`](Semaphore::acquire) and
+/// [`acquire_many`](Semaphore::acquire_many) methods.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    // `None` indicates that the future has completed.
+    semaphore: Option<&'a Semaphore>,
+    permits: usize,
+    wait_key: usize,
+}
+
+impl fmt::Debug for Acquire<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Acquire")
+            .field("semaphore", &self.semaphore)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl FusedFuture for Acquire<'_> {
+    fn is_terminated(&self) -> bool {
+        self.semaphore.is_none()
+    }
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Result<SemaphorePermit<'a>, AcquireError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let semaphore = this.semaphore.expect("polled Acquire after completion");
+
+        match semaphore.poll_acquire(this.permits, &mut this.wait_key, cx) {
+            Poll::Ready(res) => {
+                this.semaphore = None;
+                Poll::Ready(res.map(|()| SemaphorePermit { semaphore, permits: this.permits }))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let Some(semaphore) = self.semaphore {
+            semaphore.cancel_acquire(self.wait_key);
+        }
+    }
+}
+
+/// A future which resolves when permits have been acquired from a
+/// [`Semaphore`](Semaphore) held in an `Arc`.
+///
+/// This is created by the [`acquire_owned`](Semaphore::acquire_owned) and
+/// [`acquire_many_owned`](Semaphore::acquire_many_owned) methods.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AcquireOwned {
+    // `None` indicates that the future has completed.
+    semaphore: Option<Arc<Semaphore>>,
+    permits: usize,
+    wait_key: usize,
+}
+
+impl fmt::Debug for AcquireOwned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcquireOwned")
+            .field("semaphore", &self.semaphore)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl FusedFuture for AcquireOwned {
+    fn is_terminated(&self) -> bool {
+        self.semaphore.is_none()
+    }
+}
+
+impl Future for AcquireOwned {
+    type Output = Result<OwnedSemaphorePermit, AcquireError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let semaphore = this.semaphore.as_ref().expect("polled AcquireOwned after completion");
+
+        match semaphore.poll_acquire(this.permits, &mut this.wait_key, cx) {
+            Poll::Ready(res) => {
+                let semaphore = this.semaphore.take().unwrap();
+                Poll::Ready(res.map(|()| OwnedSemaphorePermit { semaphore, permits: this.permits }))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for AcquireOwned {
+    fn drop(&mut self) {
+        if let Some(semaphore) = &self.semaphore {
+            semaphore.cancel_acquire(self.wait_key);
+        }
+    }
+}
+
+/// An RAII guard holding permits of a [`Semaphore`](Semaphore). When this
+/// structure is dropped (falls out of scope), the permits are given back.
+#[must_use = "the permits are given back as soon as the permit is dropped"]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Returns the number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Drops the permit without giving its permits back to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl fmt::Debug for SemaphorePermit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphorePermit")
+            .field("semaphore", &self.semaphore)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release(self.permits);
+    }
+}
+
+/// An RAII guard holding permits of a [`Semaphore`](Semaphore) held in an
+/// `Arc`. When this structure is dropped (falls out of scope), the permits
+/// are given back.
+#[must_use = "the permits are given back as soon as the permit is dropped"]
+pub struct OwnedSemaphorePermit {
+    semaphore: Arc<Semaphore>,
+    permits: usize,
+}
+
+impl OwnedSemaphorePermit {
+    /// Returns the number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Returns the semaphore the permits belong to.
+    pub fn semaphore(&self) -> &Arc<Semaphore> {
+        &self.semaphore
+    }
+
+    /// Drops the permit without giving its permits back to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl fmt::Debug for OwnedSemaphorePermit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedSemaphorePermit")
+            .field("semaphore", &self.semaphore)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        self.semaphore.release(self.permits);
+    }
+}
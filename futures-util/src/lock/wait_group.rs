@@ -0,0 +1,178 @@
+use futures_core::future::{FusedFuture, Future};
+use futures_core::task::{Context, Poll, Waker};
+use slab::Slab;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+
+/// A futures-aware wait group, letting tasks wait until a set of other tasks
+/// is done.
+///
+/// Every clone of a `WaitGroup` counts as one piece of work in progress, and
+/// dropping it marks that work as done. The future returned by
+/// [`wait`](WaitGroup::wait) resolves once all the clones have been dropped.
+///
+/// # Examples
+///
+/// ```
+/// use futures::executor::block_on;
+/// use futures::lock::WaitGroup;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let wg = WaitGroup::new();
+/// let done = Arc::new(AtomicUsize::new(0));
+///
+/// for _ in 0..4 {
+///     let wg = wg.clone();
+///     let done = done.clone();
+///     thread::spawn(move || {
+///         done.fetch_add(1, Ordering::SeqCst);
+///         drop(wg);
+///     });
+/// }
+///
+/// block_on(wg.wait());
+/// assert_eq!(done.load(Ordering::SeqCst), 4);
+/// ```
+pub struct WaitGroup {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: StdMutex<State>,
+}
+
+struct State {
+    // Number of `WaitGroup` handles alive.
+    count: usize,
+    waiters: Slab<Waker>,
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.state.lock().unwrap();
+        f.debug_struct("WaitGroup").field("count", &state.count).finish()
+    }
+}
+
+impl Default for WaitGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for WaitGroup {
+    fn clone(&self) -> Self {
+        self.inner.state.lock().unwrap().count += 1;
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl Drop for WaitGroup {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.count -= 1;
+        if state.count == 0 {
+            for waker in state.waiters.drain() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl WaitGroup {
+    /// Creates a new wait group, with this handle as its only member.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: StdMutex::new(State { count: 1, waiters: Slab::new() }),
+            }),
+        }
+    }
+
+    /// Drops this handle, and waits until all the other clones of the wait
+    /// group have been dropped as well.
+    ///
+    /// Several handles can wait at the same time, in which case all of them
+    /// are released together.
+    pub fn wait(self) -> WaitGroupWait {
+        let inner = self.inner.clone();
+        drop(self);
+        WaitGroupWait { inner: Some(inner), wait_key: WAIT_KEY_NONE }
+    }
+}
+
+// Sentinel for when no slot in the `Slab` has been dedicated to this object.
+const WAIT_KEY_NONE: usize = usize::max_value();
+
+/// A future which resolves once all the handles of a
+/// [`WaitGroup`](WaitGroup) have been dropped.
+///
+/// This is created by the [`wait`](WaitGroup::wait) method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WaitGroupWait {
+    // `None` indicates that the future has completed.
+    inner: Option<Arc<Inner>>,
+    wait_key: usize,
+}
+
+impl fmt::Debug for WaitGroupWait {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitGroupWait")
+            .field("is_terminated", &self.inner.is_none())
+            .field(
+                "wait_key",
+                &(if self.wait_key == WAIT_KEY_NONE { None } else { Some(self.wait_key) }),
+            )
+            .finish()
+    }
+}
+
+impl FusedFuture for WaitGroupWait {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+impl Future for WaitGroupWait {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.inner.take().expect("polled WaitGroupWait after completion");
+        let mut state = inner.state.lock().unwrap();
+
+        if state.count == 0 {
+            // Once everyone is done, the wait group removed all the wakers.
+            self.wait_key = WAIT_KEY_NONE;
+            return Poll::Ready(());
+        }
+
+        if self.wait_key == WAIT_KEY_NONE {
+            self.wait_key = state.waiters.insert(cx.waker().clone());
+        } else {
+            let waker = &mut state.waiters[self.wait_key];
+            if !waker.will_wake(cx.waker()) {
+                *waker = cx.waker().clone();
+            }
+        }
+        drop(state);
+        self.inner = Some(inner);
+        Poll::Pending
+    }
+}
+
+impl Drop for WaitGroupWait {
+    fn drop(&mut self) {
+        if self.wait_key == WAIT_KEY_NONE {
+            return;
+        }
+        if let Some(inner) = &self.inner {
+            let mut state = inner.state.lock().unwrap();
+            if state.count != 0 {
+                state.waiters.remove(self.wait_key);
+            }
+        }
+    }
+}
@@ -72,6 +72,69 @@ pub mod channel {
     use super::*;
     use futures::channel::*;
 
+    assert_impl!(broadcast::Receiver<()>: Send);
+    assert_not_impl!(broadcast::Receiver<*const ()>: Send);
+    assert_impl!(broadcast::Receiver<()>: Sync);
+    assert_not_impl!(broadcast::Receiver<*const ()>: Sync);
+    assert_impl!(broadcast::Receiver<PhantomPinned>: Unpin);
+
+    assert_impl!(broadcast::RecvError: Send);
+    assert_impl!(broadcast::RecvError: Sync);
+    assert_impl!(broadcast::RecvError: Unpin);
+
+    assert_impl!(broadcast::SendError<()>: Send);
+    assert_not_impl!(broadcast::SendError<*const ()>: Send);
+    assert_impl!(broadcast::SendError<()>: Sync);
+    assert_not_impl!(broadcast::SendError<*const ()>: Sync);
+    assert_impl!(broadcast::SendError<()>: Unpin);
+    assert_not_impl!(broadcast::SendError<PhantomPinned>: Unpin);
+
+    assert_impl!(broadcast::Sender<()>: Send);
+    assert_not_impl!(broadcast::Sender<*const ()>: Send);
+    assert_impl!(broadcast::Sender<()>: Sync);
+    assert_not_impl!(broadcast::Sender<*const ()>: Sync);
+    assert_impl!(broadcast::Sender<PhantomPinned>: Unpin);
+
+    assert_impl!(broadcast::TryRecvError: Send);
+    assert_impl!(broadcast::TryRecvError: Sync);
+    assert_impl!(broadcast::TryRecvError: Unpin);
+
+    assert_impl!(mpmc::Receiver<()>: Send);
+    assert_not_impl!(mpmc::Receiver<*const ()>: Send);
+    assert_impl!(mpmc::Receiver<()>: Sync);
+    assert_not_impl!(mpmc::Receiver<*const ()>: Sync);
+    assert_impl!(mpmc::Receiver<PhantomPinned>: Unpin);
+
+    assert_impl!(mpmc::Recv<'_, ()>: Send);
+    assert_not_impl!(mpmc::Recv<'_, *const ()>: Send);
+    assert_impl!(mpmc::Recv<'_, ()>: Sync);
+    assert_not_impl!(mpmc::Recv<'_, *const ()>: Sync);
+    assert_impl!(mpmc::Recv<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(mpmc::Sender<()>: Send);
+    assert_not_impl!(mpmc::Sender<*const ()>: Send);
+    assert_impl!(mpmc::Sender<()>: Sync);
+    assert_not_impl!(mpmc::Sender<*const ()>: Sync);
+    assert_impl!(mpmc::Sender<PhantomPinned>: Unpin);
+
+    assert_impl!(mpsc::Closed<'_, ()>: Send);
+    assert_not_impl!(mpsc::Closed<'_, *const ()>: Send);
+    assert_impl!(mpsc::Closed<'_, ()>: Sync);
+    assert_not_impl!(mpsc::Closed<'_, *const ()>: Sync);
+    assert_impl!(mpsc::Closed<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(mpsc::PriorityReceiver<()>: Send);
+    assert_not_impl!(mpsc::PriorityReceiver<*const ()>: Send);
+    assert_impl!(mpsc::PriorityReceiver<()>: Sync);
+    assert_not_impl!(mpsc::PriorityReceiver<*const ()>: Sync);
+    assert_impl!(mpsc::PriorityReceiver<PhantomPinned>: Unpin);
+
+    assert_impl!(mpsc::PrioritySender<()>: Send);
+    assert_not_impl!(mpsc::PrioritySender<*const ()>: Send);
+    assert_impl!(mpsc::PrioritySender<()>: Sync);
+    assert_not_impl!(mpsc::PrioritySender<*const ()>: Sync);
+    assert_impl!(mpsc::PrioritySender<PhantomPinned>: Unpin);
+
     assert_impl!(mpsc::Receiver<()>: Send);
     assert_not_impl!(mpsc::Receiver<*const ()>: Send);
     assert_impl!(mpsc::Receiver<()>: Sync);
@@ -82,6 +145,12 @@ pub mod channel {
     assert_impl!(mpsc::SendError: Sync);
     assert_impl!(mpsc::SendError: Unpin);
 
+    assert_impl!(mpsc::SendWithPriority<'_, ()>: Send);
+    assert_not_impl!(mpsc::SendWithPriority<'_, *const ()>: Send);
+    assert_impl!(mpsc::SendWithPriority<'_, ()>: Sync);
+    assert_not_impl!(mpsc::SendWithPriority<'_, *const ()>: Sync);
+    assert_impl!(mpsc::SendWithPriority<'_, PhantomPinned>: Unpin);
+
     assert_impl!(mpsc::Sender<()>: Send);
     assert_not_impl!(mpsc::Sender<*const ()>: Send);
     assert_impl!(mpsc::Sender<()>: Sync);
@@ -132,6 +201,40 @@ pub mod channel {
     assert_impl!(oneshot::Sender<()>: Sync);
     assert_not_impl!(oneshot::Sender<*const ()>: Sync);
     assert_impl!(oneshot::Sender<PhantomPinned>: Unpin);
+
+    assert_impl!(watch::Changed<'_, ()>: Send);
+    assert_not_impl!(watch::Changed<'_, *const ()>: Send);
+    assert_impl!(watch::Changed<'_, ()>: Sync);
+    assert_not_impl!(watch::Changed<'_, *const ()>: Sync);
+    assert_impl!(watch::Changed<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(watch::Receiver<()>: Send);
+    assert_not_impl!(watch::Receiver<*const ()>: Send);
+    assert_impl!(watch::Receiver<()>: Sync);
+    assert_not_impl!(watch::Receiver<*const ()>: Sync);
+    assert_impl!(watch::Receiver<PhantomPinned>: Unpin);
+
+    assert_impl!(watch::RecvError: Send);
+    assert_impl!(watch::RecvError: Sync);
+    assert_impl!(watch::RecvError: Unpin);
+
+    assert_not_impl!(watch::Ref<'_, ()>: Send);
+    assert_impl!(watch::Ref<'_, ()>: Sync);
+    assert_not_impl!(watch::Ref<'_, *const ()>: Sync);
+    assert_impl!(watch::Ref<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(watch::SendError<()>: Send);
+    assert_not_impl!(watch::SendError<*const ()>: Send);
+    assert_impl!(watch::SendError<()>: Sync);
+    assert_not_impl!(watch::SendError<*const ()>: Sync);
+    assert_impl!(watch::SendError<()>: Unpin);
+    assert_not_impl!(watch::SendError<PhantomPinned>: Unpin);
+
+    assert_impl!(watch::Sender<()>: Send);
+    assert_not_impl!(watch::Sender<*const ()>: Send);
+    assert_impl!(watch::Sender<()>: Sync);
+    assert_not_impl!(watch::Sender<*const ()>: Sync);
+    assert_impl!(watch::Sender<PhantomPinned>: Unpin);
 }
 
 /// Assert Send/Sync/Unpin for all public types in `futures::compat`.
@@ -875,6 +978,40 @@ pub mod lock {
     use super::*;
     use futures::lock::*;
 
+    assert_impl!(Acquire<'_>: Send);
+    assert_impl!(Acquire<'_>: Sync);
+    assert_impl!(Acquire<'_>: Unpin);
+
+    assert_impl!(AcquireError: Send);
+    assert_impl!(AcquireError: Sync);
+    assert_impl!(AcquireError: Unpin);
+
+    assert_impl!(AcquireOwned: Send);
+    assert_impl!(AcquireOwned: Sync);
+    assert_impl!(AcquireOwned: Unpin);
+
+    assert_impl!(AsyncLazy<(), ()>: Send);
+    assert_not_impl!(AsyncLazy<*const (), ()>: Send);
+    assert_not_impl!(AsyncLazy<(), *const ()>: Send);
+    assert_impl!(AsyncLazy<(), ()>: Sync);
+    assert_not_impl!(AsyncLazy<std::cell::Cell<()>, ()>: Sync);
+    assert_not_impl!(AsyncLazy<(), *const ()>: Sync);
+    assert_impl!(AsyncLazy<(), ()>: Unpin);
+    assert_not_impl!(AsyncLazy<PhantomPinned, ()>: Unpin);
+    assert_not_impl!(AsyncLazy<(), PhantomPinned>: Unpin);
+
+    assert_impl!(Barrier: Send);
+    assert_impl!(Barrier: Sync);
+    assert_impl!(Barrier: Unpin);
+
+    assert_impl!(BarrierWait<'_>: Send);
+    assert_impl!(BarrierWait<'_>: Sync);
+    assert_impl!(BarrierWait<'_>: Unpin);
+
+    assert_impl!(BarrierWaitResult: Send);
+    assert_impl!(BarrierWaitResult: Sync);
+    assert_impl!(BarrierWaitResult: Unpin);
+
     #[cfg(feature = "bilock")]
     assert_impl!(BiLock<()>: Send);
     #[cfg(feature = "bilock")]
@@ -908,6 +1045,54 @@ pub mod lock {
     #[cfg(feature = "bilock")]
     assert_impl!(BiLockGuard<'_, PhantomPinned>: Unpin);
 
+    assert_impl!(Condvar: Send);
+    assert_impl!(Condvar: Sync);
+    assert_impl!(Condvar: Unpin);
+
+    assert_impl!(CondvarWait<'_, '_, ()>: Send);
+    assert_not_impl!(CondvarWait<'_, '_, *const ()>: Send);
+    assert_impl!(CondvarWait<'_, '_, ()>: Sync);
+    assert_not_impl!(CondvarWait<'_, '_, *const ()>: Sync);
+    assert_impl!(CondvarWait<'_, '_, PhantomPinned>: Unpin);
+
+    assert_impl!(CondvarWaitWhile<'_, '_, (), ()>: Send);
+    assert_not_impl!(CondvarWaitWhile<'_, '_, *const (), ()>: Send);
+    assert_not_impl!(CondvarWaitWhile<'_, '_, (), *const ()>: Send);
+    assert_impl!(CondvarWaitWhile<'_, '_, (), ()>: Sync);
+    assert_not_impl!(CondvarWaitWhile<'_, '_, *const (), ()>: Sync);
+    assert_not_impl!(CondvarWaitWhile<'_, '_, (), *const ()>: Sync);
+    assert_impl!(CondvarWaitWhile<'_, '_, PhantomPinned, PhantomPinned>: Unpin);
+
+    assert_impl!(CountdownLatch: Send);
+    assert_impl!(CountdownLatch: Sync);
+    assert_impl!(CountdownLatch: Unpin);
+
+    assert_impl!(GetOrInit<'_, (), (), ()>: Send);
+    assert_not_impl!(GetOrInit<'_, *const (), (), ()>: Send);
+    assert_not_impl!(GetOrInit<'_, (), *const (), ()>: Send);
+    assert_not_impl!(GetOrInit<'_, (), (), *const ()>: Send);
+    assert_impl!(GetOrInit<'_, (), (), ()>: Sync);
+    assert_not_impl!(GetOrInit<'_, *const (), (), ()>: Sync);
+    assert_not_impl!(GetOrInit<'_, (), *const (), ()>: Sync);
+    assert_not_impl!(GetOrInit<'_, (), (), *const ()>: Sync);
+    assert_impl!(GetOrInit<'_, PhantomPinned, PhantomPinned, ()>: Unpin);
+    assert_not_impl!(GetOrInit<'_, (), (), PhantomPinned>: Unpin);
+
+    assert_impl!(GetOrTryInit<'_, (), (), ()>: Send);
+    assert_not_impl!(GetOrTryInit<'_, *const (), (), ()>: Send);
+    assert_not_impl!(GetOrTryInit<'_, (), *const (), ()>: Send);
+    assert_not_impl!(GetOrTryInit<'_, (), (), *const ()>: Send);
+    assert_impl!(GetOrTryInit<'_, (), (), ()>: Sync);
+    assert_not_impl!(GetOrTryInit<'_, *const (), (), ()>: Sync);
+    assert_not_impl!(GetOrTryInit<'_, (), *const (), ()>: Sync);
+    assert_not_impl!(GetOrTryInit<'_, (), (), *const ()>: Sync);
+    assert_impl!(GetOrTryInit<'_, PhantomPinned, PhantomPinned, ()>: Unpin);
+    assert_not_impl!(GetOrTryInit<'_, (), (), PhantomPinned>: Unpin);
+
+    assert_impl!(LatchWait<'_>: Send);
+    assert_impl!(LatchWait<'_>: Sync);
+    assert_impl!(LatchWait<'_>: Unpin);
+
     assert_impl!(MappedMutexGuard<'_, (), ()>: Send);
     assert_not_impl!(MappedMutexGuard<'_, (), *const ()>: Send);
     assert_not_impl!(MappedMutexGuard<'_, *const (), ()>: Send);
@@ -934,6 +1119,132 @@ pub mod lock {
     assert_impl!(MutexLockFuture<'_, *const ()>: Sync);
     assert_impl!(MutexLockFuture<'_, PhantomPinned>: Unpin);
 
+    assert_impl!(MappedRwLockReadGuard<'_, (), ()>: Send);
+    assert_not_impl!(MappedRwLockReadGuard<'_, (), *const ()>: Send);
+    assert_not_impl!(MappedRwLockReadGuard<'_, *const (), ()>: Send);
+    assert_impl!(MappedRwLockReadGuard<'_, (), ()>: Sync);
+    assert_not_impl!(MappedRwLockReadGuard<'_, (), *const ()>: Sync);
+    assert_not_impl!(MappedRwLockReadGuard<'_, *const (), ()>: Sync);
+    assert_impl!(MappedRwLockReadGuard<'_, PhantomPinned, PhantomPinned>: Unpin);
+
+    assert_impl!(MappedRwLockWriteGuard<'_, (), ()>: Send);
+    assert_not_impl!(MappedRwLockWriteGuard<'_, (), *const ()>: Send);
+    assert_not_impl!(MappedRwLockWriteGuard<'_, *const (), ()>: Send);
+    assert_impl!(MappedRwLockWriteGuard<'_, (), ()>: Sync);
+    assert_not_impl!(MappedRwLockWriteGuard<'_, (), *const ()>: Sync);
+    assert_not_impl!(MappedRwLockWriteGuard<'_, *const (), ()>: Sync);
+    assert_impl!(MappedRwLockWriteGuard<'_, PhantomPinned, PhantomPinned>: Unpin);
+
+    assert_impl!(Notified<'_>: Send);
+    assert_impl!(Notified<'_>: Sync);
+    assert_impl!(Notified<'_>: Unpin);
+
+    assert_impl!(Notify: Send);
+    assert_impl!(Notify: Sync);
+    assert_impl!(Notify: Unpin);
+
+    assert_impl!(OnceCell<()>: Send);
+    assert_not_impl!(OnceCell<*const ()>: Send);
+    assert_impl!(OnceCell<()>: Sync);
+    assert_not_impl!(OnceCell<*const ()>: Sync);
+    assert_not_impl!(OnceCell<std::cell::Cell<()>>: Sync);
+    assert_impl!(OnceCell<()>: Unpin);
+    assert_not_impl!(OnceCell<PhantomPinned>: Unpin);
+
+    assert_impl!(OwnedMappedMutexGuard<(), ()>: Send);
+    assert_not_impl!(OwnedMappedMutexGuard<(), *const ()>: Send);
+    assert_not_impl!(OwnedMappedMutexGuard<*const (), ()>: Send);
+    assert_impl!(OwnedMappedMutexGuard<(), ()>: Sync);
+    assert_not_impl!(OwnedMappedMutexGuard<(), *const ()>: Sync);
+    assert_not_impl!(OwnedMappedMutexGuard<*const (), ()>: Sync);
+    assert_impl!(OwnedMappedMutexGuard<PhantomPinned, PhantomPinned>: Unpin);
+
+    assert_impl!(OwnedMutexGuard<()>: Send);
+    assert_not_impl!(OwnedMutexGuard<*const ()>: Send);
+    assert_impl!(OwnedMutexGuard<()>: Sync);
+    assert_not_impl!(OwnedMutexGuard<*const ()>: Sync);
+    assert_not_impl!(OwnedMutexGuard<std::cell::Cell<()>>: Sync);
+    assert_impl!(OwnedMutexGuard<PhantomPinned>: Unpin);
+
+    assert_impl!(OwnedMutexLockFuture<()>: Send);
+    assert_not_impl!(OwnedMutexLockFuture<*const ()>: Send);
+    assert_impl!(OwnedMutexLockFuture<()>: Sync);
+    assert_not_impl!(OwnedMutexLockFuture<*const ()>: Sync);
+    assert_impl!(OwnedMutexLockFuture<PhantomPinned>: Unpin);
+
+    assert_impl!(OwnedSemaphorePermit: Send);
+    assert_impl!(OwnedSemaphorePermit: Sync);
+    assert_impl!(OwnedSemaphorePermit: Unpin);
+
+    assert_impl!(RwLock<()>: Send);
+    assert_not_impl!(RwLock<*const ()>: Send);
+    assert_impl!(RwLock<()>: Sync);
+    assert_not_impl!(RwLock<*const ()>: Sync);
+    assert_not_impl!(RwLock<std::cell::Cell<()>>: Sync);
+    assert_impl!(RwLock<()>: Unpin);
+    assert_not_impl!(RwLock<PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockReadFuture<'_, ()>: Send);
+    assert_not_impl!(RwLockReadFuture<'_, *const ()>: Send);
+    assert_impl!(RwLockReadFuture<'_, *const ()>: Sync);
+    assert_impl!(RwLockReadFuture<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockReadGuard<'_, ()>: Send);
+    assert_not_impl!(RwLockReadGuard<'_, *const ()>: Send);
+    assert_impl!(RwLockReadGuard<'_, ()>: Sync);
+    assert_not_impl!(RwLockReadGuard<'_, *const ()>: Sync);
+    assert_impl!(RwLockReadGuard<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockUpgradableReadFuture<'_, ()>: Send);
+    assert_not_impl!(RwLockUpgradableReadFuture<'_, *const ()>: Send);
+    assert_impl!(RwLockUpgradableReadFuture<'_, *const ()>: Sync);
+    assert_impl!(RwLockUpgradableReadFuture<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockUpgradableReadGuard<'_, ()>: Send);
+    assert_not_impl!(RwLockUpgradableReadGuard<'_, *const ()>: Send);
+    assert_not_impl!(RwLockUpgradableReadGuard<'_, std::sync::MutexGuard<'static, ()>>: Send);
+    assert_impl!(RwLockUpgradableReadGuard<'_, ()>: Sync);
+    assert_not_impl!(RwLockUpgradableReadGuard<'_, *const ()>: Sync);
+    assert_impl!(RwLockUpgradableReadGuard<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockUpgradeFuture<'_, ()>: Send);
+    assert_not_impl!(RwLockUpgradeFuture<'_, *const ()>: Send);
+    assert_not_impl!(RwLockUpgradeFuture<'_, std::sync::MutexGuard<'static, ()>>: Send);
+    assert_impl!(RwLockUpgradeFuture<'_, *const ()>: Sync);
+    assert_impl!(RwLockUpgradeFuture<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockWriteFuture<'_, ()>: Send);
+    assert_not_impl!(RwLockWriteFuture<'_, *const ()>: Send);
+    assert_impl!(RwLockWriteFuture<'_, *const ()>: Sync);
+    assert_impl!(RwLockWriteFuture<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(RwLockWriteGuard<'_, ()>: Send);
+    assert_not_impl!(RwLockWriteGuard<'_, *const ()>: Send);
+    assert_not_impl!(RwLockWriteGuard<'_, std::sync::MutexGuard<'static, ()>>: Send);
+    assert_impl!(RwLockWriteGuard<'_, ()>: Sync);
+    assert_not_impl!(RwLockWriteGuard<'_, *const ()>: Sync);
+    assert_impl!(RwLockWriteGuard<'_, PhantomPinned>: Unpin);
+
+    assert_impl!(Semaphore: Send);
+    assert_impl!(Semaphore: Sync);
+    assert_impl!(Semaphore: Unpin);
+
+    assert_impl!(SemaphorePermit<'_>: Send);
+    assert_impl!(SemaphorePermit<'_>: Sync);
+    assert_impl!(SemaphorePermit<'_>: Unpin);
+
+    assert_impl!(TryAcquireError: Send);
+    assert_impl!(TryAcquireError: Sync);
+    assert_impl!(TryAcquireError: Unpin);
+
+    assert_impl!(WaitGroup: Send);
+    assert_impl!(WaitGroup: Sync);
+    assert_impl!(WaitGroup: Unpin);
+
+    assert_impl!(WaitGroupWait: Send);
+    assert_impl!(WaitGroupWait: Sync);
+    assert_impl!(WaitGroupWait: Unpin);
+
     #[cfg(feature = "bilock")]
     assert_impl!(ReuniteError<()>: Send);
     #[cfg(feature = "bilock")]
@@ -0,0 +1,105 @@
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FusedFuture, FutureExt};
+use futures::lock::Barrier;
+use futures::stream::StreamExt;
+use futures::task::{Context, Poll, SpawnExt};
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn barrier_releases_group() {
+    let barrier = Barrier::new(3);
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut a = barrier.wait();
+    let mut b = barrier.wait();
+    assert!(a.poll_unpin(&mut cx).is_pending());
+    assert!(b.poll_unpin(&mut cx).is_pending());
+    assert_eq!(counter, 0);
+
+    let c = barrier.wait().poll_unpin(&mut panic_context());
+    assert_eq!(counter, 2);
+    match (a.poll_unpin(&mut panic_context()), b.poll_unpin(&mut panic_context()), c) {
+        (Poll::Ready(a), Poll::Ready(b), Poll::Ready(c)) => {
+            assert!(!a.is_leader());
+            assert!(!b.is_leader());
+            assert!(c.is_leader());
+        }
+        _ => panic!("barrier did not release the group"),
+    }
+    assert!(a.is_terminated() && b.is_terminated());
+}
+
+#[test]
+fn barrier_is_reusable() {
+    let barrier = Barrier::new(2);
+
+    for _ in 0..3 {
+        let mut a = barrier.wait();
+        assert!(a.poll_unpin(&mut noop_context()).is_pending());
+        assert!(barrier.wait().poll_unpin(&mut panic_context()).is_ready());
+        assert!(a.poll_unpin(&mut panic_context()).is_ready());
+    }
+}
+
+#[test]
+fn barrier_of_zero_or_one_never_waits() {
+    for n in 0..2 {
+        let barrier = Barrier::new(n);
+        match barrier.wait().poll_unpin(&mut panic_context()) {
+            Poll::Ready(result) => assert!(result.is_leader()),
+            Poll::Pending => panic!("barrier waited"),
+        };
+    }
+}
+
+#[test]
+fn dropped_wait_leaves_group() {
+    let barrier = Barrier::new(2);
+
+    let mut a = barrier.wait();
+    assert!(a.poll_unpin(&mut noop_context()).is_pending());
+    drop(a);
+
+    // The dropped task does not count towards the group.
+    let mut b = barrier.wait();
+    assert!(b.poll_unpin(&mut noop_context()).is_pending());
+    assert!(barrier.wait().poll_unpin(&mut panic_context()).is_ready());
+    assert!(b.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn barrier_across_threads() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(8).create().unwrap();
+
+    let n = 8;
+    let rounds = 50;
+    let barrier = Arc::new(Barrier::new(n));
+    let leaders = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..n {
+        let tx = tx.clone();
+        let barrier = barrier.clone();
+        let leaders = leaders.clone();
+        pool.spawn(async move {
+            for _ in 0..rounds {
+                if barrier.wait().await.is_leader() {
+                    leaders.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            tx.unbounded_send(()).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..n {
+            rx.next().await.unwrap();
+        }
+    });
+    assert_eq!(leaders.load(Ordering::SeqCst), rounds);
+}
@@ -0,0 +1,239 @@
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FusedFuture, FutureExt};
+use futures::lock::{Condvar, Mutex};
+use futures::stream::StreamExt;
+use futures::task::{Context, Poll, SpawnExt};
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::Arc;
+
+#[test]
+fn condvar_wait_releases_and_relocks() {
+    let mutex = Mutex::new(0);
+    let condvar = Condvar::new();
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut wait = condvar.wait(mutex.try_lock().unwrap());
+    // The guard was released as soon as `wait` was called.
+    *mutex.try_lock().unwrap() = 1;
+    assert!(wait.poll_unpin(&mut cx).is_pending());
+
+    // Held while notifying, so the waiter then has to wait for the mutex.
+    let guard = mutex.try_lock().unwrap();
+    condvar.notify_one();
+    assert_eq!(counter, 1);
+    assert!(wait.poll_unpin(&mut cx).is_pending());
+    drop(guard);
+    assert_eq!(counter, 2);
+
+    match wait.poll_unpin(&mut panic_context()) {
+        Poll::Ready(guard) => assert_eq!(*guard, 1),
+        Poll::Pending => panic!("mutex not re-acquired"),
+    }
+    assert!(wait.is_terminated());
+    assert!(mutex.try_lock().is_some());
+}
+
+#[test]
+fn condvar_notify_before_poll_is_not_missed() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+
+    let mut wait = condvar.wait(mutex.try_lock().unwrap());
+    condvar.notify_one();
+    assert!(wait.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn condvar_notify_without_waiters_is_lost() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+
+    condvar.notify_one();
+    condvar.notify_all();
+    let mut wait = condvar.wait(mutex.try_lock().unwrap());
+    assert!(wait.poll_unpin(&mut noop_context()).is_pending());
+}
+
+#[test]
+fn condvar_notify_one_in_order() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut first = condvar.wait(mutex.try_lock().unwrap());
+    let mut second = condvar.wait(mutex.try_lock().unwrap());
+    assert!(first.poll_unpin(&mut cx).is_pending());
+    assert!(second.poll_unpin(&mut cx).is_pending());
+
+    condvar.notify_one();
+    assert_eq!(counter, 1);
+    assert!(second.poll_unpin(&mut cx).is_pending());
+    let guard = first.poll_unpin(&mut panic_context());
+    assert!(guard.is_ready());
+    drop(guard);
+
+    condvar.notify_one();
+    assert_eq!(counter, 2);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn condvar_notify_all() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut waits: Vec<_> = (0..3).map(|_| condvar.wait(mutex.try_lock().unwrap())).collect();
+    for wait in &mut waits {
+        assert!(wait.poll_unpin(&mut cx).is_pending());
+    }
+
+    condvar.notify_all();
+    assert_eq!(counter, 3);
+    for wait in &mut waits {
+        // Each waiter gets the mutex in turn.
+        let guard = wait.poll_unpin(&mut cx);
+        assert!(guard.is_ready());
+    }
+}
+
+#[test]
+fn condvar_spurious_polls_do_not_complete() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+
+    let mut wait = condvar.wait(mutex.try_lock().unwrap());
+    // The task may be polled for unrelated reasons, without a notification.
+    for _ in 0..3 {
+        assert!(wait.poll_unpin(&mut noop_context()).is_pending());
+    }
+    assert!(mutex.try_lock().is_some());
+}
+
+#[test]
+fn condvar_wait_while_ignores_spurious_wakeups() {
+    let mutex = Mutex::new(0);
+    let condvar = Condvar::new();
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut wait = condvar.wait_while(mutex.try_lock().unwrap(), |n| *n < 2);
+    assert!(wait.poll_unpin(&mut cx).is_pending());
+
+    // Woken, but the condition still holds: back to waiting.
+    *mutex.try_lock().unwrap() = 1;
+    condvar.notify_one();
+    assert_eq!(counter, 1);
+    assert!(wait.poll_unpin(&mut cx).is_pending());
+    assert!(mutex.try_lock().is_some());
+
+    *mutex.try_lock().unwrap() = 2;
+    condvar.notify_all();
+    assert_eq!(counter, 2);
+    match wait.poll_unpin(&mut panic_context()) {
+        Poll::Ready(guard) => assert_eq!(*guard, 2),
+        Poll::Pending => panic!("condition not observed"),
+    }
+    assert!(wait.is_terminated());
+}
+
+#[test]
+fn condvar_wait_while_condition_already_false() {
+    let mutex = Mutex::new(true);
+    let condvar = Condvar::new();
+
+    let mut wait = condvar.wait_while(mutex.try_lock().unwrap(), |ready| !*ready);
+    assert!(wait.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn condvar_dropped_waiter_passes_notification_on() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+    let (waker, counter) = new_count_waker();
+
+    let mut first = condvar.wait(mutex.try_lock().unwrap());
+    assert!(first.poll_unpin(&mut noop_context()).is_pending());
+    let mut second = condvar.wait(mutex.try_lock().unwrap());
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    condvar.notify_one();
+    assert_eq!(counter, 0);
+    drop(first);
+    assert_eq!(counter, 1);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn condvar_dropped_waiter_is_forgotten() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+    let (waker, counter) = new_count_waker();
+
+    let first = condvar.wait(mutex.try_lock().unwrap());
+    let mut second = condvar.wait(mutex.try_lock().unwrap());
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    drop(first);
+
+    condvar.notify_one();
+    assert_eq!(counter, 1);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn condvar_dropped_while_relocking() {
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+
+    let mut wait = condvar.wait(mutex.try_lock().unwrap());
+    let guard = mutex.try_lock().unwrap();
+    condvar.notify_one();
+    assert!(wait.poll_unpin(&mut noop_context()).is_pending());
+    drop(wait);
+    drop(guard);
+
+    assert!(mutex.try_lock().is_some());
+}
+
+#[test]
+fn condvar_producer_consumer() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(8).create().unwrap();
+
+    let queue = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
+    let num_consumers = 8;
+    let per_consumer = 100;
+
+    for _ in 0..num_consumers {
+        let tx = tx.clone();
+        let queue = queue.clone();
+        pool.spawn(async move {
+            let (mutex, condvar) = &*queue;
+            let mut sum = 0;
+            for _ in 0..per_consumer {
+                let mut items = condvar.wait_while(mutex.lock().await, |v| v.is_empty()).await;
+                sum += items.pop().unwrap();
+            }
+            tx.unbounded_send(sum).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        let (mutex, condvar) = &*queue;
+        for i in 0..num_consumers * per_consumer {
+            mutex.lock().await.push(i);
+            condvar.notify_one();
+        }
+        let mut total = 0;
+        for _ in 0..num_consumers {
+            total += rx.next().await.unwrap();
+        }
+        let n = num_consumers * per_consumer;
+        assert_eq!(total, n * (n - 1) / 2);
+    });
+}
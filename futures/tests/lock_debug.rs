@@ -0,0 +1,190 @@
+#![cfg(feature = "lock-debug")]
+
+use futures::executor::block_on;
+use futures::future::{join, FutureExt};
+use futures::lock::{set_deadlock_hook, Mutex};
+use futures::task::Context;
+use futures_test::future::FutureTestExt;
+use futures_test::task::new_count_waker;
+use std::cell::RefCell;
+
+thread_local! {
+    static REPORTS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+// Collects the reports made on the current thread. Each test runs on its
+// own thread, so the tests do not see each other's reports.
+fn record_reports() {
+    set_deadlock_hook(Box::new(|report| {
+        REPORTS.with(|reports| reports.borrow_mut().push(report.to_owned()))
+    }));
+}
+
+fn take_reports() -> Vec<String> {
+    REPORTS.with(|reports| reports.borrow_mut().drain(..).collect())
+}
+
+#[test]
+fn mutex_lock_held_by_same_task() {
+    record_reports();
+    let mutex = Mutex::new(());
+    let (waker, _) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    // Only reported, as the guard might belong to another future of the task.
+    let _guard = mutex.lock().poll_unpin(&mut cx);
+    assert!(mutex.lock().poll_unpin(&mut cx).is_pending());
+    let reports = take_reports();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].starts_with("possible deadlock"));
+}
+
+#[test]
+fn mutex_lock_contended_within_join() {
+    record_reports();
+    let mutex = Mutex::new(0);
+
+    block_on(join(
+        async {
+            let mut guard = mutex.lock().await;
+            async {}.pending_once().await;
+            *guard += 1;
+        },
+        async {
+            *mutex.lock().await += 1;
+        },
+    ));
+    assert_eq!(mutex.into_inner(), 2);
+    assert_eq!(take_reports().len(), 1);
+}
+
+#[test]
+fn mutex_locks_taken_independently_within_join() {
+    record_reports();
+    let a = Mutex::new(());
+    let b = Mutex::new(());
+
+    // The second future takes `b` while the first one holds `a`, so they
+    // look like a task taking `b` while holding `a`.
+    block_on(join(
+        async {
+            let _a = a.lock().await;
+            async {}.pending_once().await;
+        },
+        async {
+            let _b = b.lock().await;
+        },
+    ));
+    assert!(take_reports().is_empty());
+
+    // Taking them in the opposite order is only reported.
+    block_on(async {
+        let _b = b.lock().await;
+        let _a = a.lock().await;
+    });
+    let reports = take_reports();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].starts_with("lock order inversion detected"));
+}
+
+#[test]
+fn mutex_lock_held_by_other_task() {
+    let mutex = Mutex::new(());
+    let (waker1, _) = new_count_waker();
+    let (waker2, counter) = new_count_waker();
+
+    let guard = mutex.lock().poll_unpin(&mut Context::from_waker(&waker1));
+    assert!(guard.is_ready());
+
+    let mut cx = Context::from_waker(&waker2);
+    let mut waiter = mutex.lock();
+    assert!(waiter.poll_unpin(&mut cx).is_pending());
+    drop(guard);
+    assert_eq!(counter, 1);
+    assert!(waiter.poll_unpin(&mut cx).is_ready());
+}
+
+#[test]
+fn mutex_relock_after_release() {
+    let mutex = Mutex::new(());
+    let (waker, _) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    for _ in 0..3 {
+        assert!(mutex.lock().poll_unpin(&mut cx).is_ready());
+    }
+}
+
+#[test]
+fn mutex_try_lock_held_by_same_task() {
+    let mutex = Mutex::new(());
+    let (waker, _) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let guard = mutex.lock().poll_unpin(&mut cx);
+    assert!(guard.is_ready());
+    assert!(mutex.try_lock().is_none());
+}
+
+#[test]
+fn mutex_lock_order_inversion() {
+    record_reports();
+    let a = Mutex::new(());
+    let b = Mutex::new(());
+    let (waker1, _) = new_count_waker();
+    let (waker2, _) = new_count_waker();
+    let mut cx1 = Context::from_waker(&waker1);
+    let mut cx2 = Context::from_waker(&waker2);
+
+    {
+        let _a = a.lock().poll_unpin(&mut cx1);
+        let _b = b.lock().poll_unpin(&mut cx1);
+    }
+
+    let _b = b.lock().poll_unpin(&mut cx2);
+    let _ = a.lock().poll_unpin(&mut cx2);
+    let reports = take_reports();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].starts_with("lock order inversion detected"));
+}
+
+#[test]
+fn mutex_lock_order_inversion_through_cycle() {
+    record_reports();
+    let a = Mutex::new(());
+    let b = Mutex::new(());
+    let c = Mutex::new(());
+    let (waker, _) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    {
+        let _a = a.lock().poll_unpin(&mut cx);
+        let _b = b.lock().poll_unpin(&mut cx);
+    }
+    {
+        let _b = b.lock().poll_unpin(&mut cx);
+        let _c = c.lock().poll_unpin(&mut cx);
+    }
+
+    let _c = c.lock().poll_unpin(&mut cx);
+    let _ = a.lock().poll_unpin(&mut cx);
+    let reports = take_reports();
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].starts_with("lock order inversion detected"));
+}
+
+#[test]
+fn mutex_lock_consistent_order() {
+    let a = Mutex::new(());
+    let b = Mutex::new(());
+    let (waker1, _) = new_count_waker();
+    let (waker2, _) = new_count_waker();
+
+    for waker in &[waker1, waker2] {
+        let mut cx = Context::from_waker(waker);
+        let guard_a = a.lock().poll_unpin(&mut cx);
+        let guard_b = b.lock().poll_unpin(&mut cx);
+        assert!(guard_a.is_ready());
+        assert!(guard_b.is_ready());
+    }
+}
@@ -0,0 +1,73 @@
+use futures::executor::block_on;
+use futures::future::{FusedFuture, FutureExt};
+use futures::lock::CountdownLatch;
+use futures::task::Context;
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn latch_opens_at_zero() {
+    let latch = CountdownLatch::new(2);
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut a = latch.wait();
+    let mut b = latch.wait();
+    assert!(a.poll_unpin(&mut cx).is_pending());
+    assert!(b.poll_unpin(&mut cx).is_pending());
+
+    latch.count_down();
+    assert_eq!(latch.count(), 1);
+    assert_eq!(counter, 0);
+    latch.count_down();
+    assert_eq!(counter, 2);
+    assert!(a.poll_unpin(&mut panic_context()).is_ready());
+    assert!(b.poll_unpin(&mut panic_context()).is_ready());
+    assert!(a.is_terminated());
+}
+
+#[test]
+fn latch_stays_open() {
+    let latch = CountdownLatch::new(1);
+    latch.count_down();
+    latch.count_down();
+    assert_eq!(latch.count(), 0);
+    assert!(latch.wait().poll_unpin(&mut panic_context()).is_ready());
+
+    assert!(CountdownLatch::new(0).wait().poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn dropped_latch_wait() {
+    let latch = CountdownLatch::new(1);
+    let (waker, counter) = new_count_waker();
+
+    let mut a = latch.wait();
+    assert!(a.poll_unpin(&mut noop_context()).is_pending());
+    let mut b = latch.wait();
+    assert!(b.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    drop(a);
+
+    latch.count_down();
+    assert_eq!(counter, 1);
+    assert!(b.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn latch_across_threads() {
+    let latch = Arc::new(CountdownLatch::new(8));
+
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let latch = latch.clone();
+            thread::spawn(move || latch.count_down())
+        })
+        .collect();
+
+    block_on(latch.wait());
+    assert_eq!(latch.count(), 0);
+    for t in threads {
+        t.join().unwrap();
+    }
+}
@@ -1,11 +1,13 @@
 use futures::channel::mpsc;
-use futures::executor::{block_on, ThreadPool};
+use futures::executor::{block_on, LocalPool, ThreadPool};
 use futures::future::{ready, FutureExt};
-use futures::lock::Mutex;
+use futures::lock::{Mutex, OwnedMappedMutexGuard, OwnedMutexGuard};
 use futures::stream::StreamExt;
-use futures::task::{Context, SpawnExt};
+use futures::task::{Context, LocalSpawnExt, SpawnExt};
 use futures_test::future::FutureTestExt;
-use futures_test::task::{new_count_waker, panic_context};
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::Arc;
 
 #[test]
@@ -34,6 +36,61 @@ fn mutex_wakes_waiters() {
     assert!(waiter.poll_unpin(&mut panic_context()).is_ready());
 }
 
+#[test]
+fn mutex_owned_wakes_waiters() {
+    let mutex = Arc::new(Mutex::new(()));
+    let (waker, counter) = new_count_waker();
+    let lock = mutex.clone().try_lock_owned().unwrap();
+    assert!(mutex.clone().try_lock_owned().is_none());
+
+    let mut cx = Context::from_waker(&waker);
+    let mut waiter = mutex.clone().lock_owned();
+    assert!(waiter.poll_unpin(&mut cx).is_pending());
+    // Borrowing and owned waiters share the same queue.
+    let mut borrowed = mutex.lock();
+    assert!(borrowed.poll_unpin(&mut noop_context()).is_pending());
+
+    drop(lock);
+
+    assert_eq!(counter, 1);
+    let lock = waiter.poll_unpin(&mut panic_context());
+    assert!(lock.is_ready());
+    drop(lock);
+    assert!(borrowed.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn mutex_owned_dropped_waiter_wakes_another() {
+    let mutex = Arc::new(Mutex::new(()));
+    let (waker, counter) = new_count_waker();
+    let lock = mutex.clone().try_lock_owned().unwrap();
+
+    let mut first = mutex.clone().lock_owned();
+    assert!(first.poll_unpin(&mut noop_context()).is_pending());
+    let mut second = mutex.clone().lock_owned();
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    drop(lock);
+    // `first` was woken, but is dropped before taking the lock.
+    drop(first);
+    assert_eq!(counter, 1);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn mutex_owned_guard_map() {
+    let mutex = Arc::new(Mutex::new((1, String::from("a"))));
+
+    let guard = OwnedMutexGuard::map(mutex.clone().try_lock_owned().unwrap(), |v| &mut v.1);
+    let mut guard = OwnedMappedMutexGuard::map(guard, String::as_mut_str);
+    assert!(Arc::ptr_eq(OwnedMappedMutexGuard::mutex(&guard), &mutex));
+    guard.make_ascii_uppercase();
+    assert!(mutex.try_lock().is_none());
+
+    std::thread::spawn(move || drop(guard)).join().unwrap();
+    assert_eq!(mutex.try_lock().unwrap().1, "A");
+}
+
 #[test]
 fn mutex_contested() {
     let (tx, mut rx) = mpsc::unbounded();
@@ -64,3 +121,176 @@ fn mutex_contested() {
         assert_eq!(num_tasks, *lock);
     })
 }
+
+#[test]
+fn mutex_owned_contested() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(16).create().unwrap();
+
+    let mutex = Arc::new(Mutex::new(0));
+
+    let num_tasks = 1000;
+    for _ in 0..num_tasks {
+        let tx = tx.clone();
+        let mutex = mutex.clone();
+        pool.spawn(async move {
+            let mut lock = mutex.lock_owned().await;
+            ready(()).pending_once().await;
+            *lock += 1;
+            tx.unbounded_send(lock).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        // The guards are released by the receiving side.
+        for _ in 0..num_tasks {
+            drop(rx.next().await.unwrap());
+        }
+        let lock = mutex.lock().await;
+        assert_eq!(num_tasks, *lock);
+    })
+}
+
+#[test]
+fn fair_mutex_hands_off_in_order() {
+    let mutex = Mutex::new_fair(());
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let lock = mutex.try_lock().unwrap();
+    let mut first = mutex.lock();
+    let mut second = mutex.lock();
+    assert!(first.poll_unpin(&mut cx).is_pending());
+    assert!(second.poll_unpin(&mut cx).is_pending());
+
+    drop(lock);
+    assert_eq!(counter, 1);
+    // The lock went to `first` without it being polled: nobody can barge in.
+    assert!(mutex.try_lock().is_none());
+    assert!(second.poll_unpin(&mut cx).is_pending());
+    let lock = first.poll_unpin(&mut panic_context());
+    assert!(lock.is_ready());
+
+    drop(lock);
+    assert_eq!(counter, 2);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn fair_mutex_dropped_waiter_passes_lock_on() {
+    let mutex = Mutex::new_fair(());
+    let (waker, counter) = new_count_waker();
+
+    let lock = mutex.try_lock().unwrap();
+    let mut first = mutex.lock();
+    assert!(first.poll_unpin(&mut noop_context()).is_pending());
+    let mut second = mutex.lock();
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    drop(lock);
+    // `first` was handed the lock, but is dropped before taking it.
+    drop(first);
+    assert_eq!(counter, 1);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn fair_mutex_dropped_last_waiter_releases_lock() {
+    let mutex = Arc::new(Mutex::new_fair(()));
+
+    let lock = mutex.try_lock().unwrap();
+    let mut waiter = mutex.clone().lock_owned();
+    assert!(waiter.poll_unpin(&mut noop_context()).is_pending());
+    let mut cancelled = mutex.lock();
+    assert!(cancelled.poll_unpin(&mut noop_context()).is_pending());
+    drop(cancelled);
+
+    drop(lock);
+    drop(waiter);
+    assert!(mutex.try_lock().is_some());
+}
+
+#[test]
+fn fair_mutex_waiter_is_not_starved() {
+    let mutex = Mutex::new_fair(0);
+
+    let mut lock = mutex.try_lock().unwrap();
+    let mut waiter = mutex.lock();
+    assert!(waiter.poll_unpin(&mut noop_context()).is_pending());
+
+    // A task releasing and immediately re-taking the lock cannot get it
+    // again while someone is waiting.
+    drop(lock);
+    lock = match mutex.lock().poll_unpin(&mut noop_context()) {
+        std::task::Poll::Ready(_) => panic!("waiter starved"),
+        std::task::Poll::Pending => match waiter.poll_unpin(&mut panic_context()) {
+            std::task::Poll::Ready(lock) => lock,
+            std::task::Poll::Pending => panic!("lock not handed off"),
+        },
+    };
+    *lock += 1;
+}
+
+#[test]
+fn fair_mutex_bounded_waiting() {
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+
+    let num_tasks = 10;
+    let iterations = 100;
+    let mutex = Rc::new(Mutex::new_fair(()));
+    let acquisitions = Rc::new(Cell::new(0));
+
+    for _ in 0..num_tasks {
+        let mutex = mutex.clone();
+        let acquisitions = acquisitions.clone();
+        spawner
+            .spawn_local(async move {
+                for _ in 0..iterations {
+                    let started = acquisitions.get();
+                    let lock = mutex.lock().await;
+                    // Every other task got the lock at most once before us.
+                    assert!(acquisitions.get() - started < num_tasks);
+                    acquisitions.set(acquisitions.get() + 1);
+                    ready(()).pending_once().await;
+                    drop(lock);
+                    ready(()).pending_once().await;
+                }
+            })
+            .unwrap();
+    }
+
+    pool.run();
+    assert_eq!(acquisitions.get(), num_tasks * iterations);
+}
+
+#[test]
+fn fair_mutex_contested() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(16).create().unwrap();
+
+    let mutex = Arc::new(Mutex::new_fair(0));
+
+    let num_tasks = 1000;
+    for _ in 0..num_tasks {
+        let tx = tx.clone();
+        let mutex = mutex.clone();
+        pool.spawn(async move {
+            let mut lock = mutex.lock().await;
+            ready(()).pending_once().await;
+            *lock += 1;
+            drop(lock);
+            tx.unbounded_send(()).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            rx.next().await.unwrap();
+        }
+        let lock = mutex.lock().await;
+        assert_eq!(num_tasks, *lock);
+    })
+}
@@ -0,0 +1,149 @@
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{FusedFuture, FutureExt};
+use futures::lock::Notify;
+use futures::stream::StreamExt;
+use futures::task::{Context, SpawnExt};
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn notify_one_wakes_waiter() {
+    let notify = Notify::new();
+    let (waker, counter) = new_count_waker();
+
+    let mut notified = notify.notified();
+    assert!(notified.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    notify.notify_one();
+    assert_eq!(counter, 1);
+    assert!(notified.poll_unpin(&mut panic_context()).is_ready());
+    assert!(notified.is_terminated());
+}
+
+#[test]
+fn notify_one_stores_permit() {
+    let notify = Notify::new();
+    notify.notify_one();
+    notify.notify_one();
+
+    // Only one permit is stored.
+    assert!(notify.notified().poll_unpin(&mut panic_context()).is_ready());
+    assert!(notify.notified().poll_unpin(&mut noop_context()).is_pending());
+}
+
+#[test]
+fn notify_one_wakes_in_order() {
+    let notify = Notify::new();
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut first = notify.notified();
+    let mut second = notify.notified();
+    assert!(second.poll_unpin(&mut cx).is_pending());
+    assert!(first.poll_unpin(&mut cx).is_pending());
+
+    // `second` started waiting first.
+    notify.notify_one();
+    assert_eq!(counter, 1);
+    assert!(first.poll_unpin(&mut cx).is_pending());
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+
+    notify.notify_one();
+    assert_eq!(counter, 2);
+    assert!(first.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn notify_waiters_wakes_all() {
+    let notify = Notify::new();
+    let (waker, counter) = new_count_waker();
+
+    let mut a = notify.notified();
+    let mut b = notify.notified();
+    assert!(a.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    assert!(b.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    // Created, but not polled yet.
+    let mut c = notify.notified();
+
+    notify.notify_waiters();
+    assert_eq!(counter, 2);
+    assert!(a.poll_unpin(&mut panic_context()).is_ready());
+    assert!(b.poll_unpin(&mut panic_context()).is_ready());
+    assert!(c.poll_unpin(&mut panic_context()).is_ready());
+
+    // No permit is stored for later futures.
+    assert!(notify.notified().poll_unpin(&mut noop_context()).is_pending());
+}
+
+#[test]
+fn dropped_notified_passes_notification_on() {
+    let notify = Notify::new();
+    let (waker, counter) = new_count_waker();
+
+    let mut first = notify.notified();
+    assert!(first.poll_unpin(&mut noop_context()).is_pending());
+    let mut second = notify.notified();
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    notify.notify_one();
+    assert_eq!(counter, 0);
+    drop(first);
+    assert_eq!(counter, 1);
+    assert!(second.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn dropped_notified_restores_permit() {
+    let notify = Notify::new();
+
+    let mut notified = notify.notified();
+    assert!(notified.poll_unpin(&mut noop_context()).is_pending());
+    notify.notify_one();
+    drop(notified);
+
+    assert!(notify.notified().poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn dropped_waiting_notified_is_forgotten() {
+    let notify = Notify::new();
+
+    let mut notified = notify.notified();
+    assert!(notified.poll_unpin(&mut noop_context()).is_pending());
+    drop(notified);
+
+    // The notification is not lost on the dropped future.
+    notify.notify_one();
+    assert!(notify.notified().poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn notify_across_threads() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(8).create().unwrap();
+
+    let notify = Arc::new(Notify::new());
+    let woken = Arc::new(AtomicUsize::new(0));
+
+    let num_tasks = 100;
+    for _ in 0..num_tasks {
+        let tx = tx.clone();
+        let notify = notify.clone();
+        let woken = woken.clone();
+        pool.spawn(async move {
+            notify.notified().await;
+            woken.fetch_add(1, Ordering::SeqCst);
+            tx.unbounded_send(()).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            notify.notify_one();
+            rx.next().await.unwrap();
+        }
+    });
+    assert_eq!(woken.load(Ordering::SeqCst), num_tasks);
+}
@@ -0,0 +1,150 @@
+use futures::channel::oneshot;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{self, FusedFuture, FutureExt};
+use futures::lock::{AsyncLazy, OnceCell};
+use futures::task::{Context, Poll, SpawnExt};
+use futures_test::future::FutureTestExt;
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn once_cell_get_or_init() {
+    let cell = OnceCell::new();
+    assert!(!cell.is_initialized());
+
+    let mut init = cell.get_or_init(|| future::ready(1));
+    assert_eq!(init.poll_unpin(&mut panic_context()), Poll::Ready(&1));
+    assert!(init.is_terminated());
+    assert_eq!(cell.get(), Some(&1));
+    assert_eq!(block_on(cell.get_or_init(|| async { panic!("initialized twice") })), &1);
+}
+
+#[test]
+fn once_cell_runs_one_initializer() {
+    let cell = OnceCell::new();
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+    let (tx, rx) = oneshot::channel();
+
+    let mut first = cell.get_or_init(|| rx.map(Result::unwrap));
+    let mut second = cell.get_or_init(|| future::lazy(|_| panic!("initialized twice")));
+    assert!(first.poll_unpin(&mut cx).is_pending());
+    assert!(second.poll_unpin(&mut cx).is_pending());
+
+    tx.send(1).unwrap();
+    assert_eq!(first.poll_unpin(&mut cx), Poll::Ready(&1));
+    assert_eq!(counter, 2);
+    assert_eq!(second.poll_unpin(&mut panic_context()), Poll::Ready(&1));
+}
+
+#[test]
+fn once_cell_failed_initializer_lets_next_retry() {
+    let cell = OnceCell::new();
+    let (waker, counter) = new_count_waker();
+    let (tx, rx) = oneshot::channel::<Result<i32, &str>>();
+
+    let mut first = cell.get_or_try_init(|| rx.map(Result::unwrap));
+    assert!(first.poll_unpin(&mut noop_context()).is_pending());
+    let mut second = cell.get_or_try_init(|| future::ok::<_, &str>(2));
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    tx.send(Err("failed")).unwrap();
+    assert_eq!(first.poll_unpin(&mut noop_context()), Poll::Ready(Err("failed")));
+    assert!(!cell.is_initialized());
+    assert_eq!(counter, 1);
+    assert_eq!(second.poll_unpin(&mut panic_context()), Poll::Ready(Ok(&2)));
+}
+
+#[test]
+fn once_cell_cancelled_initializer_lets_next_retry() {
+    let cell = OnceCell::new();
+    let (waker, counter) = new_count_waker();
+
+    let mut first = cell.get_or_init(future::pending);
+    assert!(first.poll_unpin(&mut noop_context()).is_pending());
+    let mut second = cell.get_or_init(|| future::ready(2));
+    assert!(second.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    drop(first);
+    assert_eq!(counter, 1);
+    assert_eq!(second.poll_unpin(&mut panic_context()), Poll::Ready(&2));
+}
+
+#[test]
+fn once_cell_set_and_take() {
+    let mut cell = OnceCell::new();
+    assert_eq!(cell.set(1), Ok(()));
+    assert_eq!(cell.set(2), Err(2));
+    assert_eq!(cell.get(), Some(&1));
+
+    *cell.get_mut().unwrap() = 3;
+    assert_eq!(cell.take(), Some(3));
+    assert!(!cell.is_initialized());
+
+    // The cell cannot be set while an initializer is running.
+    let mut init = cell.get_or_init(future::pending);
+    assert!(init.poll_unpin(&mut noop_context()).is_pending());
+    assert_eq!(cell.set(4), Err(4));
+    drop(init);
+    assert_eq!(cell.set(4), Ok(()));
+    assert_eq!(cell.into_inner(), Some(4));
+}
+
+#[test]
+fn once_cell_across_threads() {
+    let pool = ThreadPool::builder().pool_size(8).create().unwrap();
+    let cell = Arc::new(OnceCell::new());
+    let inits = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..100)
+        .map(|i| {
+            let cell = cell.clone();
+            let inits = inits.clone();
+            pool.spawn_with_handle(async move {
+                *cell
+                    .get_or_init(|| async move {
+                        inits.fetch_add(1, Ordering::SeqCst);
+                        i
+                    })
+                    .await
+            })
+            .unwrap()
+        })
+        .collect();
+
+    let values = block_on(future::join_all(handles));
+    assert_eq!(inits.load(Ordering::SeqCst), 1);
+    assert!(values.iter().all(|&v| v == values[0]));
+}
+
+#[test]
+fn async_lazy_force() {
+    let calls = AtomicUsize::new(0);
+    let lazy = AsyncLazy::new(|| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        future::ready(String::from("value"))
+    });
+    assert_eq!(lazy.get(), None);
+
+    assert_eq!(block_on(lazy.force()), "value");
+    assert_eq!(block_on(lazy.force()), "value");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(lazy.into_value(), Some(String::from("value")));
+}
+
+#[test]
+fn async_lazy_retries_after_cancellation() {
+    let calls = AtomicUsize::new(0);
+    let lazy = AsyncLazy::new(|| {
+        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        future::ready(n).pending_once()
+    });
+
+    let mut force = lazy.force();
+    assert!(force.poll_unpin(&mut noop_context()).is_pending());
+    drop(force);
+    assert_eq!(lazy.get(), None);
+
+    assert_eq!(block_on(lazy.force()), &2);
+}
@@ -0,0 +1,251 @@
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{ready, FutureExt};
+use futures::lock::{
+    MappedRwLockReadGuard, RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard,
+};
+use futures::stream::StreamExt;
+use futures::task::{Context, SpawnExt};
+use futures_test::future::FutureTestExt;
+use futures_test::task::{new_count_waker, panic_context};
+use std::sync::Arc;
+
+#[test]
+fn rwlock_readers_share() {
+    let lock = RwLock::new(1);
+    let r1 = lock.read().poll_unpin(&mut panic_context());
+    let r2 = lock.read().poll_unpin(&mut panic_context());
+    assert!(r1.is_ready() && r2.is_ready());
+    assert!(lock.try_upgradable_read().is_some());
+    assert!(lock.try_write().is_none());
+}
+
+#[test]
+fn rwlock_writer_excludes_all() {
+    let lock = RwLock::new(1);
+    let writer = lock.try_write().unwrap();
+    assert!(lock.try_read().is_none());
+    assert!(lock.try_upgradable_read().is_none());
+    assert!(lock.try_write().is_none());
+    drop(writer);
+    assert!(lock.try_read().is_some());
+}
+
+#[test]
+fn rwlock_writer_wakes_readers() {
+    let lock = RwLock::new(());
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let writer = lock.try_write().unwrap();
+    let mut r1 = lock.read();
+    let mut r2 = lock.read();
+    assert!(r1.poll_unpin(&mut cx).is_pending());
+    assert!(r2.poll_unpin(&mut cx).is_pending());
+    assert_eq!(counter, 0);
+
+    drop(writer);
+
+    assert_eq!(counter, 2);
+    assert!(r1.poll_unpin(&mut panic_context()).is_ready());
+    assert!(r2.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn rwlock_prefers_writers() {
+    let lock = RwLock::new(());
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let reader = lock.try_read().unwrap();
+    let mut writer = lock.write();
+    assert!(writer.poll_unpin(&mut cx).is_pending());
+
+    // New readers wait for the writer, even though the lock is only read.
+    assert!(lock.try_read().is_none());
+    let mut late_reader = lock.read();
+    assert!(late_reader.poll_unpin(&mut cx).is_pending());
+
+    drop(reader);
+    assert_eq!(counter, 1);
+    let guard = match writer.poll_unpin(&mut panic_context()) {
+        std::task::Poll::Ready(guard) => guard,
+        std::task::Poll::Pending => panic!("writer not ready"),
+    };
+    assert!(late_reader.poll_unpin(&mut cx).is_pending());
+
+    drop(guard);
+    assert_eq!(counter, 2);
+    assert!(late_reader.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn rwlock_dropped_writer_lets_readers_in() {
+    let lock = RwLock::new(());
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let reader = lock.try_read().unwrap();
+    let mut writer = lock.write();
+    assert!(writer.poll_unpin(&mut cx).is_pending());
+    let mut late_reader = lock.read();
+    assert!(late_reader.poll_unpin(&mut cx).is_pending());
+
+    drop(writer);
+    assert_eq!(counter, 1);
+    assert!(late_reader.poll_unpin(&mut panic_context()).is_ready());
+    drop(reader);
+}
+
+#[test]
+fn rwlock_upgrade_waits_for_readers() {
+    let lock = RwLock::new(1);
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let upgradable = lock.try_upgradable_read().unwrap();
+    let reader = lock.try_read().unwrap();
+    assert!(lock.try_upgradable_read().is_none());
+
+    let upgradable = RwLockUpgradableReadGuard::try_upgrade(upgradable).unwrap_err();
+    let mut upgrade = RwLockUpgradableReadGuard::upgrade(upgradable);
+    assert!(upgrade.poll_unpin(&mut cx).is_pending());
+    assert!(lock.try_read().is_none());
+
+    drop(reader);
+    assert_eq!(counter, 1);
+    let mut writer = match upgrade.poll_unpin(&mut panic_context()) {
+        std::task::Poll::Ready(guard) => guard,
+        std::task::Poll::Pending => panic!("upgrade not ready"),
+    };
+    *writer = 2;
+    drop(writer);
+    assert_eq!(*lock.try_read().unwrap(), 2);
+}
+
+#[test]
+fn rwlock_dropped_upgrade_releases_lock() {
+    let lock = RwLock::new(());
+
+    let upgradable = lock.try_upgradable_read().unwrap();
+    let reader = lock.try_read().unwrap();
+    let mut upgrade = RwLockUpgradableReadGuard::upgrade(upgradable);
+    assert!(upgrade.poll_unpin(&mut panic_context()).is_pending());
+    drop(upgrade);
+
+    assert!(lock.try_upgradable_read().is_some());
+    drop(reader);
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn rwlock_downgrade() {
+    let lock = RwLock::new(1);
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut writer = lock.try_write().unwrap();
+    let mut reader = lock.read();
+    assert!(reader.poll_unpin(&mut cx).is_pending());
+
+    *writer = 2;
+    let downgraded = RwLockWriteGuard::downgrade(writer);
+    assert_eq!(counter, 1);
+    assert_eq!(*downgraded, 2);
+    assert!(reader.poll_unpin(&mut panic_context()).is_ready());
+    assert!(lock.try_write().is_none());
+
+    let upgradable = lock.try_upgradable_read().unwrap();
+    let _reader = RwLockUpgradableReadGuard::downgrade(upgradable);
+    assert!(lock.try_upgradable_read().is_some());
+}
+
+#[test]
+fn rwlock_downgrade_with_writer_waiting() {
+    let lock = RwLock::new(1);
+    let (writer_waker, writer_counter) = new_count_waker();
+    let (reader_waker, reader_counter) = new_count_waker();
+
+    let writer = lock.try_write().unwrap();
+    let mut waiting_writer = lock.write();
+    assert!(waiting_writer.poll_unpin(&mut Context::from_waker(&writer_waker)).is_pending());
+    let mut reader = lock.read();
+    assert!(reader.poll_unpin(&mut Context::from_waker(&reader_waker)).is_pending());
+
+    // The reader keeps waiting for the writer which was waiting first.
+    let downgraded = RwLockWriteGuard::downgrade(writer);
+    assert_eq!(reader_counter, 0);
+    assert!(lock.try_read().is_none());
+
+    drop(downgraded);
+    assert_eq!(writer_counter, 1);
+    assert_eq!(reader_counter, 0);
+    let guard = waiting_writer.poll_unpin(&mut panic_context());
+    assert!(guard.is_ready());
+    drop(guard);
+    assert_eq!(reader_counter, 1);
+    assert!(reader.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn rwlock_map_guards() {
+    let lock = RwLock::new((1, String::from("a")));
+
+    {
+        let mut s = RwLockWriteGuard::map(lock.try_write().unwrap(), |v| &mut v.1);
+        s.push('b');
+        assert!(lock.try_read().is_none());
+    }
+
+    let s = RwLockReadGuard::map(lock.try_read().unwrap(), |v| &v.1);
+    let c = MappedRwLockReadGuard::map(s, |s| &s[1..]);
+    assert_eq!(&*c, "b");
+    assert!(lock.try_write().is_none());
+    drop(c);
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn rwlock_contested() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(16).create().unwrap();
+
+    let tx = Arc::new(tx);
+    let lock = Arc::new(RwLock::new(0));
+
+    let num_tasks = 1000;
+    for i in 0..num_tasks {
+        let tx = tx.clone();
+        let lock = lock.clone();
+        pool.spawn(async move {
+            match i % 3 {
+                0 => {
+                    let mut guard = lock.write().await;
+                    ready(()).pending_once().await;
+                    *guard += 1;
+                }
+                1 => {
+                    let guard = lock.upgradable_read().await;
+                    ready(()).pending_once().await;
+                    let mut guard = RwLockUpgradableReadGuard::upgrade(guard).await;
+                    *guard += 1;
+                }
+                _ => {
+                    let guard = lock.read().await;
+                    ready(()).pending_once().await;
+                    assert!(*guard >= 0);
+                }
+            }
+            tx.unbounded_send(()).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            rx.next().await.unwrap();
+        }
+        let guard = lock.read().await;
+        assert_eq!(*guard, 667);
+    })
+}
@@ -0,0 +1,214 @@
+use futures::channel::mpsc;
+use futures::executor::{block_on, ThreadPool};
+use futures::future::{ready, FutureExt};
+use futures::lock::Semaphore;
+use futures::stream::StreamExt;
+use futures::task::{Context, Poll, SpawnExt};
+use futures_test::future::FutureTestExt;
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn semaphore_acquire_and_release() {
+    let semaphore = Semaphore::new(2);
+
+    let a = semaphore.try_acquire().unwrap();
+    let b = semaphore.acquire().poll_unpin(&mut panic_context());
+    assert!(b.is_ready());
+    assert_eq!(semaphore.available_permits(), 0);
+    assert!(semaphore.try_acquire().unwrap_err().is_no_permits());
+
+    drop(a);
+    assert_eq!(semaphore.available_permits(), 1);
+    drop(b);
+    assert_eq!(semaphore.available_permits(), 2);
+}
+
+#[test]
+fn semaphore_wakes_waiters() {
+    let semaphore = Semaphore::new(1);
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let permit = semaphore.try_acquire().unwrap();
+    let mut waiter = semaphore.acquire();
+    assert!(waiter.poll_unpin(&mut cx).is_pending());
+    assert_eq!(counter, 0);
+
+    drop(permit);
+    assert_eq!(counter, 1);
+    // The permit was handed to the waiter.
+    assert_eq!(semaphore.available_permits(), 0);
+    assert!(waiter.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn semaphore_acquire_many() {
+    let semaphore = Semaphore::new(5);
+
+    let permit = semaphore.try_acquire_many(3).unwrap();
+    assert_eq!(permit.num_permits(), 3);
+    assert!(semaphore.try_acquire_many(3).unwrap_err().is_no_permits());
+
+    let mut waiter = semaphore.acquire_many(4);
+    assert!(waiter.poll_unpin(&mut noop_context()).is_pending());
+    drop(permit);
+    match waiter.poll_unpin(&mut panic_context()) {
+        Poll::Ready(Ok(permit)) => assert_eq!(permit.num_permits(), 4),
+        _ => panic!("permits not acquired"),
+    }
+    assert_eq!(semaphore.available_permits(), 5);
+}
+
+#[test]
+fn semaphore_is_fifo() {
+    let semaphore = Semaphore::new(2);
+    let (waker, counter) = new_count_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let permit = semaphore.try_acquire().unwrap();
+    let mut big = semaphore.acquire_many(2);
+    assert!(big.poll_unpin(&mut cx).is_pending());
+
+    // One permit is available, but the task waiting for two goes first.
+    let mut small = semaphore.acquire();
+    assert!(small.poll_unpin(&mut cx).is_pending());
+    assert!(semaphore.try_acquire().is_err());
+
+    drop(permit);
+    assert_eq!(counter, 1);
+    let big = match big.poll_unpin(&mut panic_context()) {
+        Poll::Ready(Ok(permit)) => permit,
+        _ => panic!("permits not acquired"),
+    };
+    assert!(small.poll_unpin(&mut cx).is_pending());
+
+    drop(big);
+    assert_eq!(counter, 2);
+    assert!(small.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn semaphore_dropped_waiter_lets_next_in() {
+    let semaphore = Semaphore::new(1);
+    let (waker, counter) = new_count_waker();
+
+    let mut big = semaphore.acquire_many(2);
+    assert!(big.poll_unpin(&mut panic_context()).is_pending());
+    let mut small = semaphore.acquire();
+    assert!(small.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    drop(big);
+    assert_eq!(counter, 1);
+    assert!(small.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn semaphore_dropped_waiter_gives_permits_back() {
+    let semaphore = Semaphore::new(1);
+
+    let permit = semaphore.try_acquire().unwrap();
+    let mut waiter = semaphore.acquire();
+    assert!(waiter.poll_unpin(&mut noop_context()).is_pending());
+    drop(permit);
+    assert_eq!(semaphore.available_permits(), 0);
+
+    // The waiter got the permit, but is dropped before taking it.
+    drop(waiter);
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+fn semaphore_add_permits() {
+    let semaphore = Semaphore::new(0);
+    let (waker, counter) = new_count_waker();
+
+    let mut waiter = semaphore.acquire_many(2);
+    assert!(waiter.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    semaphore.add_permits(1);
+    assert_eq!(counter, 0);
+    semaphore.add_permits(2);
+    assert_eq!(counter, 1);
+    let permit = waiter.poll_unpin(&mut panic_context());
+    assert!(permit.is_ready());
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+fn semaphore_forget() {
+    let semaphore = Semaphore::new(2);
+    semaphore.try_acquire().unwrap().forget();
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+fn semaphore_close() {
+    let semaphore = Semaphore::new(1);
+    let (waker, counter) = new_count_waker();
+
+    let permit = semaphore.try_acquire().unwrap();
+    let mut waiter = semaphore.acquire();
+    assert!(waiter.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    semaphore.close();
+    assert!(semaphore.is_closed());
+    assert_eq!(counter, 1);
+    assert!(matches!(waiter.poll_unpin(&mut panic_context()), Poll::Ready(Err(_))));
+    assert!(semaphore.try_acquire().unwrap_err().is_closed());
+    assert!(block_on(semaphore.acquire()).is_err());
+
+    drop(permit);
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+fn semaphore_owned_permits() {
+    let semaphore = Arc::new(Semaphore::new(2));
+
+    let a = semaphore.clone().try_acquire_owned().unwrap();
+    let b = block_on(semaphore.clone().acquire_many_owned(1)).unwrap();
+    assert!(Arc::ptr_eq(a.semaphore(), &semaphore));
+    assert!(semaphore.clone().try_acquire_owned().is_err());
+
+    let t = std::thread::spawn(move || drop(a));
+    t.join().unwrap();
+    assert_eq!(semaphore.available_permits(), 1);
+    drop(b);
+    assert_eq!(semaphore.available_permits(), 2);
+}
+
+#[test]
+fn semaphore_limits_concurrency() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let pool = ThreadPool::builder().pool_size(16).create().unwrap();
+
+    let tx = Arc::new(tx);
+    let semaphore = Arc::new(Semaphore::new(3));
+    let running = Arc::new(AtomicUsize::new(0));
+
+    let num_tasks = 1000;
+    for i in 0..num_tasks {
+        let tx = tx.clone();
+        let semaphore = semaphore.clone();
+        let running = running.clone();
+        pool.spawn(async move {
+            let n = i % 3 + 1;
+            let permit = semaphore.acquire_many_owned(n).await.unwrap();
+            let now = running.fetch_add(n, Ordering::SeqCst) + n;
+            assert!(now <= 3);
+            ready(()).pending_once().await;
+            running.fetch_sub(n, Ordering::SeqCst);
+            drop(permit);
+            tx.unbounded_send(()).unwrap();
+        })
+        .unwrap();
+    }
+
+    block_on(async {
+        for _ in 0..num_tasks {
+            rx.next().await.unwrap();
+        }
+    });
+    assert_eq!(semaphore.available_permits(), 3);
+}
@@ -0,0 +1,79 @@
+use futures::executor::block_on;
+use futures::future::{FusedFuture, FutureExt};
+use futures::lock::WaitGroup;
+use futures::task::Context;
+use futures_test::task::{new_count_waker, noop_context, panic_context};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn wait_group_waits_for_clones() {
+    let wg = WaitGroup::new();
+    let (waker, counter) = new_count_waker();
+
+    let a = wg.clone();
+    let b = wg.clone();
+    let mut wait = wg.wait();
+    assert!(wait.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    drop(a);
+    assert_eq!(counter, 0);
+    assert!(wait.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    drop(b);
+    assert_eq!(counter, 1);
+    assert!(wait.poll_unpin(&mut panic_context()).is_ready());
+    assert!(wait.is_terminated());
+}
+
+#[test]
+fn wait_group_alone_does_not_wait() {
+    let wg = WaitGroup::new();
+    assert!(wg.wait().poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn wait_group_several_waiters() {
+    let wg = WaitGroup::new();
+    let (waker, counter) = new_count_waker();
+
+    let worker = wg.clone();
+    let mut a = wg.clone().wait();
+    let mut b = wg.wait();
+    assert!(a.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+    assert!(b.poll_unpin(&mut Context::from_waker(&waker)).is_pending());
+
+    drop(worker);
+    assert_eq!(counter, 2);
+    assert!(a.poll_unpin(&mut panic_context()).is_ready());
+    assert!(b.poll_unpin(&mut panic_context()).is_ready());
+}
+
+#[test]
+fn dropped_wait_group_wait() {
+    let wg = WaitGroup::new();
+    let worker = wg.clone();
+
+    let mut wait = wg.wait();
+    assert!(wait.poll_unpin(&mut noop_context()).is_pending());
+    drop(wait);
+    drop(worker);
+}
+
+#[test]
+fn wait_group_across_threads() {
+    let wg = WaitGroup::new();
+    let done = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..8 {
+        let wg = wg.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            done.fetch_add(1, Ordering::SeqCst);
+            drop(wg);
+        });
+    }
+
+    block_on(wg.wait());
+    assert_eq!(done.load(Ordering::SeqCst), 8);
+}
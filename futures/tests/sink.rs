@@ -524,6 +524,7 @@ fn sink_unfold() {
         assert_eq!(unfold.as_mut().start_send(4), Ok(()));
         assert_eq!(unfold.as_mut().poll_flush(cx), Poll::Pending); // Channel full
         assert_eq!(rx.try_next().unwrap(), Some(3));
+        assert_eq!(unfold.as_mut().poll_flush(cx), Poll::Ready(Ok(())));
         assert_eq!(rx.try_next().unwrap(), Some(4));
 
         Poll::Ready(())